@@ -4,6 +4,8 @@
 // To test this server, access it at:
 //    http://localhost:3000/?start=50&end=100
 //
+// Stop it with Ctrl+C or SIGTERM, which lets the requests in flight finish.
+//
 // Created based on https://github.com/programatik29/axum-tutorial/blob/master/tutorial/04-generate-random-number.md
 
 use axum::{extract::Query, response::Html, routing::get, Router};
@@ -20,10 +22,7 @@ async fn main() {
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
     println!("Starting server at port {}", port);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    http_server_shutdown::serve(addr, app).await.unwrap();
 }
 
 #[derive(Deserialize)]
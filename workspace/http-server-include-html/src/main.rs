@@ -4,6 +4,8 @@
 // To test this server, access it at:
 //    http://localhost:3000
 //
+// Stop it with Ctrl+C or SIGTERM, which lets the requests in flight finish.
+//
 // Created based on https://github.com/programatik29/axum-tutorial/blob/master/tutorial/05-include-html.md
 
 use axum::{response::Html, routing::get, Router};
@@ -18,10 +20,7 @@ async fn main() {
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
     println!("Starting server at port {}", port);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    http_server_shutdown::serve(addr, app).await.unwrap();
 }
 
 async fn handler() -> Html<&'static str> {
@@ -0,0 +1,71 @@
+// Graceful shutdown shared by the `http-server-*` binaries.
+//
+// On Ctrl+C or SIGTERM the server stops accepting connections and lets the
+// requests in flight finish, for up to `SHUTDOWN_TIMEOUT_SECS` (30 by default).
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::Router;
+use tokio::sync::oneshot;
+
+/// Drain timeout used when `SHUTDOWN_TIMEOUT_SECS` is not set.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Completes on Ctrl+C or, on Unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Serves `app` on `addr` until [`signal`] completes, then waits for the
+/// requests in flight to finish, for at most `SHUTDOWN_TIMEOUT_SECS`.
+///
+/// Requests still running past the timeout are left behind, to be dropped
+/// along with the runtime.
+pub async fn serve(addr: SocketAddr, app: Router) -> hyper::Result<()> {
+    let timeout = std::env::var("SHUTDOWN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let timeout = Duration::from_secs(timeout);
+
+    let (signalled, on_signal) = oneshot::channel();
+    let server = axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(async move {
+            signal().await;
+            println!("Shutting down, waiting up to {timeout:?} for requests in flight");
+            let _ = signalled.send(());
+        });
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => result,
+        Ok(()) = on_signal => match tokio::time::timeout(timeout, server).await {
+            Ok(result) => result,
+            Err(_) => {
+                println!("Requests still in flight after {timeout:?}, exiting anyway");
+                Ok(())
+            }
+        },
+    }
+}
@@ -0,0 +1,11 @@
+DROP TRIGGER task_set_updated_at ON task;
+DROP FUNCTION set_updated_at();
+
+ALTER TABLE task
+  DROP COLUMN status,
+  DROP COLUMN priority,
+  DROP COLUMN due_at,
+  DROP COLUMN created_at,
+  DROP COLUMN updated_at;
+
+DROP TYPE task_status;
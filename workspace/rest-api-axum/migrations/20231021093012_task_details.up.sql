@@ -0,0 +1,19 @@
+CREATE TYPE task_status AS ENUM ('todo', 'in_progress', 'done');
+
+ALTER TABLE task
+  ADD COLUMN status task_status NOT NULL DEFAULT 'todo',
+  ADD COLUMN priority smallint NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+  ADD COLUMN due_at timestamptz,
+  ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+  ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+
+CREATE FUNCTION set_updated_at() RETURNS trigger AS $$
+BEGIN
+  NEW.updated_at = now();
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_set_updated_at
+  BEFORE UPDATE ON task
+  FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,4 @@
+DROP TRIGGER task_bump_version ON task;
+DROP FUNCTION bump_version();
+
+ALTER TABLE task DROP COLUMN version;
@@ -0,0 +1,12 @@
+ALTER TABLE task ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+CREATE FUNCTION bump_version() RETURNS trigger AS $$
+BEGIN
+  NEW.version = OLD.version + 1;
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_bump_version
+  BEFORE UPDATE ON task
+  FOR EACH ROW EXECUTE FUNCTION bump_version();
@@ -0,0 +1,4 @@
+DROP INDEX task_owner_id_idx;
+ALTER TABLE task DROP COLUMN owner_id;
+
+DROP TABLE users;
@@ -0,0 +1,11 @@
+CREATE TABLE users (
+  id  SERIAL PRIMARY KEY,
+  email varchar(255) NOT NULL UNIQUE,
+  password_hash text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- Tasks created before authentication existed have no owner, and nobody can see them.
+ALTER TABLE task ADD COLUMN owner_id integer REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX task_owner_id_idx ON task (owner_id);
@@ -0,0 +1,5 @@
+DROP TRIGGER task_record_event ON task;
+DROP FUNCTION record_task_event();
+
+DROP TABLE task_event;
+DROP TYPE task_event_kind;
@@ -0,0 +1,38 @@
+-- A log of every change to a task, written by a trigger in the same
+-- transaction, and announced on the `task_events` channel once committed.
+CREATE TYPE task_event_kind AS ENUM ('created', 'updated', 'deleted');
+
+CREATE TABLE task_event (
+  id  BIGSERIAL PRIMARY KEY,
+  kind task_event_kind NOT NULL,
+  task_id integer NOT NULL,
+  owner_id integer,
+  -- The task after the change, NULL for deletions.
+  task jsonb,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX task_event_owner_id_idx ON task_event (owner_id, id);
+CREATE INDEX task_event_created_at_idx ON task_event (created_at);
+
+CREATE FUNCTION record_task_event() RETURNS trigger AS $$
+BEGIN
+  -- Serialize writers until they commit, so that event ids are handed out in
+  -- commit order and readers resuming after an id never skip an event.
+  PERFORM pg_advisory_xact_lock(hashtext('task_event'));
+  IF TG_OP = 'DELETE' THEN
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+  ELSE
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END::task_event_kind,
+              NEW.id, NEW.owner_id, to_jsonb(NEW));
+  END IF;
+  -- Identical notifications of a transaction are sent only once.
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_record_event
+  AFTER INSERT OR UPDATE OR DELETE ON task
+  FOR EACH ROW EXECUTE FUNCTION record_task_event();
@@ -0,0 +1,22 @@
+CREATE OR REPLACE FUNCTION record_task_event() RETURNS trigger AS $$
+BEGIN
+  -- Serialize writers until they commit, so that event ids are handed out in
+  -- commit order and readers resuming after an id never skip an event.
+  PERFORM pg_advisory_xact_lock(hashtext('task_event'));
+  IF TG_OP = 'DELETE' THEN
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+  ELSE
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END::task_event_kind,
+              NEW.id, NEW.owner_id, to_jsonb(NEW));
+  END IF;
+  -- Identical notifications of a transaction are sent only once.
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX task_search_vector_idx;
+DROP TRIGGER task_set_search_vector ON task;
+DROP FUNCTION set_search_vector();
+ALTER TABLE task DROP COLUMN search_vector;
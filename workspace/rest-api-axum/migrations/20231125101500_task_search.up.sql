@@ -0,0 +1,39 @@
+-- Full-text search over the text of tasks, for `GET /tasks/search`.
+ALTER TABLE task ADD COLUMN search_vector tsvector;
+
+-- Tasks are plain text, so hide `<` from the parser, which would otherwise
+-- skip anything looking like an HTML tag. Queries highlight the same text.
+CREATE FUNCTION set_search_vector() RETURNS trigger AS $$
+BEGIN
+  NEW.search_vector = to_tsvector('english', replace(NEW.task, '<', chr(1)));
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_set_search_vector
+  BEFORE INSERT OR UPDATE OF task ON task
+  FOR EACH ROW EXECUTE FUNCTION set_search_vector();
+
+-- Fill in existing tasks without bumping their version or recording events.
+ALTER TABLE task DISABLE TRIGGER USER;
+UPDATE task SET search_vector = to_tsvector('english', replace(task, '<', chr(1)));
+ALTER TABLE task ENABLE TRIGGER USER;
+
+ALTER TABLE task ALTER COLUMN search_vector SET NOT NULL;
+CREATE INDEX task_search_vector_idx ON task USING GIN (search_vector);
+
+-- Keep the vector out of the snapshots of task events.
+CREATE OR REPLACE FUNCTION record_task_event() RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_advisory_xact_lock(hashtext('task_event'));
+  IF TG_OP = 'DELETE' THEN
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+  ELSE
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END::task_event_kind,
+              NEW.id, NEW.owner_id, to_jsonb(NEW) - 'search_vector');
+  END IF;
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
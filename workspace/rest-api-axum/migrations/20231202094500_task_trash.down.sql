@@ -0,0 +1,20 @@
+-- Without a trash, what is in it is gone for good.
+DELETE FROM task WHERE deleted_at IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION record_task_event() RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_advisory_xact_lock(hashtext('task_event'));
+  IF TG_OP = 'DELETE' THEN
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+  ELSE
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (CASE TG_OP WHEN 'INSERT' THEN 'created' ELSE 'updated' END::task_event_kind,
+              NEW.id, NEW.owner_id, to_jsonb(NEW) - 'search_vector');
+  END IF;
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX task_deleted_at_idx;
+ALTER TABLE task DROP COLUMN deleted_at;
@@ -0,0 +1,20 @@
+-- Who changed which task, when and how, for `GET /audit`. Written by the
+-- application in the transaction of each change, as only it knows who makes
+-- the change and through which request.
+CREATE TYPE audit_action AS ENUM ('create', 'update', 'delete', 'restore', 'purge');
+
+CREATE TABLE audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  action audit_action NOT NULL,
+  task_id integer NOT NULL,
+  owner_id integer,
+  -- Absent for changes made by the server itself, like purging the trash.
+  actor_id integer,
+  request_id text,
+  before jsonb,
+  after jsonb,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX audit_log_owner_id_idx ON audit_log (owner_id, id);
+CREATE INDEX audit_log_task_id_idx ON audit_log (task_id, id);
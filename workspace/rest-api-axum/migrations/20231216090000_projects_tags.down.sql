@@ -0,0 +1,6 @@
+DROP TABLE task_tag;
+
+ALTER TABLE task DROP COLUMN parent_id, DROP COLUMN project_id;
+
+DROP TABLE tag;
+DROP TABLE project;
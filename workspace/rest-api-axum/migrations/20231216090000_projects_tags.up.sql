@@ -0,0 +1,35 @@
+-- Tasks can be grouped in projects, labelled with tags and split into
+-- subtasks. Deleting a project, or a task for good, takes its tasks or
+-- subtasks with it. The application deletes them itself first, to record
+-- them in the audit log, so the cascades only catch what it would miss.
+CREATE TABLE project (
+  id  SERIAL PRIMARY KEY,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name varchar(255) NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (owner_id, name)
+);
+
+CREATE TABLE tag (
+  id  SERIAL PRIMARY KEY,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name varchar(64) NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (owner_id, name)
+);
+
+ALTER TABLE task
+  ADD COLUMN project_id integer REFERENCES project (id) ON DELETE CASCADE,
+  ADD COLUMN parent_id integer REFERENCES task (id) ON DELETE CASCADE;
+
+CREATE INDEX task_project_id_idx ON task (project_id);
+CREATE INDEX task_parent_id_idx ON task (parent_id);
+
+-- Deleting a tag only takes it off its tasks.
+CREATE TABLE task_tag (
+  task_id integer NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+  tag_id integer NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+  PRIMARY KEY (task_id, tag_id)
+);
+
+CREATE INDEX task_tag_tag_id_idx ON task_tag (tag_id);
@@ -0,0 +1,10 @@
+-- Token buckets of the rate limiter, when instances share them through the
+-- database. A missing bucket is a full one, so rows of clients that have
+-- been quiet for long enough are pruned.
+CREATE TABLE rate_limit_bucket (
+  key text PRIMARY KEY,
+  tokens double precision NOT NULL,
+  updated_at timestamptz NOT NULL
+);
+
+CREATE INDEX rate_limit_bucket_updated_at_idx ON rate_limit_bucket (updated_at);
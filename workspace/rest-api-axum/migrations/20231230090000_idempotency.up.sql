@@ -0,0 +1,16 @@
+-- Responses to the requests made with an `Idempotency-Key`, replayed when
+-- clients retry them. A key without a status is held by a request still in
+-- progress, until `locked_until`.
+CREATE TABLE idempotency_key (
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  key text NOT NULL,
+  fingerprint text NOT NULL,
+  status smallint,
+  content_type text,
+  body bytea,
+  locked_until timestamptz NOT NULL,
+  expires_at timestamptz NOT NULL,
+  PRIMARY KEY (owner_id, key)
+);
+
+CREATE INDEX idempotency_key_expires_at_idx ON idempotency_key (expires_at);
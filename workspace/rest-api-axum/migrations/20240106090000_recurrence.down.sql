@@ -0,0 +1,4 @@
+DROP TABLE task_occurrence;
+
+DROP INDEX task_recurring_done_idx;
+ALTER TABLE task DROP COLUMN recurrence;
@@ -0,0 +1,17 @@
+-- Tasks can recur, following an RRULE. Once a recurring task is done, the
+-- scheduler creates its next occurrence and records it here, in the same
+-- transaction. The primary key is what keeps a task from recurring twice,
+-- even if it is reopened and done again or its occurrence is deleted.
+ALTER TABLE task ADD COLUMN recurrence text;
+
+CREATE TABLE task_occurrence (
+  task_id integer PRIMARY KEY REFERENCES task (id) ON DELETE CASCADE,
+  -- NULL once the occurrence is purged, or if the rule had none left.
+  next_id integer REFERENCES task (id) ON DELETE SET NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX task_next_id_idx ON task_occurrence (next_id);
+-- The candidates of the scheduler.
+CREATE INDEX task_recurring_done_idx ON task (id)
+  WHERE recurrence IS NOT NULL AND status = 'done' AND deleted_at IS NULL;
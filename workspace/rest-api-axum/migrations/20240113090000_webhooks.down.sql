@@ -0,0 +1,5 @@
+DROP TRIGGER task_event_enqueue_webhooks ON task_event;
+DROP FUNCTION enqueue_webhook_deliveries();
+DROP TABLE webhook_delivery;
+DROP TYPE webhook_delivery_status;
+DROP TABLE webhook;
@@ -0,0 +1,52 @@
+-- Webhooks the events of the tasks of their owner are POSTed to, and the
+-- outbox of their deliveries. A trigger adds a delivery for every webhook
+-- wanting an event in the same transaction as the event, so that none is
+-- missed and none is sent for a change rolled back. Deliveries keep a copy
+-- of their event, which may be pruned before they are sent.
+CREATE TABLE webhook (
+  id  SERIAL PRIMARY KEY,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  url text NOT NULL,
+  -- The kinds of events sent, as an array of strings.
+  events jsonb NOT NULL,
+  secret text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhook_owner_id_idx ON webhook (owner_id);
+
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'delivered', 'dead');
+
+CREATE TABLE webhook_delivery (
+  id  BIGSERIAL PRIMARY KEY,
+  webhook_id integer NOT NULL REFERENCES webhook (id) ON DELETE CASCADE,
+  event_id bigint NOT NULL,
+  kind task_event_kind NOT NULL,
+  task_id integer NOT NULL,
+  task jsonb,
+  status webhook_delivery_status NOT NULL DEFAULT 'pending',
+  attempts integer NOT NULL DEFAULT 0,
+  next_attempt_at timestamptz NOT NULL DEFAULT now(),
+  response_status integer,
+  last_error text,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  delivered_at timestamptz
+);
+
+CREATE INDEX webhook_delivery_webhook_id_idx ON webhook_delivery (webhook_id, id);
+CREATE INDEX webhook_delivery_due_idx ON webhook_delivery (next_attempt_at)
+  WHERE status = 'pending';
+CREATE INDEX webhook_delivery_created_at_idx ON webhook_delivery (created_at);
+
+CREATE FUNCTION enqueue_webhook_deliveries() RETURNS trigger AS $$
+BEGIN
+  INSERT INTO webhook_delivery (webhook_id, event_id, kind, task_id, task, next_attempt_at, created_at)
+    SELECT id, NEW.id, NEW.kind, NEW.task_id, NEW.task, NEW.created_at, NEW.created_at
+    FROM webhook WHERE owner_id = NEW.owner_id AND events ? NEW.kind::text;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER task_event_enqueue_webhooks
+  AFTER INSERT ON task_event
+  FOR EACH ROW EXECUTE FUNCTION enqueue_webhook_deliveries();
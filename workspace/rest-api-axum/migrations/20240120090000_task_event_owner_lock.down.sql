@@ -0,0 +1,32 @@
+CREATE OR REPLACE FUNCTION record_task_event() RETURNS trigger AS $$
+DECLARE
+  event_kind task_event_kind;
+BEGIN
+  IF TG_OP = 'DELETE' AND OLD.deleted_at IS NOT NULL THEN
+    RETURN NULL;
+  END IF;
+  event_kind = CASE
+    WHEN TG_OP = 'INSERT' THEN 'created'
+    WHEN TG_OP = 'DELETE' THEN 'deleted'
+    WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'deleted'
+    WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'created'
+    ELSE 'updated'
+  END;
+
+  -- Serialize writers until they commit, so that event ids are handed out in
+  -- commit order and readers resuming after an id never skip an event.
+  PERFORM pg_advisory_xact_lock(hashtext('task_event'));
+  IF event_kind = 'deleted' THEN
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES (event_kind, OLD.id, OLD.owner_id);
+  ELSE
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (event_kind, NEW.id, NEW.owner_id, to_jsonb(NEW) - 'search_vector');
+  END IF;
+  -- Identical notifications of a transaction are sent only once.
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX task_event_xid_idx;
+ALTER TABLE task_event DROP COLUMN xid;
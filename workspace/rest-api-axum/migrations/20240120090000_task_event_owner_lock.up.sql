@@ -0,0 +1,39 @@
+-- Writers of events only wait for those changing the tasks of the same user,
+-- instead of every other one. Event ids are still handed out in commit order
+-- among the events of a user, so that clients resuming their feed after an
+-- id never skip an event. The reader of the events of everybody remembers the
+-- transactions still in flight as it moves past ids, and reads their events
+-- once committed, by the `xid` of the transaction recording them.
+ALTER TABLE task_event ADD COLUMN xid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+CREATE INDEX task_event_xid_idx ON task_event (xid);
+
+CREATE OR REPLACE FUNCTION record_task_event() RETURNS trigger AS $$
+DECLARE
+  event_kind task_event_kind;
+BEGIN
+  IF TG_OP = 'DELETE' AND OLD.deleted_at IS NOT NULL THEN
+    RETURN NULL;
+  END IF;
+  event_kind = CASE
+    WHEN TG_OP = 'INSERT' THEN 'created'
+    WHEN TG_OP = 'DELETE' THEN 'deleted'
+    WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL THEN 'deleted'
+    WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL THEN 'created'
+    ELSE 'updated'
+  END;
+
+  -- Serialize the writers of the events of the same user until they commit.
+  IF event_kind = 'deleted' THEN
+    PERFORM pg_advisory_xact_lock(hashtext('task_event'), COALESCE(OLD.owner_id, 0));
+    INSERT INTO task_event (kind, task_id, owner_id) VALUES (event_kind, OLD.id, OLD.owner_id);
+  ELSE
+    PERFORM pg_advisory_xact_lock(hashtext('task_event'), COALESCE(NEW.owner_id, 0));
+    INSERT INTO task_event (kind, task_id, owner_id, task)
+      VALUES (event_kind, NEW.id, NEW.owner_id, to_jsonb(NEW) - 'search_vector');
+  END IF;
+  -- Identical notifications of a transaction are sent only once.
+  PERFORM pg_notify('task_events', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,13 @@
+-- The SQLite schema mirrors the Postgres one in `migrations/`. Timestamps are
+-- RFC 3339 text and, lacking triggers, `updated_at` and `version` are
+-- maintained by the queries.
+CREATE TABLE task (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  task text NOT NULL CHECK (length(task) <= 255),
+  status text NOT NULL DEFAULT 'todo' CHECK (status IN ('todo', 'in_progress', 'done')),
+  priority integer NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+  due_at text,
+  created_at text NOT NULL,
+  updated_at text NOT NULL,
+  version integer NOT NULL DEFAULT 1
+);
@@ -0,0 +1,19 @@
+-- SQLite cannot drop a column that references another table, so rebuild `task` without it.
+DROP INDEX task_owner_id_idx;
+
+CREATE TABLE task_without_owner (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  task text NOT NULL CHECK (length(task) <= 255),
+  status text NOT NULL DEFAULT 'todo' CHECK (status IN ('todo', 'in_progress', 'done')),
+  priority integer NOT NULL DEFAULT 0 CHECK (priority BETWEEN 0 AND 3),
+  due_at text,
+  created_at text NOT NULL,
+  updated_at text NOT NULL,
+  version integer NOT NULL DEFAULT 1
+);
+INSERT INTO task_without_owner
+  SELECT id, task, status, priority, due_at, created_at, updated_at, version FROM task;
+DROP TABLE task;
+ALTER TABLE task_without_owner RENAME TO task;
+
+DROP TABLE users;
@@ -0,0 +1,10 @@
+CREATE TABLE users (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  email text NOT NULL UNIQUE,
+  password_hash text NOT NULL,
+  created_at text NOT NULL
+);
+
+ALTER TABLE task ADD COLUMN owner_id integer REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX task_owner_id_idx ON task (owner_id);
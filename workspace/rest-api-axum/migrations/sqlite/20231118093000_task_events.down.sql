@@ -0,0 +1,5 @@
+DROP TRIGGER task_record_deleted;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_created;
+
+DROP TABLE task_event;
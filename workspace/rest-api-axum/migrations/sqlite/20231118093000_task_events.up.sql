@@ -0,0 +1,31 @@
+-- See the Postgres migration. SQLite has a single writer at a time, so event
+-- ids follow the commit order without any locking.
+CREATE TABLE task_event (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  kind text NOT NULL CHECK (kind IN ('created', 'updated', 'deleted')),
+  task_id integer NOT NULL,
+  owner_id integer,
+  task text,
+  created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX task_event_owner_id_idx ON task_event (owner_id, id);
+CREATE INDEX task_event_created_at_idx ON task_event (created_at);
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id));
+END;
+
+CREATE TRIGGER task_record_deleted AFTER DELETE ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+END;
@@ -0,0 +1,28 @@
+DELETE FROM task WHERE deleted_at IS NOT NULL;
+
+DROP TRIGGER task_record_created;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_restored;
+DROP TRIGGER task_record_trashed;
+DROP TRIGGER task_record_deleted;
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id));
+END;
+
+CREATE TRIGGER task_record_deleted AFTER DELETE ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+END;
+
+DROP INDEX task_deleted_at_idx;
+ALTER TABLE task DROP COLUMN deleted_at;
@@ -0,0 +1,43 @@
+-- See the Postgres migration.
+ALTER TABLE task ADD COLUMN deleted_at text;
+
+CREATE INDEX task_deleted_at_idx ON task (deleted_at) WHERE deleted_at IS NOT NULL;
+
+DROP TRIGGER task_record_created;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_deleted;
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER task_record_restored AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER task_record_trashed AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+END;
+
+CREATE TRIGGER task_record_deleted AFTER DELETE ON task WHEN OLD.deleted_at IS NULL BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id) VALUES ('deleted', OLD.id, OLD.owner_id);
+END;
@@ -0,0 +1,15 @@
+-- See the Postgres migration.
+CREATE TABLE audit_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  action text NOT NULL CHECK (action IN ('create', 'update', 'delete', 'restore', 'purge')),
+  task_id integer NOT NULL,
+  owner_id integer,
+  actor_id integer,
+  request_id text,
+  before text,
+  after text,
+  created_at text NOT NULL
+);
+
+CREATE INDEX audit_log_owner_id_idx ON audit_log (owner_id, id);
+CREATE INDEX audit_log_task_id_idx ON audit_log (task_id, id);
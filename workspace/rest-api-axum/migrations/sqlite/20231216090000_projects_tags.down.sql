@@ -0,0 +1,38 @@
+DROP TRIGGER task_record_created;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_restored;
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+CREATE TRIGGER task_record_restored AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at));
+END;
+
+DROP TABLE task_tag;
+
+DROP INDEX task_parent_id_idx;
+DROP INDEX task_project_id_idx;
+ALTER TABLE task DROP COLUMN parent_id;
+ALTER TABLE task DROP COLUMN project_id;
+
+DROP TABLE tag;
+DROP TABLE project;
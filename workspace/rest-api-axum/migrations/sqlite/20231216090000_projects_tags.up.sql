@@ -0,0 +1,62 @@
+-- See the Postgres migration.
+CREATE TABLE project (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name text NOT NULL CHECK (length(name) <= 255),
+  created_at text NOT NULL,
+  UNIQUE (owner_id, name)
+);
+
+CREATE TABLE tag (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  name text NOT NULL CHECK (length(name) <= 64),
+  created_at text NOT NULL,
+  UNIQUE (owner_id, name)
+);
+
+ALTER TABLE task ADD COLUMN project_id integer REFERENCES project (id) ON DELETE CASCADE;
+ALTER TABLE task ADD COLUMN parent_id integer REFERENCES task (id) ON DELETE CASCADE;
+
+CREATE INDEX task_project_id_idx ON task (project_id);
+CREATE INDEX task_parent_id_idx ON task (parent_id);
+
+CREATE TABLE task_tag (
+  task_id integer NOT NULL REFERENCES task (id) ON DELETE CASCADE,
+  tag_id integer NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+  PRIMARY KEY (task_id, tag_id)
+);
+
+CREATE INDEX task_tag_tag_id_idx ON task_tag (tag_id);
+
+DROP TRIGGER task_record_created;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_restored;
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id));
+END;
+
+CREATE TRIGGER task_record_restored AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id));
+END;
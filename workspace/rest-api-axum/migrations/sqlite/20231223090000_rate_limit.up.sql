@@ -0,0 +1,8 @@
+-- See the Postgres migration.
+CREATE TABLE rate_limit_bucket (
+  key text PRIMARY KEY,
+  tokens real NOT NULL,
+  updated_at text NOT NULL
+);
+
+CREATE INDEX rate_limit_bucket_updated_at_idx ON rate_limit_bucket (updated_at);
@@ -0,0 +1,14 @@
+-- See the Postgres migration.
+CREATE TABLE idempotency_key (
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  key text NOT NULL,
+  fingerprint text NOT NULL,
+  status integer,
+  content_type text,
+  body blob,
+  locked_until text NOT NULL,
+  expires_at text NOT NULL,
+  PRIMARY KEY (owner_id, key)
+);
+
+CREATE INDEX idempotency_key_expires_at_idx ON idempotency_key (expires_at);
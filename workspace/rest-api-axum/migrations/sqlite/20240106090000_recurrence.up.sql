@@ -0,0 +1,47 @@
+-- See the Postgres migration.
+ALTER TABLE task ADD COLUMN recurrence text;
+
+CREATE TABLE task_occurrence (
+  task_id integer PRIMARY KEY REFERENCES task (id) ON DELETE CASCADE,
+  next_id integer REFERENCES task (id) ON DELETE SET NULL,
+  created_at text NOT NULL
+);
+
+CREATE INDEX task_next_id_idx ON task_occurrence (next_id);
+CREATE INDEX task_recurring_done_idx ON task (id)
+  WHERE recurrence IS NOT NULL AND status = 'done' AND deleted_at IS NULL;
+
+DROP TRIGGER task_record_created;
+DROP TRIGGER task_record_updated;
+DROP TRIGGER task_record_restored;
+
+CREATE TRIGGER task_record_created AFTER INSERT ON task BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id,
+                'recurrence', NEW.recurrence));
+END;
+
+CREATE TRIGGER task_record_updated AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('updated', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id,
+                'recurrence', NEW.recurrence));
+END;
+
+CREATE TRIGGER task_record_restored AFTER UPDATE ON task
+  WHEN OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL
+BEGIN
+  INSERT INTO task_event (kind, task_id, owner_id, task) VALUES ('created', NEW.id, NEW.owner_id,
+    json_object('id', NEW.id, 'task', NEW.task, 'status', NEW.status, 'priority', NEW.priority,
+                'due_at', NEW.due_at, 'created_at', NEW.created_at, 'updated_at', NEW.updated_at,
+                'version', NEW.version, 'owner_id', NEW.owner_id, 'deleted_at', NEW.deleted_at,
+                'project_id', NEW.project_id, 'parent_id', NEW.parent_id,
+                'recurrence', NEW.recurrence));
+END;
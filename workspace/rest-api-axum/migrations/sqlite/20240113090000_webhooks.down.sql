@@ -0,0 +1,3 @@
+DROP TRIGGER task_event_enqueue_webhooks;
+DROP TABLE webhook_delivery;
+DROP TABLE webhook;
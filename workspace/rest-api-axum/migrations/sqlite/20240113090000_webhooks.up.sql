@@ -0,0 +1,39 @@
+-- See the Postgres migration.
+CREATE TABLE webhook (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  owner_id integer NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  url text NOT NULL,
+  events text NOT NULL,
+  secret text NOT NULL,
+  created_at text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX webhook_owner_id_idx ON webhook (owner_id);
+
+CREATE TABLE webhook_delivery (
+  id  INTEGER PRIMARY KEY AUTOINCREMENT,
+  webhook_id integer NOT NULL REFERENCES webhook (id) ON DELETE CASCADE,
+  event_id integer NOT NULL,
+  kind text NOT NULL,
+  task_id integer NOT NULL,
+  task text,
+  status text NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'dead')),
+  attempts integer NOT NULL DEFAULT 0,
+  next_attempt_at text NOT NULL,
+  response_status integer,
+  last_error text,
+  created_at text NOT NULL,
+  delivered_at text
+);
+
+CREATE INDEX webhook_delivery_webhook_id_idx ON webhook_delivery (webhook_id, id);
+-- Times are compared as dates, which an index on them would not help with.
+CREATE INDEX webhook_delivery_status_idx ON webhook_delivery (status);
+CREATE INDEX webhook_delivery_created_at_idx ON webhook_delivery (created_at);
+
+CREATE TRIGGER task_event_enqueue_webhooks AFTER INSERT ON task_event BEGIN
+  INSERT INTO webhook_delivery (webhook_id, event_id, kind, task_id, task, next_attempt_at, created_at)
+    SELECT id, NEW.id, NEW.kind, NEW.task_id, NEW.task, NEW.created_at, NEW.created_at
+    FROM webhook WHERE owner_id = NEW.owner_id
+      AND EXISTS (SELECT 1 FROM json_each(webhook.events) WHERE value = NEW.kind);
+END;
@@ -0,0 +1,254 @@
+// Authentication with JSON Web Tokens.
+//
+// `POST /auth/login` hands out a short-lived access token and a longer-lived
+// refresh token, both HS256 JWTs signed with `JWT_SECRET`. Requests prove who
+// they are with `Authorization: Bearer <access token>`, which the
+// [`CurrentUser`] extractor checks.
+
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Query},
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use chrono::Utc;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tower_http::request_id::RequestId;
+
+use crate::config::Config;
+use crate::errors::CustomError;
+use crate::models::audit::Actor;
+use crate::models::user::TokenResponse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Claims {
+    /// Id of the user, as a string like the spec requires.
+    sub: String,
+    iat: i64,
+    exp: i64,
+    /// Keeps a refresh token from being used as an access token, and the other way round.
+    typ: TokenType,
+}
+
+/// Signs and checks tokens.
+pub struct Keys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    validation: Validation,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
+}
+
+impl Keys {
+    pub fn new(config: &Config) -> Self {
+        let secret = config.jwt_secret.as_bytes();
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            validation: Validation::new(Algorithm::HS256),
+            access_token_ttl: config.access_token_ttl,
+            refresh_token_ttl: config.refresh_token_ttl,
+        }
+    }
+
+    /// A fresh pair of tokens for the user `user_id`.
+    pub fn issue(&self, user_id: i32) -> Result<TokenResponse, CustomError> {
+        Ok(TokenResponse {
+            access_token: self.sign(user_id, TokenType::Access, self.access_token_ttl)?,
+            refresh_token: self.sign(user_id, TokenType::Refresh, self.refresh_token_ttl)?,
+            token_type: "Bearer",
+            expires_in: self.access_token_ttl.as_secs(),
+        })
+    }
+
+    /// Checks the signature, expiry and type of `token`, returning the id of its user.
+    pub fn verify(&self, token: &str, typ: TokenType) -> Result<i32, CustomError> {
+        let claims = jsonwebtoken::decode::<Claims>(token, &self.decoding, &self.validation)
+            .map_err(|err| match err.kind() {
+                ErrorKind::ExpiredSignature => {
+                    CustomError::Unauthorized("token expired".to_owned())
+                }
+                _ => CustomError::Unauthorized("invalid token".to_owned()),
+            })?
+            .claims;
+        if claims.typ != typ {
+            return Err(CustomError::Unauthorized("invalid token".to_owned()));
+        }
+        claims
+            .sub
+            .parse()
+            .map_err(|_| CustomError::Unauthorized("invalid token".to_owned()))
+    }
+
+    fn sign(&self, user_id: i32, typ: TokenType, ttl: Duration) -> Result<String, CustomError> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            iat: now,
+            exp: now + ttl.as_secs() as i64,
+            typ,
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .map_err(|err| CustomError::InternalServerError(err.to_string()))
+    }
+}
+
+/// Hashes a password with Argon2, off the async runtime as it is slow on purpose.
+pub async fn hash_password(password: String) -> Result<String, CustomError> {
+    blocking(move || hash(&password)).await?
+}
+
+/// Checks `password` against `hash`. Without a hash, e.g. for an unknown
+/// email, still spends the time of a check so that timing does not tell
+/// which emails have an account.
+pub async fn verify_password(password: String, hash: Option<String>) -> Result<bool, CustomError> {
+    blocking(move || {
+        let Some(hash) = hash else {
+            static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+            let dummy = DUMMY_HASH.get_or_init(|| self::hash("dummy password").unwrap_or_default());
+            let _ = verify(&password, dummy);
+            return Ok(false);
+        };
+        verify(&password, &hash)
+    })
+    .await?
+}
+
+fn hash(password: &str) -> Result<String, CustomError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| CustomError::InternalServerError(err.to_string()))
+}
+
+fn verify(password: &str, hash: &str) -> Result<bool, CustomError> {
+    let hash =
+        PasswordHash::new(hash).map_err(|err| CustomError::InternalServerError(err.to_string()))?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, CustomError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| CustomError::InternalServerError(err.to_string()))
+}
+
+/// The user making the request, authenticated by the access token in the
+/// `Authorization` header. Rejects the request with a 401 otherwise.
+pub struct CurrentUser {
+    pub id: i32,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    Arc<Keys>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = CustomError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let keys = Arc::<Keys>::from_ref(state);
+
+        let token = bearer_token(parts)
+            .ok_or_else(|| CustomError::Unauthorized("missing bearer token".to_owned()))?;
+
+        let id = keys.verify(token, TokenType::Access)?;
+
+        Ok(Self { id })
+    }
+}
+
+/// Like [`CurrentUser`], but also accepts the access token in an
+/// `access_token` query parameter, as browsers cannot send headers when
+/// opening an `EventSource` or a WebSocket. Reserved to those endpoints, as
+/// URLs tend to end up in logs.
+pub struct FeedUser {
+    pub id: i32,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for FeedUser
+where
+    Arc<Keys>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = CustomError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(AUTHORIZATION) {
+            let user = CurrentUser::from_request_parts(parts, state).await?;
+            return Ok(Self { id: user.id });
+        }
+
+        let keys = Arc::<Keys>::from_ref(state);
+        let token = Query::<TokenQuery>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(query)| query.access_token)
+            .ok_or_else(|| CustomError::Unauthorized("missing bearer token".to_owned()))?;
+
+        let id = keys.verify(&token, TokenType::Access)?;
+
+        Ok(Self { id })
+    }
+}
+
+/// Like [`CurrentUser`], for the handlers that change tasks, which record who
+/// made the change and through which request in the audit log.
+#[async_trait]
+impl<S> FromRequestParts<S> for Actor
+where
+    Arc<Keys>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = CustomError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = CurrentUser::from_request_parts(parts, state).await?;
+        // Set by the `SetRequestIdLayer` of the app, unless the client sent one.
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .and_then(|id| id.header_value().to_str().ok())
+            .map(str::to_owned);
+
+        Ok(Self {
+            user_id: user.id,
+            request_id,
+        })
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token.trim())
+}
@@ -0,0 +1,106 @@
+use std::ops::Deref;
+
+use anyhow::Context;
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::Pool;
+
+use super::MigrateCommand;
+use crate::db::Database;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Applies pending migrations, if the backend has any.
+pub async fn up(db: &Database) -> anyhow::Result<()> {
+    match db {
+        Database::Postgres(pool) => MIGRATOR.run(pool).await?,
+        Database::Sqlite(pool) => SQLITE_MIGRATOR.run(pool).await?,
+        Database::Memory(_) => {}
+    }
+    Ok(())
+}
+
+pub async fn run(db: &Database, command: MigrateCommand) -> anyhow::Result<()> {
+    match db {
+        Database::Postgres(pool) => run_with(&MIGRATOR, pool, command).await,
+        Database::Sqlite(pool) => run_with(&SQLITE_MIGRATOR, pool, command).await,
+        Database::Memory(_) => anyhow::bail!("the in-memory backend has no schema to migrate"),
+    }
+}
+
+async fn run_with<DB>(
+    migrator: &Migrator,
+    pool: &Pool<DB>,
+    command: MigrateCommand,
+) -> anyhow::Result<()>
+where
+    DB: sqlx::Database,
+    DB::Connection: Migrate,
+    for<'c> &'c Pool<DB>: sqlx::Acquire<'c, Database = DB>,
+    for<'c> <&'c Pool<DB> as sqlx::Acquire<'c>>::Connection: Deref<Target = DB::Connection>,
+{
+    match command {
+        MigrateCommand::Up => {
+            migrator.run(pool).await?;
+            println!("Database is up to date");
+        }
+        MigrateCommand::Down { target } => {
+            let target = match target {
+                Some(target) => target,
+                None => {
+                    let applied = applied_versions(pool).await?;
+                    // Going down one step means keeping everything but the latest.
+                    match applied.iter().rev().nth(1) {
+                        Some(version) => *version,
+                        None if applied.is_empty() => {
+                            println!("No migration to revert");
+                            return Ok(());
+                        }
+                        None => 0,
+                    }
+                }
+            };
+            migrator.undo(pool, target).await?;
+            println!("Reverted migrations after {target}");
+        }
+        MigrateCommand::Status => {
+            let mut conn = pool.acquire().await?;
+            conn.ensure_migrations_table().await?;
+            let applied = conn.list_applied_migrations().await?;
+
+            for migration in migrator
+                .iter()
+                .filter(|m| !m.migration_type.is_down_migration())
+            {
+                let status = match applied.iter().find(|a| a.version == migration.version) {
+                    Some(a) if a.checksum != migration.checksum => "applied (checksum mismatch)",
+                    Some(_) => "applied",
+                    None => "pending",
+                };
+                println!(
+                    "{} {:<20} {status}",
+                    migration.version, migration.description
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn applied_versions<DB>(pool: &Pool<DB>) -> anyhow::Result<Vec<i64>>
+where
+    DB: sqlx::Database,
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let mut versions: Vec<i64> = conn
+        .list_applied_migrations()
+        .await
+        .context("could not list applied migrations")?
+        .into_iter()
+        .map(|m| m.version)
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+pub mod migrate;
+pub mod seed;
+
+#[derive(Parser)]
+#[command(about = "A simple REST API using axum and sqlx")]
+pub struct Cli {
+    /// Defaults to `serve`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the HTTP server.
+    Serve {
+        /// Apply pending migrations before accepting requests.
+        #[arg(long)]
+        migrate: bool,
+    },
+    /// Manage the database schema.
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Insert the tasks listed in a JSON file.
+    Seed {
+        /// A JSON array of tasks, in the same shape as the body of `POST /task`.
+        file: PathBuf,
+        /// Email of the user who will own the tasks, who must already have an account.
+        #[arg(long)]
+        owner: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations.
+    Up,
+    /// Revert the latest applied migration, or every migration after `--target`.
+    Down {
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    /// List migrations and whether they have been applied.
+    Status,
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::models::audit::Actor;
+use crate::models::task;
+use crate::models::user::normalize_email;
+use crate::repository::Store;
+
+pub async fn run(store: &dyn Store, file: &Path, owner: &str) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(file)
+        .with_context(|| format!("could not read {}", file.display()))?;
+    let tasks: Vec<task::NewTask> =
+        serde_json::from_str(&contents).with_context(|| format!("invalid {}", file.display()))?;
+
+    for (i, task) in tasks.iter().enumerate() {
+        task.validate()
+            .with_context(|| format!("invalid task at index {i}"))?;
+    }
+
+    let mut conn = store.acquire().await?;
+    let email = normalize_email(owner);
+    let owner = conn
+        .find_user_by_email(&email)
+        .await?
+        .with_context(|| format!("no user with email {email}"))?;
+
+    // Either the whole file is loaded or nothing is.
+    let actor = Actor {
+        user_id: owner.id,
+        request_id: None,
+    };
+    conn.create_many(&actor, &tasks).await?;
+
+    println!("Inserted {} tasks", tasks.len());
+    Ok(())
+}
@@ -0,0 +1,621 @@
+// Typed service configuration.
+//
+// Every setting is looked up under a single key, in increasing order of precedence:
+// 1. the defaults below
+// 2. an optional TOML file (`config.toml`, or the file named by `CONFIG_FILE`),
+//    using the lowercase key, e.g. `database_max_connections = 20`
+// 3. the `.env` file
+// 4. the process environment, using the uppercase key, e.g. `DATABASE_MAX_CONNECTIONS=20`
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+
+use crate::logging::LogFormat;
+use crate::models::rate_limit::{Limit, Rule};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+const DATABASE_URL: &str = "DATABASE_URL";
+const DATABASE_MAX_CONNECTIONS: &str = "DATABASE_MAX_CONNECTIONS";
+const DATABASE_MIN_CONNECTIONS: &str = "DATABASE_MIN_CONNECTIONS";
+const DATABASE_ACQUIRE_TIMEOUT_SECS: &str = "DATABASE_ACQUIRE_TIMEOUT_SECS";
+const DATABASE_STATEMENT_TIMEOUT_SECS: &str = "DATABASE_STATEMENT_TIMEOUT_SECS";
+const DATABASE_AUTO_MIGRATE: &str = "DATABASE_AUTO_MIGRATE";
+const REQUEST_TIMEOUT_SECS: &str = "REQUEST_TIMEOUT_SECS";
+const SHUTDOWN_TIMEOUT_SECS: &str = "SHUTDOWN_TIMEOUT_SECS";
+const BIND_ADDRESS: &str = "BIND_ADDRESS";
+const LOG_FILTER: &str = "LOG_FILTER";
+const LOG_FORMAT: &str = "LOG_FORMAT";
+const OTLP_ENDPOINT: &str = "OTLP_ENDPOINT";
+const CORS_ORIGINS: &str = "CORS_ORIGINS";
+const JWT_SECRET: &str = "JWT_SECRET";
+const ACCESS_TOKEN_TTL_SECS: &str = "ACCESS_TOKEN_TTL_SECS";
+const REFRESH_TOKEN_TTL_SECS: &str = "REFRESH_TOKEN_TTL_SECS";
+const EVENTS_RETENTION_SECS: &str = "EVENTS_RETENTION_SECS";
+const TRASH_RETENTION_SECS: &str = "TRASH_RETENTION_SECS";
+const RECURRENCE_INTERVAL_SECS: &str = "RECURRENCE_INTERVAL_SECS";
+const IDEMPOTENCY_TTL_SECS: &str = "IDEMPOTENCY_TTL_SECS";
+const WEBHOOK_INTERVAL_SECS: &str = "WEBHOOK_INTERVAL_SECS";
+const WEBHOOK_TIMEOUT_SECS: &str = "WEBHOOK_TIMEOUT_SECS";
+const WEBHOOK_MAX_ATTEMPTS: &str = "WEBHOOK_MAX_ATTEMPTS";
+const WEBHOOK_BACKOFF_SECS: &str = "WEBHOOK_BACKOFF_SECS";
+const WEBHOOK_ALLOWED_HOSTS: &str = "WEBHOOK_ALLOWED_HOSTS";
+const RATE_LIMIT: &str = "RATE_LIMIT";
+const RATE_LIMIT_ROUTES: &str = "RATE_LIMIT_ROUTES";
+const RATE_LIMIT_SHARED: &str = "RATE_LIMIT_SHARED";
+
+/// HS256 keys shorter than the hash output weaken the signature.
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// Secrets published in this repository, which anyone could sign tokens with.
+/// Only accepted by debug builds, for local development and the tests.
+const PUBLIC_JWT_SECRETS: &[&str] = &[
+    DEFAULT_JWT_SECRET,
+    "change-me-to-a-long-random-string-in-production",
+];
+
+const DEFAULT_JWT_SECRET: &str = "insecure development secret, do not use in production";
+
+const KEYS: &[&str] = &[
+    DATABASE_URL,
+    DATABASE_MAX_CONNECTIONS,
+    DATABASE_MIN_CONNECTIONS,
+    DATABASE_ACQUIRE_TIMEOUT_SECS,
+    DATABASE_STATEMENT_TIMEOUT_SECS,
+    DATABASE_AUTO_MIGRATE,
+    REQUEST_TIMEOUT_SECS,
+    SHUTDOWN_TIMEOUT_SECS,
+    BIND_ADDRESS,
+    LOG_FILTER,
+    LOG_FORMAT,
+    OTLP_ENDPOINT,
+    CORS_ORIGINS,
+    JWT_SECRET,
+    ACCESS_TOKEN_TTL_SECS,
+    REFRESH_TOKEN_TTL_SECS,
+    EVENTS_RETENTION_SECS,
+    TRASH_RETENTION_SECS,
+    RECURRENCE_INTERVAL_SECS,
+    IDEMPOTENCY_TTL_SECS,
+    WEBHOOK_INTERVAL_SECS,
+    WEBHOOK_TIMEOUT_SECS,
+    WEBHOOK_MAX_ATTEMPTS,
+    WEBHOOK_BACKOFF_SECS,
+    WEBHOOK_ALLOWED_HOSTS,
+    RATE_LIMIT,
+    RATE_LIMIT_ROUTES,
+    RATE_LIMIT_SHARED,
+];
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub database_url: String,
+    pub database_max_connections: u32,
+    pub database_min_connections: u32,
+    /// How long to wait for a free connection before giving up.
+    pub database_acquire_timeout: Duration,
+    /// Server-side limit for a single statement. `None` disables it.
+    pub database_statement_timeout: Option<Duration>,
+    /// Apply pending migrations when the server starts.
+    pub database_auto_migrate: bool,
+    pub request_timeout: Duration,
+    /// How long to let in-flight requests finish once asked to shut down.
+    /// `None` waits for them however long they take.
+    pub shutdown_timeout: Option<Duration>,
+    pub bind_address: SocketAddr,
+    pub log_filter: String,
+    pub log_format: LogFormat,
+    /// The OTLP collector to export traces to, over gRPC. `None` does not
+    /// export them. Needs the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+    /// Origins allowed to make cross-origin requests. `*` allows any origin.
+    pub cors_origins: Vec<HeaderValue>,
+    /// Key used to sign the JSON Web Tokens handed out at login.
+    pub jwt_secret: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+    /// How long task events are kept for clients resuming the feed. `None`
+    /// keeps them forever.
+    pub events_retention: Option<Duration>,
+    /// How long deleted tasks stay in the trash before they are purged.
+    /// `None` keeps them forever.
+    pub trash_retention: Option<Duration>,
+    /// How often the scheduler looks for recurring tasks that are done,
+    /// besides whenever tasks change. `None` does not run it, so tasks do
+    /// not recur.
+    pub recurrence_interval: Option<Duration>,
+    /// How long the responses to requests with an `Idempotency-Key` are
+    /// kept for retries. `None` ignores the header.
+    pub idempotency_ttl: Option<Duration>,
+    /// How often the webhook worker looks for deliveries to retry, besides
+    /// whenever tasks change. `None` does not run it, so nothing is sent.
+    pub webhook_interval: Option<Duration>,
+    /// How long a webhook has to respond to a delivery.
+    pub webhook_timeout: Duration,
+    /// Attempts at a delivery before it is given up on as dead.
+    pub webhook_max_attempts: u32,
+    /// How long to wait before the first retry of a delivery, doubling with
+    /// every attempt.
+    pub webhook_backoff: Duration,
+    /// Hosts webhooks may be on even though they resolve to a loopback,
+    /// private or link-local address, lowercase.
+    pub webhook_allowed_hosts: Vec<String>,
+    /// Requests allowed to every client on the routes without a rule of
+    /// their own. `None` does not limit them.
+    pub rate_limit: Option<Limit>,
+    /// Limits of specific routes, the first matching rule winning.
+    pub rate_limit_routes: Vec<Rule>,
+    /// Keep the rate limits in the database, for all instances to share.
+    pub rate_limit_shared: bool,
+}
+
+/// What the configuration is loaded for, as not every command needs every key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Purpose {
+    /// Serving the API, which signs and checks tokens with `JWT_SECRET`.
+    Serve,
+    /// Commands only working on the database, e.g. migrations and seeding,
+    /// which ignore `JWT_SECRET`.
+    Database,
+}
+
+/// Where the configuration is read from, besides the defaults.
+pub struct Sources {
+    /// The `.env` file, which may be missing.
+    pub dotenv: PathBuf,
+    /// The environment, where `CONFIG_FILE` names the TOML file.
+    pub env: HashMap<String, OsString>,
+}
+
+impl Sources {
+    /// Those of the process: the `.env` file of the working directory and the
+    /// environment variables.
+    pub fn process() -> Self {
+        Self {
+            dotenv: PathBuf::from(".env"),
+            env: std::env::vars_os()
+                .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+                .collect(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration from all sources, reporting every invalid key at once.
+    pub fn load(purpose: Purpose) -> Result<Self, ConfigErrors> {
+        Self::load_from(purpose, &Sources::process())
+    }
+
+    /// Loads the configuration from `sources`, reporting every invalid key at once.
+    pub fn load_from(purpose: Purpose, sources: &Sources) -> Result<Self, ConfigErrors> {
+        let mut loader = Loader::default();
+
+        match sources.env.get("CONFIG_FILE") {
+            Some(path) => loader.read_toml(Path::new(path), true),
+            None => loader.read_toml(Path::new(DEFAULT_CONFIG_FILE), false),
+        }
+        loader.read_dotenv(&sources.dotenv);
+        loader.read_env(&sources.env);
+
+        let defaults = Self::default();
+        let config = Self {
+            database_url: loader.required(DATABASE_URL),
+            database_max_connections: loader
+                .parse(DATABASE_MAX_CONNECTIONS, defaults.database_max_connections),
+            database_min_connections: loader
+                .parse(DATABASE_MIN_CONNECTIONS, defaults.database_min_connections),
+            database_acquire_timeout: loader.parse_secs(
+                DATABASE_ACQUIRE_TIMEOUT_SECS,
+                defaults.database_acquire_timeout,
+            ),
+            database_statement_timeout: Some(loader.parse_secs(
+                DATABASE_STATEMENT_TIMEOUT_SECS,
+                defaults.database_statement_timeout.unwrap_or_default(),
+            ))
+            .filter(|timeout| !timeout.is_zero()),
+            database_auto_migrate: loader
+                .parse(DATABASE_AUTO_MIGRATE, defaults.database_auto_migrate),
+            request_timeout: loader.parse_secs(REQUEST_TIMEOUT_SECS, defaults.request_timeout),
+            shutdown_timeout: Some(loader.parse_secs(
+                SHUTDOWN_TIMEOUT_SECS,
+                defaults.shutdown_timeout.unwrap_or_default(),
+            ))
+            .filter(|timeout| !timeout.is_zero()),
+            bind_address: loader.parse(BIND_ADDRESS, defaults.bind_address),
+            log_filter: loader.parse(LOG_FILTER, defaults.log_filter),
+            log_format: loader.parse(LOG_FORMAT, defaults.log_format),
+            otlp_endpoint: Some(
+                loader.parse(OTLP_ENDPOINT, defaults.otlp_endpoint.unwrap_or_default()),
+            )
+            .filter(|endpoint| !endpoint.is_empty()),
+            cors_origins: loader.cors_origins(),
+            jwt_secret: match purpose {
+                Purpose::Serve => loader.required(JWT_SECRET),
+                Purpose::Database => loader.parse(JWT_SECRET, String::new()),
+            },
+            access_token_ttl: loader.parse_secs(ACCESS_TOKEN_TTL_SECS, defaults.access_token_ttl),
+            refresh_token_ttl: loader
+                .parse_secs(REFRESH_TOKEN_TTL_SECS, defaults.refresh_token_ttl),
+            events_retention: Some(loader.parse_secs(
+                EVENTS_RETENTION_SECS,
+                defaults.events_retention.unwrap_or_default(),
+            ))
+            .filter(|retention| !retention.is_zero()),
+            trash_retention: Some(loader.parse_secs(
+                TRASH_RETENTION_SECS,
+                defaults.trash_retention.unwrap_or_default(),
+            ))
+            .filter(|retention| !retention.is_zero()),
+            recurrence_interval: Some(loader.parse_secs(
+                RECURRENCE_INTERVAL_SECS,
+                defaults.recurrence_interval.unwrap_or_default(),
+            ))
+            .filter(|interval| !interval.is_zero()),
+            idempotency_ttl: Some(loader.parse_secs(
+                IDEMPOTENCY_TTL_SECS,
+                defaults.idempotency_ttl.unwrap_or_default(),
+            ))
+            .filter(|ttl| !ttl.is_zero()),
+            webhook_interval: Some(loader.parse_secs(
+                WEBHOOK_INTERVAL_SECS,
+                defaults.webhook_interval.unwrap_or_default(),
+            ))
+            .filter(|interval| !interval.is_zero()),
+            webhook_timeout: loader.parse_secs(WEBHOOK_TIMEOUT_SECS, defaults.webhook_timeout),
+            webhook_max_attempts: loader.parse(WEBHOOK_MAX_ATTEMPTS, defaults.webhook_max_attempts),
+            webhook_backoff: loader.parse_secs(WEBHOOK_BACKOFF_SECS, defaults.webhook_backoff),
+            webhook_allowed_hosts: loader.webhook_allowed_hosts(defaults.webhook_allowed_hosts),
+            rate_limit: loader.rate_limit(defaults.rate_limit),
+            rate_limit_routes: loader.rate_limit_routes(defaults.rate_limit_routes),
+            rate_limit_shared: loader.parse(RATE_LIMIT_SHARED, defaults.rate_limit_shared),
+        };
+
+        if config.database_max_connections == 0 {
+            loader.invalid(DATABASE_MAX_CONNECTIONS, "must be at least 1");
+        }
+        if config.database_min_connections > config.database_max_connections {
+            loader.invalid(
+                DATABASE_MIN_CONNECTIONS,
+                format!("must not exceed {DATABASE_MAX_CONNECTIONS}"),
+            );
+        }
+        if config.request_timeout.is_zero() {
+            loader.invalid(REQUEST_TIMEOUT_SECS, "must be at least 1");
+        }
+        if config.webhook_timeout.is_zero() {
+            loader.invalid(WEBHOOK_TIMEOUT_SECS, "must be at least 1");
+        }
+        if config.webhook_max_attempts == 0 {
+            loader.invalid(WEBHOOK_MAX_ATTEMPTS, "must be at least 1");
+        }
+        if purpose == Purpose::Serve {
+            if !config.jwt_secret.is_empty() && config.jwt_secret.len() < MIN_JWT_SECRET_LEN {
+                loader.invalid(
+                    JWT_SECRET,
+                    format!("must be at least {MIN_JWT_SECRET_LEN} bytes long"),
+                );
+            }
+            if !cfg!(debug_assertions) && PUBLIC_JWT_SECRETS.contains(&config.jwt_secret.as_str()) {
+                loader.invalid(
+                    JWT_SECRET,
+                    "is a published example, generate a random one (e.g. `openssl rand -hex 32`)",
+                );
+            }
+        }
+        if config.access_token_ttl.is_zero() {
+            loader.invalid(ACCESS_TOKEN_TTL_SECS, "must be at least 1");
+        }
+        if config.refresh_token_ttl <= config.access_token_ttl {
+            loader.invalid(
+                REFRESH_TOKEN_TTL_SECS,
+                format!("must be longer than {ACCESS_TOKEN_TTL_SECS}"),
+            );
+        }
+        if let Err(err) = tracing_subscriber::EnvFilter::try_new(&config.log_filter) {
+            loader.invalid(LOG_FILTER, err.to_string());
+        }
+        if cfg!(not(feature = "otel")) && config.otlp_endpoint.is_some() {
+            loader.invalid(OTLP_ENDPOINT, "needs a build with the otel feature");
+        }
+
+        if loader.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors(loader.errors))
+        }
+    }
+}
+
+impl Default for Config {
+    /// The defaults of every key, using the in-memory backend as there is no
+    /// sensible default database. `DATABASE_URL` and, to serve, `JWT_SECRET` must
+    /// still be set explicitly when loading, and release builds refuse the default
+    /// secret.
+    fn default() -> Self {
+        Self {
+            database_url: "memory:".to_owned(),
+            database_max_connections: 50,
+            database_min_connections: 0,
+            database_acquire_timeout: Duration::from_secs(5),
+            database_statement_timeout: Some(Duration::from_secs(10)),
+            database_auto_migrate: false,
+            request_timeout: Duration::from_secs(30),
+            shutdown_timeout: Some(Duration::from_secs(30)),
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            log_filter: "rest_api_axum=debug,tower_http=debug".to_owned(),
+            log_format: LogFormat::Text,
+            otlp_endpoint: None,
+            cors_origins: Vec::new(),
+            jwt_secret: DEFAULT_JWT_SECRET.to_owned(),
+            access_token_ttl: Duration::from_secs(15 * 60),
+            refresh_token_ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            events_retention: Some(Duration::from_secs(24 * 60 * 60)),
+            trash_retention: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            recurrence_interval: Some(Duration::from_secs(60)),
+            idempotency_ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            webhook_interval: Some(Duration::from_secs(5)),
+            webhook_timeout: Duration::from_secs(10),
+            // The last one about an hour after the event, with the default backoff.
+            webhook_max_attempts: 8,
+            webhook_backoff: Duration::from_secs(30),
+            webhook_allowed_hosts: Vec::new(),
+            rate_limit: Some(Limit {
+                requests: 600,
+                period: Duration::from_secs(60),
+            }),
+            // Logging in and registering hash passwords, which is slow on purpose.
+            rate_limit_routes: ["POST /auth/login=10/60", "POST /auth/register=10/60"]
+                .iter()
+                .map(|rule| rule.parse().unwrap())
+                .collect(),
+            rate_limit_shared: false,
+        }
+    }
+}
+
+/// Where the value of a key came from, to point at the right place in error reports.
+#[derive(Clone, Debug)]
+pub enum Source {
+    File(PathBuf),
+    DotEnv,
+    Env,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::DotEnv => f.write_str(".env"),
+            Self::Env => f.write_str("environment"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub key: String,
+    pub source: Option<Source>,
+    pub message: String,
+}
+
+/// Every problem found while loading the configuration.
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration ({} errors):", self.0.len())?;
+        for error in &self.0 {
+            match &error.source {
+                Some(source) => write!(f, "\n  {} (from {source}): {}", error.key, error.message)?,
+                None => write!(f, "\n  {}: {}", error.key, error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+#[derive(Default)]
+struct Loader {
+    values: HashMap<&'static str, (String, Source)>,
+    errors: Vec<ConfigError>,
+}
+
+impl Loader {
+    fn read_toml(&mut self, path: &Path, required: bool) {
+        let source = Source::File(path.to_owned());
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => return,
+            Err(err) => return self.error("CONFIG_FILE", Some(source), err.to_string()),
+        };
+        let table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(err) => return self.error("CONFIG_FILE", Some(source), err.to_string()),
+        };
+
+        for (name, value) in table {
+            let Some(key) = KEYS.iter().find(|key| key.eq_ignore_ascii_case(&name)) else {
+                self.error(&name, Some(source.clone()), "unknown key".to_owned());
+                continue;
+            };
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                // Lists, e.g. `cors_origins`, are comma separated elsewhere.
+                toml::Value::Array(values) => {
+                    let values: Option<Vec<_>> = values.iter().map(|v| v.as_str()).collect();
+                    match values {
+                        Some(values) => values.join(","),
+                        None => {
+                            self.error(
+                                key,
+                                Some(source.clone()),
+                                "expected a list of strings".to_owned(),
+                            );
+                            continue;
+                        }
+                    }
+                }
+                other => {
+                    self.error(
+                        key,
+                        Some(source.clone()),
+                        format!("unexpected {}", other.type_str()),
+                    );
+                    continue;
+                }
+            };
+            self.values.insert(key, (value, source.clone()));
+        }
+    }
+
+    fn read_dotenv(&mut self, path: &Path) {
+        let iter = match dotenvy::from_path_iter(path) {
+            Ok(iter) => iter,
+            Err(err) if err.not_found() => return,
+            Err(err) => return self.error(".env", None, err.to_string()),
+        };
+        for item in iter {
+            match item {
+                Ok((name, value)) => {
+                    // `.env` is shared with other tools (e.g. sqlx-cli), so other keys are fine.
+                    if let Some(key) = KEYS.iter().find(|key| **key == name) {
+                        self.values.insert(key, (value, Source::DotEnv));
+                    }
+                }
+                Err(err) => self.error(".env", None, err.to_string()),
+            }
+        }
+    }
+
+    fn read_env(&mut self, env: &HashMap<String, OsString>) {
+        for key in KEYS {
+            match env.get(*key).cloned().map(OsString::into_string) {
+                Some(Ok(value)) => {
+                    self.values.insert(key, (value, Source::Env));
+                }
+                None => {}
+                Some(Err(_)) => self.error(key, Some(Source::Env), "not valid unicode".to_owned()),
+            }
+        }
+    }
+
+    fn required(&mut self, key: &'static str) -> String {
+        match self.values.get(key) {
+            Some((value, _)) => value.clone(),
+            None => {
+                self.error(key, None, "missing".to_owned());
+                String::new()
+            }
+        }
+    }
+
+    fn parse<T>(&mut self, key: &'static str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let Some((value, source)) = self.values.get(key) else {
+            return default;
+        };
+        match value.trim().parse() {
+            Ok(value) => value,
+            Err(err) => {
+                let message = format!("invalid value {value:?}: {err}");
+                self.error(key, Some(source.clone()), message);
+                default
+            }
+        }
+    }
+
+    fn parse_secs(&mut self, key: &'static str, default: Duration) -> Duration {
+        Duration::from_secs(self.parse(key, default.as_secs()))
+    }
+
+    fn cors_origins(&mut self) -> Vec<HeaderValue> {
+        let Some((value, source)) = self.values.get(CORS_ORIGINS).cloned() else {
+            return Vec::new();
+        };
+        let mut origins = Vec::new();
+        for origin in value.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let valid =
+                origin == "*" || origin.starts_with("http://") || origin.starts_with("https://");
+            match HeaderValue::from_str(origin) {
+                Ok(header) if valid => origins.push(header),
+                _ => self.error(
+                    CORS_ORIGINS,
+                    Some(source.clone()),
+                    format!("invalid origin {origin:?}"),
+                ),
+            }
+        }
+        origins
+    }
+
+    fn webhook_allowed_hosts(&mut self, default: Vec<String>) -> Vec<String> {
+        let Some((value, _)) = self.values.get(WEBHOOK_ALLOWED_HOSTS) else {
+            return default;
+        };
+        value
+            .split(',')
+            .map(|host| host.trim().trim_start_matches('[').trim_end_matches(']'))
+            .filter(|host| !host.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    fn rate_limit(&mut self, default: Option<Limit>) -> Option<Limit> {
+        let Some((value, source)) = self.values.get(RATE_LIMIT).cloned() else {
+            return default;
+        };
+        match value.trim() {
+            "0" => None,
+            value => match value.parse() {
+                Ok(limit) => Some(limit),
+                Err(err) => {
+                    self.error(RATE_LIMIT, Some(source), err);
+                    default
+                }
+            },
+        }
+    }
+
+    fn rate_limit_routes(&mut self, default: Vec<Rule>) -> Vec<Rule> {
+        let Some((value, source)) = self.values.get(RATE_LIMIT_ROUTES).cloned() else {
+            return default;
+        };
+        let mut rules = Vec::new();
+        for rule in value.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            match rule.parse() {
+                Ok(rule) => rules.push(rule),
+                Err(err) => self.error(RATE_LIMIT_ROUTES, Some(source.clone()), err),
+            }
+        }
+        rules
+    }
+
+    /// Reports a value that parsed fine but does not make sense.
+    fn invalid(&mut self, key: &'static str, message: impl Into<String>) {
+        let source = self.values.get(key).map(|(_, source)| source.clone());
+        self.error(key, source, message.into());
+    }
+
+    fn error(&mut self, key: &str, source: Option<Source>, message: String) {
+        self.errors.push(ConfigError {
+            key: key.to_owned(),
+            source,
+            message,
+        });
+    }
+}
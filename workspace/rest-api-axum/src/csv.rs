@@ -0,0 +1,86 @@
+// Just enough CSV (RFC 4180) for exporting and importing tasks: fields are
+// separated by commas, and quoted when they hold a comma, a quote or a line
+// break, quotes being doubled inside quoted fields.
+
+/// `fields` as a record, line break included.
+pub fn record<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut line = String::new();
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+    line.push_str("\r\n");
+    line
+}
+
+/// The records of `text`, along with the line each starts on, from 1.
+/// Blank lines are skipped, and line breaks may be `\r\n` or `\n`.
+pub fn records(text: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '"' => {
+                return Err(format!(
+                    "line {line}: unexpected quote in an unquoted field"
+                ))
+            }
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_record(&mut records, &mut fields, &mut field, start);
+                line += 1;
+                start = line;
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("line {start}: unterminated quoted field"));
+    }
+    end_record(&mut records, &mut fields, &mut field, start);
+    Ok(records)
+}
+
+fn end_record(
+    records: &mut Vec<(usize, Vec<String>)>,
+    fields: &mut Vec<String>,
+    field: &mut String,
+    line: usize,
+) {
+    fields.push(std::mem::take(field));
+    let fields = std::mem::take(fields);
+    if fields.len() > 1 || !fields[0].is_empty() {
+        records.push((line, fields));
+    }
+}
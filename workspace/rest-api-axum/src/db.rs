@@ -1,34 +1,148 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Context;
 use axum::{
     async_trait,
-    extract::{FromRef, FromRequestParts},
-    http::{request::Parts, StatusCode},
-    // routing::get,
-    // Router,
+    extract::{FromRef, FromRequestParts, MatchedPath},
+    http::request::Parts,
 };
-use sqlx::postgres::PgPool;
-// use tokio::net::TcpListener;
-// use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use tracing::Instrument;
+
+use crate::config::Config;
+use crate::errors::CustomError;
+use crate::metrics::Metrics;
+use crate::repository::instrumented::Instrumented;
+use crate::repository::memory::MemoryStore;
+use crate::repository::postgres::PgStore;
+use crate::repository::sqlite::SqliteStore;
+use crate::repository::{Repository, Store};
+
+/// The backend selected by the scheme of `DATABASE_URL`:
+/// `postgres://`, `sqlite:` or `memory:`.
+pub enum Database {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+    Memory(MemoryStore),
+}
+
+impl Database {
+    pub fn store(&self) -> Arc<dyn Store> {
+        match self {
+            Self::Postgres(pool) => Arc::new(PgStore { pool: pool.clone() }),
+            Self::Sqlite(pool) => Arc::new(SqliteStore::new(pool.clone())),
+            Self::Memory(store) => Arc::new(store.clone()),
+        }
+    }
 
-// use std::time::Duration;
-use crate::errors::internal_error;
+    /// Closes the pool, once the connections in use, along with their
+    /// transactions, are done with.
+    pub async fn close(&self) {
+        match self {
+            Self::Postgres(pool) => pool.close().await,
+            Self::Sqlite(pool) => pool.close().await,
+            Self::Memory(_) => {}
+        }
+    }
+}
+
+/// Connects to the database described by the configuration.
+pub async fn connect(config: &Config) -> anyhow::Result<Database> {
+    let url = &config.database_url;
+    if url.starts_with("memory:") {
+        Ok(Database::Memory(MemoryStore::default()))
+    } else if url.starts_with("sqlite:") {
+        connect_sqlite(config).await.map(Database::Sqlite)
+    } else {
+        connect_postgres(config).await.map(Database::Postgres)
+    }
+}
+
+async fn connect_postgres(config: &Config) -> anyhow::Result<PgPool> {
+    let mut connect_options =
+        PgConnectOptions::from_str(&config.database_url).context("invalid DATABASE_URL")?;
+    if let Some(timeout) = config.database_statement_timeout {
+        connect_options =
+            connect_options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))]);
+    }
+
+    PgPoolOptions::new()
+        .max_connections(config.database_max_connections)
+        .min_connections(config.database_min_connections)
+        .acquire_timeout(config.database_acquire_timeout)
+        .connect_with(connect_options)
+        .await
+        .context("could not connect to database_url")
+}
+
+async fn connect_sqlite(config: &Config) -> anyhow::Result<SqlitePool> {
+    let connect_options = SqliteConnectOptions::from_str(&config.database_url)
+        .context("invalid DATABASE_URL")?
+        .create_if_missing(true);
+
+    let mut pool_options = SqlitePoolOptions::new()
+        .max_connections(config.database_max_connections)
+        .min_connections(config.database_min_connections)
+        .acquire_timeout(config.database_acquire_timeout);
+    // Every connection to `sqlite::memory:` opens its own empty database, so
+    // keep a single one alive for the whole life of the pool.
+    if config.database_url.contains(":memory:") || config.database_url.contains("mode=memory") {
+        pool_options = pool_options
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None);
+    }
+
+    pool_options
+        .connect_with(connect_options)
+        .await
+        .context("could not connect to database_url")
+}
 
 // we can also write a custom extractor that grabs a connection from the pool
 // which setup is appropriate depends on your application
-pub struct DatabaseConnection(pub sqlx::pool::PoolConnection<sqlx::Postgres>);
+pub struct DatabaseConnection(pub Box<dyn Repository>);
 
 #[async_trait]
 impl<S> FromRequestParts<S> for DatabaseConnection
 where
-    PgPool: FromRef<S>,
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Metrics>: FromRef<S>,
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, String);
-
-    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let pool = PgPool::from_ref(state);
+    type Rejection = CustomError;
 
-        let conn = pool.acquire().await.map_err(internal_error)?;
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let store = Arc::<dyn Store>::from_ref(state);
+        let metrics = Arc::<Metrics>::from_ref(state);
 
-        Ok(Self(conn))
+        let route = parts
+            .extensions
+            .get::<MatchedPath>()
+            .map_or("unmatched", |path| path.as_str());
+        let handler = format!("{} {route}", parts.method);
+        Ok(Self(acquire(&*store, metrics, handler).await?))
     }
 }
+
+/// A connection from `store`, its queries counted as made by `handler`.
+///
+/// What [`DatabaseConnection`] extracts, for handlers that must not hold a
+/// connection for the whole request.
+pub async fn acquire(
+    store: &dyn Store,
+    metrics: Arc<Metrics>,
+    handler: String,
+) -> Result<Box<dyn Repository>, CustomError> {
+    let conn = {
+        let _waiting = metrics.wait_for_connection();
+        store
+            .acquire()
+            .instrument(tracing::debug_span!("acquire_connection"))
+            .await?
+    };
+    Ok(Box::new(Instrumented::new(conn, handler, metrics)))
+}
@@ -1,30 +1,295 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
-use serde_json::json;
+use std::fmt;
+use std::time::Duration;
 
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Media type of the RFC 7807 bodies returned for every error.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// A single invalid field of a request body.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// The RFC 7807 body of every error response.
+#[derive(Serialize, ToSchema)]
+pub struct Problem {
+    /// A relative URI identifying the kind of problem, e.g. `/problems/not-found`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The reason phrase of the status code.
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// The invalid fields of a validation error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldError>>,
+    /// The kind of resource that was not found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<&'static str>,
+    /// The id of the resource that was not found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum CustomError {
-    BadRequest,
-    TaskNotFound,
-    InternalServerError,
+    /// The request could not be understood at all, e.g. a malformed body.
+    BadRequest(String),
+    /// The request was well formed, but some fields hold invalid values.
+    Validation(Vec<FieldError>),
+    NotFound {
+        resource: &'static str,
+        id: String,
+    },
+    /// The request conflicts with the current state of the resource.
+    Conflict(String),
+    UniqueViolation {
+        constraint: Option<String>,
+    },
+    /// Missing, invalid or expired credentials.
+    Unauthorized(String),
+    PreconditionFailed,
+    /// The body is longer than the given number of bytes.
+    PayloadTooLarge(usize),
+    UnsupportedMediaType(String),
+    /// Well-formed, but cannot be processed, e.g. an idempotency key reused
+    /// for another request.
+    UnprocessableEntity(String),
+    /// Not applied because another operation of the same batch failed.
+    FailedDependency(String),
+    /// The client went over its rate limit, and may retry after the given delay.
+    TooManyRequests(Duration),
+    DbUnavailable,
+    Timeout,
+    /// Anything else. The message is logged but never sent to the client.
+    InternalServerError(String),
 }
 
-impl IntoResponse for CustomError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match self {
-            Self::InternalServerError => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error")
-            }
-            Self::BadRequest => (StatusCode::BAD_REQUEST, "Bad Request"),
-            Self::TaskNotFound => (StatusCode::NOT_FOUND, "Task Not Found"),
+impl CustomError {
+    pub fn not_found(resource: &'static str, id: impl ToString) -> Self {
+        Self::NotFound {
+            resource,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) | Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Conflict(_) | Self::UniqueViolation { .. } => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::FailedDependency(_) => StatusCode::FAILED_DEPENDENCY,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::DbUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Short identifier of the problem, used to build its `type` URI.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad-request",
+            Self::Validation(_) => "validation-error",
+            Self::NotFound { .. } => "not-found",
+            Self::Conflict(_) => "conflict",
+            Self::UniqueViolation { .. } => "unique-violation",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::PreconditionFailed => "precondition-failed",
+            Self::PayloadTooLarge(_) => "payload-too-large",
+            Self::UnsupportedMediaType(_) => "unsupported-media-type",
+            Self::UnprocessableEntity(_) => "unprocessable-entity",
+            Self::FailedDependency(_) => "failed-dependency",
+            Self::TooManyRequests(_) => "too-many-requests",
+            Self::DbUnavailable => "db-unavailable",
+            Self::Timeout => "timeout",
+            Self::InternalServerError(_) => "internal-server-error",
+        }
+    }
+
+    /// The body describing the error to the client.
+    pub fn into_problem(self) -> Problem {
+        let status = self.status();
+        if let Self::InternalServerError(message) = &self {
+            tracing::error!("internal server error: {message}");
+        }
+
+        let mut problem = Problem {
+            kind: format!("/problems/{}", self.kind()),
+            title: status.canonical_reason().unwrap_or_default().to_owned(),
+            status: status.as_u16(),
+            detail: self.to_string(),
+            errors: None,
+            resource: None,
+            id: None,
         };
-        (status, Json(json!({"error": error_message}))).into_response()
+        match self {
+            Self::Validation(errors) => problem.errors = Some(errors),
+            Self::NotFound { resource, id } => {
+                problem.resource = Some(resource);
+                problem.id = Some(id);
+            }
+            _ => {}
+        }
+        problem
+    }
+}
+
+impl fmt::Display for CustomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadRequest(detail)
+            | Self::Conflict(detail)
+            | Self::Unauthorized(detail)
+            | Self::UnprocessableEntity(detail)
+            | Self::FailedDependency(detail) => f.write_str(detail),
+            Self::Validation(errors) => {
+                f.write_str("invalid fields: ")?;
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{} {}", error.field, error.message)?;
+                }
+                Ok(())
+            }
+            Self::NotFound { resource, id } => write!(f, "{resource} {id} not found"),
+            Self::UniqueViolation {
+                constraint: Some(constraint),
+            } => write!(f, "value already exists ({constraint})"),
+            Self::UniqueViolation { constraint: None } => f.write_str("value already exists"),
+            Self::PreconditionFailed => f.write_str("the resource has been modified"),
+            Self::PayloadTooLarge(limit) => write!(f, "the body must be at most {limit} bytes"),
+            Self::UnsupportedMediaType(expected) => write!(f, "expected {expected}"),
+            Self::TooManyRequests(retry_after) => write!(
+                f,
+                "rate limit exceeded, retry in {} seconds",
+                retry_after.as_secs()
+            ),
+            Self::DbUnavailable => f.write_str("the database is unavailable"),
+            Self::Timeout => f.write_str("the database did not answer in time"),
+            Self::InternalServerError(_) => f.write_str("an unexpected error occurred"),
+        }
+    }
+}
+
+impl std::error::Error for CustomError {}
+
+impl From<sqlx::Error> for CustomError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::Database(db) => match db.code().as_deref() {
+                // unique_violation
+                Some("23505") => Self::UniqueViolation {
+                    constraint: db.constraint().map(str::to_owned),
+                },
+                // foreign_key_violation, serialization_failure, deadlock_detected
+                Some("23503" | "40001" | "40P01") => Self::Conflict(db.message().to_owned()),
+                // query_canceled, raised by `statement_timeout`
+                Some("57014") => Self::Timeout,
+                // cannot_connect_now, too_many_connections
+                Some("57P03" | "53300") => Self::DbUnavailable,
+                // SQLite has codes of its own
+                _ if db.is_unique_violation() => Self::UniqueViolation {
+                    constraint: db.constraint().map(str::to_owned),
+                },
+                _ if db.is_foreign_key_violation() => Self::Conflict(db.message().to_owned()),
+                _ => Self::InternalServerError(err.to_string()),
+            },
+            sqlx::Error::PoolTimedOut => Self::Timeout,
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => Self::DbUnavailable,
+            // Handlers look rows up with `fetch_optional` and report which
+            // resource was missing, so this one is unexpected too.
+            _ => Self::InternalServerError(err.to_string()),
+        }
+    }
+}
+
+impl From<JsonRejection> for CustomError {
+    fn from(rejection: JsonRejection) -> Self {
+        match rejection {
+            JsonRejection::MissingJsonContentType(_) => {
+                Self::UnsupportedMediaType("application/json".to_owned())
+            }
+            // Well-formed JSON, but not of the expected shape or values.
+            JsonRejection::JsonDataError(err) => Self::UnprocessableEntity(err.body_text()),
+            rejection if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                Self::PayloadTooLarge(crate::extract::MAX_BODY_LEN)
+            }
+            rejection => Self::BadRequest(rejection.body_text()),
+        }
     }
 }
 
-/// Utility function for mapping any error into a `500 Internal Server Error`
-/// response.
-pub fn internal_error<E>(err: E) -> (StatusCode, String)
-where
-    E: std::error::Error,
-{
-    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+impl From<PathRejection> for CustomError {
+    fn from(rejection: PathRejection) -> Self {
+        match rejection {
+            PathRejection::FailedToDeserializePathParams(err) => Self::BadRequest(err.body_text()),
+            // The route does not match the extractor, a bug rather than a bad request.
+            rejection => Self::InternalServerError(rejection.body_text()),
+        }
+    }
+}
+
+impl From<QueryRejection> for CustomError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::BadRequest(rejection.body_text())
+    }
+}
+
+impl IntoResponse for CustomError {
+    fn into_response(self) -> axum::response::Response {
+        let status = self.status();
+        let retry_after = match &self {
+            Self::TooManyRequests(retry_after) => Some(retry_after.as_secs()),
+            _ => None,
+        };
+        let problem = self.into_problem();
+
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, PROBLEM_JSON)],
+            Json(problem),
+        )
+            .into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from(retry_after));
+        }
+        response
+    }
 }
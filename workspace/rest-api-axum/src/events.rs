@@ -0,0 +1,208 @@
+// Live feed of the changes to tasks, behind `GET /tasks/events` and `GET /tasks/ws`.
+//
+// Backends record every committed change in a log of events, whichever way
+// the task changed. A single [`Hub`] per process reads the new events as soon
+// as the backend reports a change, through LISTEN/NOTIFY on Postgres so that
+// changes made through any instance are seen, and broadcasts them to the
+// subscriptions of connected clients. Subscriptions first catch up from the
+// log, which is how clients resume after their `Last-Event-ID`. Closing the
+// hub on shutdown ends every subscription, for feeds not to hold it up.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::errors::CustomError;
+use crate::models::event::{EventCursor, TaskEvent};
+use crate::repository::Store;
+
+/// Live events kept for subscriptions that fall behind, which read the log
+/// instead once they miss more.
+const BUFFER_SIZE: usize = 1024;
+/// Events read from the log at once.
+const PAGE_SIZE: i64 = 500;
+/// Reads the log even without a notification, which may have been lost, or
+/// never sent by another process writing to SQLite.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+pub struct Hub {
+    store: Arc<dyn Store>,
+    sender: broadcast::Sender<Arc<TaskEvent>>,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl Hub {
+    /// Starts following the events of `store` in the background, pruning
+    /// those older than `retention`.
+    pub fn start(store: Arc<dyn Store>, retention: Option<Duration>) -> Self {
+        let (sender, _) = broadcast::channel(BUFFER_SIZE);
+        let hub = Self {
+            store,
+            sender,
+            closed: Arc::new(watch::channel(false).0),
+        };
+        tokio::spawn(hub.clone().run(retention));
+        hub
+    }
+
+    /// Subscribes to the events of the tasks of `owner` after the event
+    /// `after` or, without one, to those still to come.
+    pub async fn subscribe(
+        &self,
+        owner: i32,
+        after: Option<i64>,
+    ) -> Result<Subscription, CustomError> {
+        // Subscribe before looking at the log, so that no event falls between both.
+        let live = self.sender.subscribe();
+        let cursor = match after {
+            Some(after) => after,
+            None => self.store.acquire().await?.last_event_id(owner).await?,
+        };
+        Ok(Subscription {
+            store: self.store.clone(),
+            owner,
+            cursor,
+            live,
+            backlog: VecDeque::new(),
+            caught_up: false,
+            closed: self.closed.subscribe(),
+        })
+    }
+
+    /// Stops following the events and ends every subscription, which then
+    /// fail as if the hub had stopped.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    async fn run(self, retention: Option<Duration>) {
+        let mut closed = self.closed.subscribe();
+        let run = async {
+            let mut cursor = None;
+            let mut pruned_at = None;
+            loop {
+                if let Err(err) = self.follow(&mut cursor, &mut pruned_at, retention).await {
+                    // The pool may close before this task notices the hub did.
+                    if *self.closed.borrow() {
+                        return;
+                    }
+                    tracing::error!("could not follow task events: {err}");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        };
+        tokio::select! {
+            () = run => {}
+            _ = closed.wait_for(|closed| *closed) => {}
+        }
+    }
+
+    /// Broadcasts the events after `cursor` as they are recorded.
+    async fn follow(
+        &self,
+        cursor: &mut Option<EventCursor>,
+        pruned_at: &mut Option<Instant>,
+        retention: Option<Duration>,
+    ) -> Result<(), CustomError> {
+        let mut listener = self.store.listen().await?;
+        loop {
+            let mut conn = self.store.acquire().await?;
+            // Older events are read from the log by the subscriptions themselves.
+            let cursor = match cursor {
+                Some(cursor) => cursor,
+                None => cursor.insert(conn.event_cursor().await?),
+            };
+            loop {
+                let events = conn.follow_events(cursor, PAGE_SIZE).await?;
+                let done = (events.len() as i64) < PAGE_SIZE;
+                for event in events {
+                    // Fails only when nobody is subscribed.
+                    let _ = self.sender.send(Arc::new(event));
+                }
+                if done {
+                    break;
+                }
+            }
+
+            if let Some(retention) = retention {
+                if pruned_at.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                    let before =
+                        Utc::now() - chrono::Duration::from_std(retention).unwrap_or_default();
+                    let count = conn.prune_events(before).await?;
+                    tracing::debug!("pruned {count} task events");
+                    *pruned_at = Some(Instant::now());
+                }
+            }
+            drop(conn);
+
+            if let Ok(result) = tokio::time::timeout(POLL_INTERVAL, listener.changed()).await {
+                result?;
+            }
+        }
+    }
+}
+
+/// The events of the tasks of one user, in order.
+pub struct Subscription {
+    store: Arc<dyn Store>,
+    owner: i32,
+    /// The last event returned.
+    cursor: i64,
+    live: broadcast::Receiver<Arc<TaskEvent>>,
+    /// Events read from the log, not returned yet.
+    backlog: VecDeque<TaskEvent>,
+    /// Whether the log has nothing more than the live events.
+    caught_up: bool,
+    closed: watch::Receiver<bool>,
+}
+
+impl Subscription {
+    /// Waits for the next event. Cancelling it loses no event.
+    pub async fn next(&mut self) -> Result<TaskEvent, CustomError> {
+        loop {
+            if let Some(event) = self.backlog.pop_front() {
+                self.cursor = event.id;
+                return Ok(event);
+            }
+
+            if !self.caught_up {
+                let events = self
+                    .store
+                    .acquire()
+                    .await?
+                    .events_after(Some(self.owner), self.cursor, PAGE_SIZE)
+                    .await?;
+                self.caught_up = (events.len() as i64) < PAGE_SIZE;
+                self.backlog.extend(events);
+                continue;
+            }
+
+            let received = tokio::select! {
+                received = self.live.recv() => received,
+                _ = self.closed.wait_for(|closed| *closed) => Err(RecvError::Closed),
+            };
+            match received {
+                // Events already read from the log come again live.
+                Ok(event) if event.owner_id == Some(self.owner) && event.id > self.cursor => {
+                    self.cursor = event.id;
+                    return Ok(TaskEvent::clone(&event));
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => self.caught_up = false,
+                Err(RecvError::Closed) => {
+                    return Err(CustomError::InternalServerError(
+                        "the task event hub stopped".to_owned(),
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+// Idempotency keys, so that clients can safely retry requests creating
+// something, e.g. after a timeout.
+//
+// The first request with a given `Idempotency-Key` claims it, and its response
+// is stored along with a fingerprint of the request for `IDEMPOTENCY_TTL_SECS`.
+// Retries with the same key get that response again, with
+// `Idempotent-Replayed: true`. Other requests reusing the key get a 422, and
+// those made while the first one is still in progress a 409. Keys belong to
+// the user making the request.
+//
+// Server errors are not stored, so that the request can really be retried.
+// Neither are the responses of requests that never finished, e.g. because the
+// client went away: their key is free again after `REQUEST_TIMEOUT_SECS`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{boxed, Body, Bytes, Full, HttpBody};
+use axum::extract::{MatchedPath, State};
+use axum::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use http_body::{LengthLimitError, Limited};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+use crate::auth::CurrentUser;
+use crate::errors::CustomError;
+use crate::extract::MAX_BODY_LEN;
+use crate::models::idempotency::StoredResponse;
+use crate::repository::Store;
+use crate::shutdown::Stop;
+use crate::state::AppState;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+const MAX_KEY_LEN: usize = 255;
+/// Upper bound on how long expired keys are kept.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Middleware storing the response to each request with an
+/// `Idempotency-Key`, and replaying it to the retries of that request.
+/// Requests without the header, or without valid credentials, go through.
+pub async fn idempotent(
+    State(state): State<AppState>,
+    user: Option<CurrentUser>,
+    path: Option<MatchedPath>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let (Some(ttl), Some(user)) = (state.config.idempotency_ttl, user) else {
+        return next.run(request).await;
+    };
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY).cloned() else {
+        return next.run(request).await;
+    };
+    let path = path.map_or_else(
+        || request.uri().path().to_owned(),
+        |p| p.as_str().to_owned(),
+    );
+
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LEN => key.to_owned(),
+        _ => {
+            let message =
+                format!("the Idempotency-Key header must be 1 to {MAX_KEY_LEN} characters");
+            return CustomError::BadRequest(message).into_response();
+        }
+    };
+    let (parts, body) = request.into_parts();
+    let body = match collect(Limited::new(body, MAX_BODY_LEN)).await {
+        Ok(body) => body,
+        Err(err) if err.is::<LengthLimitError>() => {
+            return CustomError::PayloadTooLarge(MAX_BODY_LEN).into_response()
+        }
+        Err(err) => return CustomError::BadRequest(err.to_string()).into_response(),
+    };
+    let fingerprint = fingerprint(parts.method.as_str(), &path, &body);
+
+    let claim = Claim {
+        store: &*state.store,
+        owner: user.id,
+        key: &key,
+    };
+    match claim
+        .take(&fingerprint, ttl, state.config.request_timeout)
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(stored)) => return replay(stored),
+        Err(err) => return err.into_response(),
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    claim.settle(response).await
+}
+
+/// Reads the whole of `body`.
+async fn collect<B>(mut body: B) -> Result<Vec<u8>, B::Error>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.data().await {
+        buffer.extend_from_slice(&chunk?);
+    }
+    Ok(buffer)
+}
+
+/// Identifies a request by what it does, so that a key is not reused for
+/// another one.
+fn fingerprint(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method);
+    hasher.update(" ");
+    hasher.update(path);
+    hasher.update("\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// The key of a request.
+struct Claim<'a> {
+    store: &'a dyn Store,
+    owner: i32,
+    key: &'a str,
+}
+
+impl Claim<'_> {
+    /// Claims the key for the request with `fingerprint`, or returns the
+    /// response to replay instead.
+    async fn take(
+        &self,
+        fingerprint: &str,
+        ttl: Duration,
+        request_timeout: Duration,
+    ) -> Result<Option<StoredResponse>, CustomError> {
+        let now = Utc::now();
+        let locked_until = now + chrono::Duration::from_std(request_timeout).unwrap_or_default();
+        let expires_at = now + chrono::Duration::from_std(ttl).unwrap_or_default();
+        let held = self
+            .store
+            .acquire()
+            .await?
+            .claim_idempotency_key(
+                self.owner,
+                self.key,
+                fingerprint,
+                now,
+                locked_until,
+                expires_at,
+            )
+            .await?;
+        let Some(held) = held else {
+            return Ok(None);
+        };
+        if held.fingerprint != fingerprint {
+            return Err(CustomError::UnprocessableEntity(
+                "the Idempotency-Key was already used for another request".to_owned(),
+            ));
+        }
+        match held.response() {
+            Some(stored) => Ok(Some(stored)),
+            None => Err(CustomError::Conflict(
+                "a request with the same Idempotency-Key is still in progress".to_owned(),
+            )),
+        }
+    }
+
+    /// Stores `response` for the retries of the request, or lets go of the
+    /// key if it is a server error, and returns it.
+    async fn settle(&self, response: Response) -> Response {
+        if response.status().is_server_error() {
+            if let Err(err) = self.release().await {
+                tracing::error!("could not release an idempotency key: {err}");
+            }
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let buffer = match collect(body).await {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                // The response is lost, and so would be any retry.
+                let _ = self.release().await;
+                return CustomError::InternalServerError(err.to_string()).into_response();
+            }
+        };
+        let stored = StoredResponse {
+            status: parts.status.as_u16(),
+            content_type: parts
+                .headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            body: buffer,
+        };
+        if let Err(err) = self.save(&stored).await {
+            // Retries after the key is free again would repeat the request.
+            tracing::error!("could not store the response to an idempotency key: {err}");
+        }
+        Response::from_parts(parts, boxed(Full::from(stored.body)))
+    }
+
+    async fn save(&self, stored: &StoredResponse) -> Result<(), CustomError> {
+        let mut conn = self.store.acquire().await?;
+        conn.save_idempotent_response(self.owner, self.key, stored)
+            .await
+    }
+
+    async fn release(&self) -> Result<(), CustomError> {
+        let mut conn = self.store.acquire().await?;
+        conn.release_idempotency_key(self.owner, self.key).await
+    }
+}
+
+/// The response stored for a request, again.
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    headers.remove(CONTENT_TYPE);
+    if let Some(content_type) = stored
+        .content_type
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+/// Deletes the expired keys in the background, every hour, until asked to `stop`.
+pub fn start_pruning(store: Arc<dyn Store>, mut stop: Stop) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = stop.requested() => return,
+            }
+            let pruned = async {
+                let mut conn = store.acquire().await?;
+                conn.prune_idempotency_keys(Utc::now()).await
+            };
+            match pruned.await {
+                Ok(count) => tracing::debug!("pruned {count} idempotency keys"),
+                Err(err) => tracing::error!("could not prune idempotency keys: {err}"),
+            }
+        }
+    })
+}
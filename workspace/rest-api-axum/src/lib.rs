@@ -0,0 +1,184 @@
+// A simple REST API using axum and sqlx.
+//
+// The binary in `main.rs` wires this library to a real database; tests drive
+// the router returned by [`app`] directly.
+
+use axum::{
+    middleware,
+    routing::{delete, get, patch, post, put},
+    Router,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::timeout::TimeoutLayer;
+use tower_http::trace::TraceLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use config::Config;
+use state::AppState;
+
+pub mod auth;
+pub mod cli;
+pub mod config;
+pub mod csv;
+pub mod db;
+pub mod errors;
+pub mod events;
+pub mod extract;
+pub mod idempotency;
+pub mod logging;
+pub mod metrics;
+pub mod models;
+pub mod openapi;
+pub mod rate_limit;
+pub mod repository;
+pub mod routes;
+pub mod scheduler;
+pub mod shutdown;
+pub mod state;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod trash;
+pub mod webhooks;
+
+/// Builds the whole API, layers included, on top of `state`.
+pub fn app(state: AppState) -> Router {
+    let config = state.config.clone();
+    let metrics = state.metrics.clone();
+
+    Router::new()
+        .route("/hello", get(root))
+        .route("/auth/register", post(routes::auth::register::handler))
+        .route("/auth/login", post(routes::auth::login::handler))
+        .route("/auth/refresh", post(routes::auth::refresh::handler))
+        .route("/tasks", get(routes::tasks::get_tasks::handler))
+        .route("/tasks/bulk", post(routes::tasks::bulk_tasks::handler))
+        .route("/tasks/export", get(routes::tasks::export_tasks::handler))
+        .route("/tasks/import", post(routes::tasks::import_tasks::handler))
+        .route("/tasks/search", get(routes::tasks::search_tasks::handler))
+        .route("/tasks/trash", get(routes::tasks::get_trash::handler))
+        .route("/tasks/events", get(routes::tasks::task_events::handler))
+        .route("/tasks/ws", get(routes::tasks::task_socket::handler))
+        .route(
+            "/task",
+            post(routes::tasks::create_task::handler).layer(middleware::from_fn_with_state(
+                state.clone(),
+                idempotency::idempotent,
+            )),
+        )
+        .route("/task/:id", get(routes::tasks::get_task::handler))
+        .route("/task/:id", put(routes::tasks::update_task::handler))
+        .route("/task/:id", patch(routes::tasks::patch_task::handler))
+        .route("/task/:id", delete(routes::tasks::delete_task::handler))
+        .route(
+            "/task/:id/restore",
+            post(routes::tasks::restore_task::handler),
+        )
+        .route(
+            "/task/:id/history",
+            get(routes::tasks::task_history::handler),
+        )
+        .route(
+            "/task/:id/subtasks",
+            get(routes::tasks::get_subtasks::handler).post(routes::tasks::create_subtask::handler),
+        )
+        .route("/task/:id/tags", get(routes::tasks::get_task_tags::handler))
+        .route(
+            "/task/:id/tags/:tag_id",
+            put(routes::tasks::tag_task::handler).delete(routes::tasks::untag_task::handler),
+        )
+        .route(
+            "/projects",
+            get(routes::projects::get_projects::handler)
+                .post(routes::projects::create_project::handler),
+        )
+        .route(
+            "/projects/:id",
+            get(routes::projects::get_project::handler)
+                .put(routes::projects::update_project::handler)
+                .delete(routes::projects::delete_project::handler),
+        )
+        .route(
+            "/projects/:id/tasks",
+            get(routes::projects::get_project_tasks::handler)
+                .post(routes::projects::create_project_task::handler),
+        )
+        .route(
+            "/tags",
+            get(routes::tags::get_tags::handler).post(routes::tags::create_tag::handler),
+        )
+        .route(
+            "/tags/:id",
+            put(routes::tags::update_tag::handler).delete(routes::tags::delete_tag::handler),
+        )
+        .route("/audit", get(routes::audit::get_audit::handler))
+        .route(
+            "/webhooks",
+            get(routes::webhooks::get_webhooks::handler)
+                .post(routes::webhooks::create_webhook::handler),
+        )
+        .route(
+            "/webhooks/:id",
+            delete(routes::webhooks::delete_webhook::handler),
+        )
+        .route(
+            "/webhooks/:id/deliveries",
+            get(routes::webhooks::get_deliveries::handler),
+        )
+        .route(
+            "/webhooks/:id/deliveries/:delivery_id/redeliver",
+            post(routes::webhooks::redeliver::handler),
+        )
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", openapi::ApiDoc::openapi()))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit,
+        ))
+        // Probes and scrapes are never limited.
+        .route("/healthz", get(routes::health::healthz::handler))
+        .route("/readyz", get(routes::health::readyz::handler))
+        .route("/metrics", get(routes::health::metrics::handler))
+        .with_state(state)
+        .layer(TimeoutLayer::new(config.request_timeout))
+        .layer(cors_layer(&config))
+        .layer(TraceLayer::new_for_http().make_span_with(logging::request_span))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        // Keeps the id the client sent, if any.
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        // Outermost, to also count the requests turned down by the layers.
+        .layer(middleware::from_fn_with_state(metrics, metrics::track))
+}
+
+fn cors_layer(config: &Config) -> CorsLayer {
+    let origin = if config.cors_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.cors_origins.clone())
+    };
+    CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(tower_http::cors::Any)
+        .allow_headers(tower_http::cors::Any)
+        .expose_headers([
+            axum::http::header::ETAG,
+            axum::http::header::WWW_AUTHENTICATE,
+            axum::http::header::RETRY_AFTER,
+            idempotency::IDEMPOTENT_REPLAYED,
+            logging::X_REQUEST_ID,
+            rate_limit::RATELIMIT_LIMIT,
+            rate_limit::RATELIMIT_REMAINING,
+            rate_limit::RATELIMIT_RESET,
+            rate_limit::RATELIMIT_POLICY,
+        ])
+}
+
+#[utoipa::path(
+    get,
+    path = "/hello",
+    operation_id = "hello",
+    responses((status = 200, description = "A greeting", body = String, content_type = "text/plain")),
+)]
+async fn root() -> &'static str {
+    "Hello, World!"
+}
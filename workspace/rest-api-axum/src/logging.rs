@@ -0,0 +1,106 @@
+// Logs, as text for people or as JSON lines for log collectors, picked by
+// `LOG_FORMAT`.
+//
+// Every request gets a `request` span carrying its `X-Request-Id`, which
+// handlers run in. The queries they make get a `query` span of their own
+// within it, so every line logged while handling a request can be traced back
+// to it, and to the id the client got in the response. With the `otel`
+// feature, the same spans can also be exported, see `telemetry`.
+
+use std::str::FromStr;
+
+use axum::extract::MatchedPath;
+use axum::http::header::HeaderName;
+use axum::http::Request;
+use tower_http::request_id::RequestId;
+use tracing::Span;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::Config;
+
+/// Set by the client, or else generated, and echoed in the response.
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the event and of every
+    /// span it happened in.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("expected text or json".to_owned()),
+        }
+    }
+}
+
+/// Logs to stdout, and exports traces if enabled, as configured, for the rest
+/// of the process.
+pub fn init(config: &Config) -> anyhow::Result<()> {
+    let subscriber = tracing_subscriber::registry()
+        .with(EnvFilter::new(&config.log_filter))
+        .with(layer(config.log_format, std::io::stdout));
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(
+        config
+            .otlp_endpoint
+            .as_deref()
+            .map(crate::telemetry::layer)
+            .transpose()?,
+    );
+    subscriber.init();
+    Ok(())
+}
+
+/// Formats events in `format`, writing them with `writer`.
+pub fn layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// The span of a request, for `TraceLayer`. Must run after the id was set.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str());
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        route,
+        request_id,
+    );
+    #[cfg(feature = "otel")]
+    crate::telemetry::set_parent(&span, request.headers());
+    span
+}
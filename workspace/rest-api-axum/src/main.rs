@@ -2,72 +2,122 @@
 //
 // To run this project:
 // 1. Bring Postgres DB up with `docker compose up`
-// 2. Start the service with `cargo run`
+// 2. Set `JWT_SECRET` to a random string of at least 32 bytes, e.g.
+//    `export JWT_SECRET=$(openssl rand -hex 32)`
+// 3. Start the service with `cargo run -- serve --migrate`
+//
+// `DATABASE_URL` also accepts `sqlite:tasks.db`, `sqlite::memory:` or `memory:`
+// to run without Postgres.
+//
+// Other commands:
+//    cargo run -- migrate up|down|status
+//    cargo run -- seed tasks.json --owner someone@example.com
 //
 // To test this server, access it at:
 //    http://localhost:8000
+// Interactive API docs are served at `/docs`, the OpenAPI spec at `/openapi.json`.
+// Changes to tasks are streamed at `/tasks/events` (SSE) and `/tasks/ws` (WebSocket).
+// Deleted tasks wait in `/tasks/trash` for `TRASH_RETENTION_SECS` before they are purged.
+// Tasks with a `recurrence` get their next occurrence once they are done.
+// Webhooks registered at `/webhooks` are POSTed the changes to tasks, signed.
 //
 // Created based on:
 // https://carlosmv.hashnode.dev/creating-a-rest-api-with-axum-sqlx-rust
 // https://github.com/tokio-rs/axum/tree/main/examples
 
-use axum::{
-    routing::{delete, get, post, put},
-    Router,
-};
-
-use anyhow::Context;
-use sqlx::postgres::PgPoolOptions;
-use std::fs;
-use std::net::SocketAddr;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::net::TcpListener;
 
-mod db;
-mod errors;
-mod models;
-mod routes;
+use clap::Parser;
+use rest_api_axum::cli::{self, Cli, Command};
+use rest_api_axum::config::{Config, Purpose};
+use rest_api_axum::db;
+use rest_api_axum::idempotency;
+use rest_api_axum::logging;
+use rest_api_axum::scheduler;
+use rest_api_axum::shutdown;
+use rest_api_axum::state::AppState;
+use rest_api_axum::trash;
+use rest_api_axum::webhooks;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let env = fs::read_to_string(".env").unwrap();
-    let (key, database_url) = env.split_once('=').unwrap();
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Serve { migrate: false });
+    let config = Config::load(match command {
+        Command::Serve { .. } => Purpose::Serve,
+        Command::Migrate(_) | Command::Seed { .. } => Purpose::Database,
+    })?;
 
-    assert_eq!(key, "DATABASE_URL");
+    logging::init(&config)?;
 
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("tower_http=trace")
-                .unwrap_or_else(|_| "rest_api_axum=debug,tower_http=debug".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let db = db::connect(&config).await?;
 
-    let pool = PgPoolOptions::new()
-        .max_connections(50)
-        .connect(&database_url)
-        .await
-        .context("could not connect to database_url")?;
+    match command {
+        Command::Serve { migrate } => {
+            if migrate || config.database_auto_migrate {
+                cli::migrate::up(&db).await?;
+            }
+            serve(config, &db).await
+        }
+        Command::Migrate(command) => cli::migrate::run(&db, command).await,
+        Command::Seed { file, owner } => cli::seed::run(&*db.store(), &file, &owner).await,
+    }
+}
 
-    let app = Router::new()
-        .route("/hello", get(root))
-        .route("/tasks", get(routes::tasks::get_tasks::handler))
-        .route("/task", post(routes::tasks::create_task::handler))
-        .route("/task/:id", get(routes::tasks::get_task::handler))
-        .route("/task/:id", put(routes::tasks::update_task::handler))
-        .route("/task/:id", delete(routes::tasks::delete_task::handler))
-        .with_state(pool)
-        .layer(TraceLayer::new_for_http());
+async fn serve(config: Config, db: &db::Database) -> anyhow::Result<()> {
+    let addr = config.bind_address;
+    let timeout = config.shutdown_timeout;
+    let mut workers = shutdown::Workers::new();
+    if let Some(retention) = config.trash_retention {
+        workers.push(trash::start_purging(
+            db.store(),
+            retention,
+            workers.stop_signal(),
+        ));
+    }
+    if let Some(interval) = config.recurrence_interval {
+        workers.push(scheduler::start(
+            db.store(),
+            interval,
+            workers.stop_signal(),
+        ));
+    }
+    if let Some(settings) = webhooks::Settings::new(&config) {
+        workers.push(webhooks::Worker::new(db.store(), settings).start(workers.stop_signal()));
+    }
+    if config.idempotency_ttl.is_some() {
+        workers.push(idempotency::start_pruning(
+            db.store(),
+            workers.stop_signal(),
+        ));
+    }
+    let state = AppState::new(config, db.store());
+    let events = state.events.clone();
+    let app = rest_api_axum::app(state);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     tracing::debug!("Listening on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await?;
+    let listener = TcpListener::bind(addr)?;
+    let signal = async move {
+        shutdown::signal().await;
+        // Event feeds would otherwise run until the timeout.
+        events.close();
+    };
+    shutdown::serve(listener, app, signal, timeout).await?;
 
+    // The workers finish what they are doing with the pool before it closes.
+    let close = async {
+        workers.stop().await;
+        db.close().await;
+    };
+    match timeout {
+        Some(timeout) => {
+            if tokio::time::timeout(timeout, close).await.is_err() {
+                tracing::warn!("connections still in use after {timeout:?}, not closing the pool");
+            }
+        }
+        None => close.await,
+    }
+    #[cfg(feature = "otel")]
+    rest_api_axum::telemetry::shutdown();
     Ok(())
 }
-
-async fn root() -> &'static str {
-    "Hello, World!"
-}
@@ -0,0 +1,168 @@
+// Prometheus metrics of the API, served at `/metrics`.
+//
+// Requests are counted and timed per route and status by the `track`
+// middleware, and the queries of handlers per handler and operation by
+// [`Instrumented`](crate::repository::instrumented::Instrumented) repositories.
+// The connections of the pool are counted when metrics are scraped, but the
+// requests waiting for one are counted as they wait.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, State};
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::repository::PoolStatus;
+
+/// The route of requests matching none.
+const UNMATCHED: &str = "unmatched";
+
+pub struct Metrics {
+    /// Every instance has its own, so that tests do not share their metrics.
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_query_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requests handled"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle requests",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time taken by database queries",
+            )
+            .buckets(vec![
+                0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+            ]),
+            &["handler", "operation"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Connections of the pool that are idle or in use, and requests waiting for one",
+            ),
+            &["state"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_query_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_pool_connections.clone()))
+            .unwrap();
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_query_duration,
+            db_pool_connections,
+        }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, &status];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records a query made by `handler`, the route it handles.
+    pub fn observe_query(&self, handler: &str, operation: &str, elapsed: Duration) {
+        self.db_query_duration
+            .with_label_values(&[handler, operation])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Counts a request waiting for a connection until the guard is dropped.
+    pub fn wait_for_connection(&self) -> Waiting {
+        let gauge = self.db_pool_connections.with_label_values(&["waiting"]);
+        gauge.inc();
+        Waiting(gauge)
+    }
+
+    /// Every metric, in the text format of Prometheus, along with the
+    /// connections of `pool` as of now.
+    pub fn render(&self, pool: Option<PoolStatus>) -> String {
+        if let Some(pool) = pool {
+            self.db_pool_connections
+                .with_label_values(&["idle"])
+                .set(pool.idle.into());
+            self.db_pool_connections
+                .with_label_values(&["in_use"])
+                .set(pool.size.saturating_sub(pool.idle).into());
+        }
+        let mut buffer = Vec::new();
+        // Only fails on metrics with invalid names, which would not register.
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A request waiting for a connection of the pool.
+pub struct Waiting(IntGauge);
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Middleware counting and timing every request, by the route it matched.
+pub async fn track<B>(
+    State(metrics): State<Arc<Metrics>>,
+    path: Option<MatchedPath>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let route = path.as_ref().map_or(UNMATCHED, |p| p.as_str()).to_owned();
+
+    let response = next.run(request).await;
+
+    metrics.observe_request(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        start.elapsed(),
+    );
+    response
+}
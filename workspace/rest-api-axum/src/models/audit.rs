@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::{IntoParams, ToSchema};
+
+use super::task::{Task, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::errors::{CustomError, FieldError};
+
+/// Who makes a change, recorded in the audit log along with it.
+#[derive(Clone, Debug)]
+pub struct Actor {
+    pub user_id: i32,
+    /// The `X-Request-Id` of the request making the change.
+    pub request_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "audit_action", rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// Moved to the trash.
+    Delete,
+    /// Taken out of the trash.
+    Restore,
+    /// Deleted for good.
+    Purge,
+}
+
+/// A change to a task, with who made it and the task before and after.
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub action: AuditAction,
+    pub task_id: i32,
+    #[serde(skip)]
+    pub owner_id: Option<i32>,
+    /// The user who made the change. Absent for changes made by the server
+    /// itself, like purging the trash.
+    pub actor_id: Option<i32>,
+    /// The `X-Request-Id` of the request that made the change.
+    pub request_id: Option<String>,
+    /// The task before the change, absent for creations.
+    #[schema(value_type = Option<Task>)]
+    pub before: Option<Json<Task>>,
+    /// The task after the change, absent for purges.
+    #[schema(value_type = Option<Task>)]
+    pub after: Option<Json<Task>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Query parameters of `GET /audit`.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditParams {
+    pub task_id: Option<i32>,
+    pub action: Option<AuditAction>,
+    pub actor_id: Option<i32>,
+    pub request_id: Option<String>,
+    /// Only the changes made at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only the changes made before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Page size, 50 by default.
+    #[param(minimum = 1, maximum = 500)]
+    pub limit: Option<i64>,
+    /// The `next_cursor` returned by the previous page.
+    pub cursor: Option<i64>,
+}
+
+impl AuditParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit()) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if until < since {
+                errors.push(FieldError::new("until", "must not be before since"));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CustomError::Validation(errors))
+        }
+    }
+}
+
+/// Query parameters of `GET /task/{id}/history`.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryParams {
+    /// Page size, 50 by default.
+    #[param(minimum = 1, maximum = 500)]
+    pub limit: Option<i64>,
+    /// The `next_cursor` returned by the previous page.
+    pub cursor: Option<i64>,
+}
+
+impl HistoryParams {
+    /// The same page of the audit log, restricted to the task `id`.
+    pub fn of_task(self, id: i32) -> AuditParams {
+        AuditParams {
+            task_id: Some(id),
+            limit: self.limit,
+            cursor: self.cursor,
+            ..Default::default()
+        }
+    }
+}
+
+/// A page of the audit log, newest entries first.
+#[derive(Serialize, ToSchema)]
+pub struct AuditPage {
+    pub items: Vec<AuditEntry>,
+    /// Pass as `cursor` to get the next page. `None` when this is the last page.
+    pub next_cursor: Option<i64>,
+}
+
+impl AuditPage {
+    /// Builds a page out of up to `limit + 1` entries, the extra one only
+    /// telling that there is a next page.
+    pub fn from_rows(mut rows: Vec<AuditEntry>, limit: i64) -> Self {
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().map(|entry| entry.id)
+        } else {
+            None
+        };
+        Self {
+            items: rows,
+            next_cursor,
+        }
+    }
+}
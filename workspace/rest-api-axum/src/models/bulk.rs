@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::task::{NewTask, Task, UpdateTask};
+use crate::errors::{CustomError, FieldError, Problem};
+
+pub const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// Body of `POST /tasks/bulk`.
+#[derive(Deserialize, ToSchema)]
+pub struct BulkRequest {
+    #[serde(default)]
+    pub mode: BulkMode,
+    /// Applied in order, at most 1000 of them.
+    pub operations: Vec<Operation>,
+}
+
+impl BulkRequest {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if self.operations.len() > MAX_BULK_OPERATIONS {
+            return Err(CustomError::Validation(vec![FieldError::new(
+                "operations",
+                format!("must have at most {MAX_BULK_OPERATIONS} items"),
+            )]));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// Either every operation is applied or none is.
+    #[default]
+    Atomic,
+    /// Operations that fail are skipped, the others are applied.
+    BestEffort,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    Create {
+        task: NewTask,
+    },
+    Update {
+        id: i32,
+        /// Only update the task if it is still at this version, like `If-Match`.
+        #[serde(default)]
+        version: Option<i32>,
+        task: UpdateTask,
+    },
+    Delete {
+        id: i32,
+    },
+}
+
+impl Operation {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        match self {
+            Self::Create { task } => task.validate(),
+            Self::Update { task, .. } => task.validate(),
+            Self::Delete { .. } => Ok(()),
+        }
+    }
+}
+
+/// What a successful operation did.
+pub enum Applied {
+    Created(Task),
+    Updated(Task),
+    Deleted,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkResponse {
+    /// Whether the changes were saved: in atomic mode, nothing is as soon as
+    /// one operation fails.
+    pub committed: bool,
+    /// One per operation, in the same order.
+    pub results: Vec<BulkResult>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BulkResult {
+    /// The status code of the equivalent single request.
+    pub status: u16,
+    /// The created or updated task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Problem>,
+}
+
+impl From<Result<Applied, CustomError>> for BulkResult {
+    fn from(result: Result<Applied, CustomError>) -> Self {
+        let (status, task) = match result {
+            Ok(Applied::Created(task)) => (201, Some(task)),
+            Ok(Applied::Updated(task)) => (200, Some(task)),
+            Ok(Applied::Deleted) => (200, None),
+            Err(err) => {
+                return Self {
+                    status: err.status().as_u16(),
+                    task: None,
+                    error: Some(err.into_problem()),
+                }
+            }
+        };
+        Self {
+            status,
+            task,
+            error: None,
+        }
+    }
+}
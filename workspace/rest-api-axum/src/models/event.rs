@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::{IntoParams, ToSchema};
+
+use super::task::Task;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "task_event_kind", rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// A committed change to a task.
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct TaskEvent {
+    /// Increases with every change to the tasks of a user, in commit order.
+    /// Send the last one seen as `Last-Event-ID` to resume after it.
+    pub id: i64,
+    pub kind: EventKind,
+    pub task_id: i32,
+    #[serde(skip)]
+    pub owner_id: Option<i32>,
+    /// The task after the change, absent for deletions.
+    #[schema(value_type = Option<Task>)]
+    pub task: Option<Json<Task>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a reader of the events of everybody is, see
+/// [`EventRepository::follow_events`](crate::repository::EventRepository::follow_events).
+#[derive(Clone, Debug, Default)]
+pub struct EventCursor {
+    /// The last event read.
+    pub after: i64,
+    /// The transactions in flight when the events were last read, which may
+    /// still record events with ids up to `after`. Only used by Postgres,
+    /// where only the events of the same user are recorded in commit order.
+    pub in_flight: Vec<i64>,
+}
+
+/// Where a feed of events starts.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FeedParams {
+    /// Only send the events after this one, like the `Last-Event-ID` header,
+    /// which takes precedence. Without either, only new events are sent.
+    pub last_event_id: Option<i64>,
+    /// The access token, for clients that cannot send an `Authorization`
+    /// header, like browsers opening an `EventSource` or a WebSocket.
+    pub access_token: Option<String>,
+}
@@ -0,0 +1,28 @@
+/// A response stored for an idempotency key, to be replayed as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// What a request claiming an idempotency key found there instead: the
+/// fingerprint of the request holding it, and its response once there is one.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct IdempotencyRecord {
+    pub fingerprint: String,
+    pub status: Option<i16>,
+    pub content_type: Option<String>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl IdempotencyRecord {
+    /// The response of the request, `None` while it is still in progress.
+    pub fn response(self) -> Option<StoredResponse> {
+        Some(StoredResponse {
+            status: u16::try_from(self.status?).ok()?,
+            content_type: self.content_type,
+            body: self.body?,
+        })
+    }
+}
@@ -1 +1,14 @@
+pub mod audit;
+pub mod bulk;
+pub mod event;
+pub mod idempotency;
+pub mod page;
+pub mod project;
+pub mod rate_limit;
+pub mod recurrence;
+pub mod search;
+pub mod tag;
 pub mod task;
+pub mod transfer;
+pub mod user;
+pub mod webhook;
@@ -0,0 +1,33 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::task::Task;
+
+/// A single page of a listing, along with what a client needs to fetch the next one.
+#[derive(Serialize, ToSchema)]
+#[aliases(TaskPage = Page<Task>)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass as `cursor` to get the next page. `None` when this is the last page.
+    pub next_cursor: Option<i32>,
+    /// Number of rows matching the filter, regardless of pagination.
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    /// Builds a page out of up to `limit + 1` rows, the extra one only telling
+    /// that there is a next page.
+    pub fn from_rows(mut rows: Vec<T>, limit: i64, total: i64, id: impl Fn(&T) -> i32) -> Self {
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().map(id)
+        } else {
+            None
+        };
+        Self {
+            items: rows,
+            next_cursor,
+            total,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::errors::{CustomError, FieldError};
+
+/// Longest `name` the `varchar(255)` column accepts.
+pub const MAX_PROJECT_NAME_LEN: usize = 255;
+
+/// A group of tasks. Deleting it deletes its tasks for good.
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct Project {
+    pub id: i32,
+    #[serde(skip)]
+    pub owner_id: i32,
+    /// Unique among the projects of a user.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of both `POST /projects` and `PUT /projects/{id}`.
+#[derive(Deserialize, ToSchema)]
+pub struct NewProject {
+    #[schema(min_length = 1, max_length = 255)]
+    pub name: String,
+}
+
+impl NewProject {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        validate_name(&self.name, MAX_PROJECT_NAME_LEN)
+    }
+}
+
+/// Checks the name of a project or tag.
+pub(crate) fn validate_name(name: &str, max_len: usize) -> Result<(), CustomError> {
+    let message = if name.trim().is_empty() {
+        "must not be empty".to_owned()
+    } else if name.chars().count() > max_len {
+        format!("must be at most {max_len} characters")
+    } else {
+        return Ok(());
+    };
+    Err(CustomError::Validation(vec![FieldError::new(
+        "name", message,
+    )]))
+}
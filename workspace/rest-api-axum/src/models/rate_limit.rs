@@ -0,0 +1,157 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+
+/// At most `requests` requests per `period`, written `requests/seconds`,
+/// e.g. `600/60`. Bursts use up to the whole allowance at once.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Limit {
+    /// Tokens added back to a bucket per second.
+    fn rate(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+impl FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, secs) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected requests/seconds, got {s:?}"))?;
+        let requests = requests
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&requests| requests > 0)
+            .ok_or_else(|| format!("invalid number of requests in {s:?}"))?;
+        let secs = secs
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&secs| secs > 0)
+            .ok_or_else(|| format!("invalid number of seconds in {s:?}"))?;
+        Ok(Self {
+            requests,
+            period: Duration::from_secs(secs),
+        })
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.requests, self.period.as_secs())
+    }
+}
+
+/// The limit of a route, written `[METHOD] PATH=LIMIT`, e.g.
+/// `POST /task=10/60`. `PATH` is the route as declared in the router, like
+/// `/task/:id`, and without `METHOD` the rule applies to every method. A
+/// limit of `0` exempts the route from the default limit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub method: Option<Method>,
+    pub path: String,
+    pub limit: Option<Limit>,
+}
+
+impl Rule {
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        self.path == path && self.method.as_ref().is_none_or(|m| m == method)
+    }
+
+    /// What the rule applies to, which tells its buckets apart.
+    pub fn scope(&self) -> String {
+        match &self.method {
+            Some(method) => format!("{method} {}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (route, limit) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected [METHOD] PATH=LIMIT, got {s:?}"))?;
+        let limit = match limit.trim() {
+            "0" => None,
+            limit => Some(limit.parse()?),
+        };
+        let (method, path) = match route.trim().split_once(char::is_whitespace) {
+            Some((method, path)) => {
+                let method = method
+                    .parse()
+                    .map_err(|_| format!("invalid method in {s:?}"))?;
+                (Some(method), path.trim())
+            }
+            None => (None, route.trim()),
+        };
+        if !path.starts_with('/') {
+            return Err(format!("invalid path in {s:?}"));
+        }
+        Ok(Self {
+            method,
+            path: path.to_owned(),
+            limit,
+        })
+    }
+}
+
+/// The tokens left to a client, one per request.
+#[derive(Clone, Copy, Debug, sqlx::FromRow)]
+pub struct Bucket {
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Bucket {
+    /// Takes a token from `bucket`, or from a full one if the client has
+    /// none yet, returning what is left.
+    pub fn take(bucket: Option<Bucket>, limit: Limit, now: DateTime<Utc>) -> (Bucket, Decision) {
+        let capacity = f64::from(limit.requests);
+        let tokens = match bucket {
+            Some(bucket) => {
+                let elapsed = (now - bucket.updated_at).to_std().unwrap_or_default();
+                (bucket.tokens + elapsed.as_secs_f64() * limit.rate()).min(capacity)
+            }
+            None => capacity,
+        };
+        let allowed = tokens >= 1.0;
+        let tokens = if allowed { tokens - 1.0 } else { tokens };
+        let secs_until = |target: f64| ((target - tokens).max(0.0) / limit.rate()).ceil() as u64;
+        let decision = Decision {
+            allowed,
+            remaining: tokens.floor() as u32,
+            retry_after: Duration::from_secs(if allowed { 0 } else { secs_until(1.0) }),
+            reset: Duration::from_secs(secs_until(capacity)),
+        };
+        let bucket = Bucket {
+            tokens,
+            updated_at: now,
+        };
+        (bucket, decision)
+    }
+}
+
+/// Whether a request may go through, and what the client is told about its
+/// allowance.
+#[derive(Clone, Copy, Debug)]
+pub struct Decision {
+    pub allowed: bool,
+    pub remaining: u32,
+    /// How long until the next request may go through.
+    pub retry_after: Duration,
+    /// How long until the whole allowance is back.
+    pub reset: Duration,
+}
@@ -0,0 +1,325 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::database::{HasArguments, HasValueRef};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Database, Decode, Encode};
+
+/// Largest `INTERVAL` accepted.
+pub const MAX_INTERVAL: u32 = 1000;
+
+/// Periods looked through for the next occurrence before giving up, which
+/// only rules such as the 31st of every other February ever need.
+const MAX_PERIODS: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// A weekday of `BYDAY`, e.g. `MO`. In monthly rules, it may be preceded by
+/// its rank in the month, e.g. `1MO` for the first Monday or `-1FR` for the
+/// last Friday.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByDay {
+    pub rank: Option<i8>,
+    pub weekday: Weekday,
+}
+
+/// When a task repeats, as the subset of iCalendar RRULEs (RFC 5545) made of
+/// `FREQ` (`DAILY`, `WEEKLY` or `MONTHLY`), `INTERVAL` and `BYDAY`, e.g.
+/// `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`. Dates are counted in UTC.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Every how many days, weeks or months, at least 1.
+    pub interval: u32,
+    /// In daily rules, the only days to repeat on. In weekly and monthly
+    /// ones, the days of the week or month to repeat on, instead of those
+    /// of the start.
+    pub by_day: Vec<ByDay>,
+}
+
+impl Recurrence {
+    /// The first occurrence after `after` of the recurrence starting at
+    /// `start`, at the same time of day. `None` if there is none in sight.
+    pub fn next_after(&self, start: DateTime<Utc>, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let first = self.period_of(start, after);
+        (first..first.saturating_add(MAX_PERIODS)).find_map(|period| {
+            let mut occurrences: Vec<_> = self
+                .dates(start.date_naive(), period)
+                .into_iter()
+                .map(|date| date.and_time(start.time()).and_utc())
+                .filter(|&occurrence| occurrence >= start && occurrence > after)
+                .collect();
+            occurrences.sort_unstable();
+            occurrences.first().copied()
+        })
+    }
+
+    /// The period, counted from the one of `start`, that `after` falls in.
+    fn period_of(&self, start: DateTime<Utc>, after: DateTime<Utc>) -> u32 {
+        let (start, after) = (start.date_naive(), after.date_naive());
+        let units = match self.frequency {
+            Frequency::Daily => (after - start).num_days(),
+            Frequency::Weekly => (week_of(after) - week_of(start)).num_weeks(),
+            Frequency::Monthly => {
+                i64::from(after.year() - start.year()) * 12 + i64::from(after.month())
+                    - i64::from(start.month())
+            }
+        };
+        u32::try_from(units.max(0) / i64::from(self.interval)).unwrap_or(u32::MAX)
+    }
+
+    /// The dates of the recurrence in the `period`-th period after the one
+    /// of `start`, in no particular order.
+    fn dates(&self, start: NaiveDate, period: u32) -> Vec<NaiveDate> {
+        let units = u64::from(period) * u64::from(self.interval);
+        match self.frequency {
+            Frequency::Daily => start
+                .checked_add_days(Days::new(units))
+                .filter(|date| {
+                    self.by_day.is_empty()
+                        || self.by_day.iter().any(|day| day.weekday == date.weekday())
+                })
+                .into_iter()
+                .collect(),
+            Frequency::Weekly => {
+                let Some(monday) = week_of(start).checked_add_days(Days::new(units * 7)) else {
+                    return Vec::new();
+                };
+                let weekdays = match self.by_day.as_slice() {
+                    [] => vec![start.weekday()],
+                    days => days.iter().map(|day| day.weekday).collect(),
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        monday.checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let Some(month) = u32::try_from(units)
+                    .ok()
+                    .and_then(|units| start.with_day(1)?.checked_add_months(Months::new(units)))
+                else {
+                    return Vec::new();
+                };
+                if self.by_day.is_empty() {
+                    return month.with_day(start.day()).into_iter().collect();
+                }
+                self.by_day
+                    .iter()
+                    .flat_map(|day| days_in_month(month, *day))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The Monday of the week of `date`.
+fn week_of(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+/// The days of the month starting at `first` matching `day`.
+fn days_in_month(first: NaiveDate, day: ByDay) -> Vec<NaiveDate> {
+    let all: Vec<NaiveDate> = first
+        .iter_days()
+        .take_while(|date| date.month() == first.month())
+        .filter(|date| date.weekday() == day.weekday)
+        .collect();
+    match day.rank {
+        None => all,
+        Some(rank) if rank > 0 => all.get(rank as usize - 1).copied().into_iter().collect(),
+        Some(rank) => all
+            .len()
+            .checked_sub(rank.unsigned_abs() as usize)
+            .map(|index| all[index])
+            .into_iter()
+            .collect(),
+    }
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+impl FromStr for ByDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid BYDAY {s:?}");
+        let split = s
+            .len()
+            .checked_sub(2)
+            .filter(|&split| s.is_char_boundary(split))
+            .ok_or_else(invalid)?;
+        let (rank, weekday) = s.split_at(split);
+        let weekday = WEEKDAYS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(weekday))
+            .map(|&(_, weekday)| weekday)
+            .ok_or_else(invalid)?;
+        let rank = match rank {
+            "" => None,
+            rank => Some(
+                rank.parse()
+                    .ok()
+                    .filter(|rank: &i8| (1..=5).contains(&rank.unsigned_abs()))
+                    .ok_or_else(invalid)?,
+            ),
+        };
+        Ok(Self { rank, weekday })
+    }
+}
+
+impl fmt::Display for ByDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rank) = self.rank {
+            write!(f, "{rank}")?;
+        }
+        let (name, _) = WEEKDAYS[self.weekday.num_days_from_monday() as usize];
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = match s.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &s[6..],
+            _ => s,
+        };
+        let (mut frequency, mut interval, mut by_day) = (None, None, None);
+        for part in rule.split(';') {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected NAME=VALUE, got {part:?}"))?;
+            let name = name.to_ascii_uppercase();
+            let duplicate = match name.as_str() {
+                "FREQ" => frequency
+                    .replace(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(format!("unsupported FREQ {value:?}")),
+                    })
+                    .is_some(),
+                "INTERVAL" => interval
+                    .replace(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|interval| (1..=MAX_INTERVAL).contains(interval))
+                            .ok_or_else(|| {
+                                format!("INTERVAL must be between 1 and {MAX_INTERVAL}")
+                            })?,
+                    )
+                    .is_some(),
+                "BYDAY" => {
+                    let mut days = Vec::new();
+                    for day in value.split(',') {
+                        let day: ByDay = day.parse()?;
+                        if !days.contains(&day) {
+                            days.push(day);
+                        }
+                    }
+                    by_day.replace(days).is_some()
+                }
+                _ => return Err(format!("unsupported rule part {name}")),
+            };
+            if duplicate {
+                return Err(format!("{name} is given twice"));
+            }
+        }
+        let frequency = frequency.ok_or("FREQ is missing")?;
+        let by_day = by_day.unwrap_or_default();
+        if frequency != Frequency::Monthly && by_day.iter().any(|day| day.rank.is_some()) {
+            return Err("BYDAY only takes ranks in monthly rules".to_owned());
+        }
+        Ok(Self {
+            frequency,
+            interval: interval.unwrap_or(1),
+            by_day,
+        })
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.frequency {
+            Frequency::Daily => "FREQ=DAILY",
+            Frequency::Weekly => "FREQ=WEEKLY",
+            Frequency::Monthly => "FREQ=MONTHLY",
+        })?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        for (i, day) in self.by_day.iter().enumerate() {
+            f.write_str(if i == 0 { ";BYDAY=" } else { "," })?;
+            write!(f, "{day}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+// Stored as the text of the rule, on every backend.
+impl<DB: Database> sqlx::Type<DB> for Recurrence
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: Database> Decode<'r, DB> for Recurrence
+where
+    String: Decode<'r, DB>,
+{
+    fn decode(value: <DB as HasValueRef<'r>>::ValueRef) -> Result<Self, BoxDynError> {
+        Ok(String::decode(value)?.parse()?)
+    }
+}
+
+impl<'q, DB: Database> Encode<'q, DB> for Recurrence
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(&self, buf: &mut <DB as HasArguments<'q>>::ArgumentBuffer) -> IsNull {
+        self.to_string().encode(buf)
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::task::{Task, MAX_TASK_LEN};
+use crate::errors::{CustomError, FieldError};
+
+pub const DEFAULT_SEARCH_LIMIT: i64 = 20;
+pub const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// Delimit matches in snippets until they are turned into HTML, as the task
+/// text cannot be escaped before the database highlights it.
+pub const MATCH_START: char = '\u{2}';
+pub const MATCH_END: char = '\u{3}';
+/// Stands for `<` in the text Postgres indexes and highlights, as it would
+/// otherwise skip anything looking like an HTML tag.
+pub const LESS_THAN: char = '\u{1}';
+
+/// Query parameters of `GET /tasks/search`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    /// The words to look for. On Postgres, this also accepts the web search
+    /// syntax: `"quoted phrases"`, `or` and `-excluded` words.
+    #[serde(default)]
+    #[param(min_length = 1, max_length = 255)]
+    pub q: String,
+    /// Maximum number of results, 20 by default.
+    #[param(minimum = 1, maximum = 100)]
+    pub limit: Option<i64>,
+}
+
+impl SearchParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)
+    }
+
+    /// The distinct words of `q`, for backends without full-text search.
+    pub fn terms(&self) -> Vec<&str> {
+        let mut terms: Vec<&str> = Vec::new();
+        for term in self.q.split_whitespace() {
+            if !terms
+                .iter()
+                .any(|t| t.to_lowercase() == term.to_lowercase())
+            {
+                terms.push(term);
+            }
+        }
+        terms
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let mut errors = Vec::new();
+        if self.q.trim().is_empty() {
+            errors.push(FieldError::new("q", "must not be empty"));
+        } else if self.q.chars().count() > MAX_TASK_LEN {
+            errors.push(FieldError::new(
+                "q",
+                format!("must be at most {MAX_TASK_LEN} characters"),
+            ));
+        }
+        if !(1..=MAX_SEARCH_LIMIT).contains(&self.limit()) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_SEARCH_LIMIT}"),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CustomError::Validation(errors))
+        }
+    }
+}
+
+/// A task matching a search.
+#[derive(Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct SearchHit {
+    #[sqlx(flatten)]
+    pub task: Task,
+    /// How well the task matches, higher is better. Absent when the backend
+    /// has no full-text search, in which case the newest tasks come first.
+    pub rank: Option<f32>,
+    /// The matching part of the text as HTML, with matches in `<mark>` elements.
+    pub snippet: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchResults {
+    pub results: Vec<SearchHit>,
+}
+
+/// Turns text with matches between [`MATCH_START`] and [`MATCH_END`] into
+/// HTML, escaping everything else.
+pub fn snippet_html(marked: &str) -> String {
+    let mut html = String::with_capacity(marked.len() + 16);
+    for c in marked.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' | LESS_THAN => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+/// Delimits every occurrence of `terms` in `text`, ignoring case, the way
+/// Postgres does for full-text matches.
+pub fn mark_terms(text: &str, terms: &[&str]) -> String {
+    let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+    let mut marked = String::with_capacity(text.len() + 8);
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let longest = terms
+            .iter()
+            .filter_map(|term| match_len(rest, term))
+            .max()
+            .unwrap_or(0);
+        if longest > 0 {
+            marked.push(MATCH_START);
+            marked.push_str(&rest[..longest]);
+            marked.push(MATCH_END);
+            rest = &rest[longest..];
+        } else {
+            marked.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    marked
+}
+
+/// The length in bytes of the prefix of `text` equal to the lowercase `term`,
+/// ignoring case, if there is one.
+fn match_len(text: &str, term: &str) -> Option<usize> {
+    let mut expected = term.chars();
+    let mut len = 0;
+    for c in text.chars() {
+        if expected.as_str().is_empty() {
+            break;
+        }
+        for lower in c.to_lowercase() {
+            if expected.next() != Some(lower) {
+                return None;
+            }
+        }
+        len += c.len_utf8();
+    }
+    (expected.as_str().is_empty() && len > 0).then_some(len)
+}
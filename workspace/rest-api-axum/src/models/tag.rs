@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::project::validate_name;
+use crate::errors::CustomError;
+
+/// Longest `name` the `varchar(64)` column accepts.
+pub const MAX_TAG_NAME_LEN: usize = 64;
+
+/// A label put on any number of tasks. Deleting it only takes it off them.
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct Tag {
+    pub id: i32,
+    #[serde(skip)]
+    pub owner_id: i32,
+    /// Unique among the tags of a user, and how `GET /tasks?tag=` refers to it.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of both `POST /tags` and `PUT /tags/{id}`.
+#[derive(Deserialize, ToSchema)]
+pub struct NewTag {
+    #[schema(min_length = 1, max_length = 64)]
+    pub name: String,
+}
+
+impl NewTag {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        validate_name(&self.name, MAX_TAG_NAME_LEN)
+    }
+}
@@ -1,17 +1,292 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(sqlx::FromRow, Deserialize, Serialize)]
+use crate::errors::{CustomError, FieldError};
+use crate::models::recurrence::Recurrence;
+
+/// Longest `task` text the `varchar(255)` column accepts.
+pub const MAX_TASK_LEN: usize = 255;
+pub const MAX_PRIORITY: i16 = 3;
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "task_status", rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Todo,
+    InProgress,
+    Done,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow, Deserialize, Serialize, ToSchema)]
 pub struct Task {
     pub id: i32,
     pub task: String,
+    pub status: Status,
+    /// From 0 (none) to 3 (high).
+    #[schema(minimum = 0, maximum = 3)]
+    pub priority: i16,
+    pub due_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Bumped on every update, used as the `ETag` of the task.
+    pub version: i32,
+    /// The user who created the task. Only tasks created before authentication
+    /// was introduced have none.
+    pub owner_id: Option<i32>,
+    /// When the task was moved to the trash, if it is there.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The project the task belongs to, if any.
+    pub project_id: Option<i32>,
+    /// The task this one is a subtask of, if any. Set at creation.
+    pub parent_id: Option<i32>,
+    /// How the task repeats, if it does. Once it is done, its next occurrence
+    /// is created, see [`Task::next_due_at`].
+    #[schema(value_type = Option<String>, example = "FREQ=WEEKLY;BYDAY=MO")]
+    pub recurrence: Option<Recurrence>,
 }
 
-#[derive(sqlx::FromRow, Deserialize, Serialize)]
+impl Task {
+    /// When the next occurrence of the task is due: the first occurrence of
+    /// its recurrence, counted from its due date, after both that date and
+    /// its completion. Tasks without a due date recur from their completion,
+    /// taken to be their last update. `None` if the task does not recur.
+    pub fn next_due_at(&self) -> Option<DateTime<Utc>> {
+        let recurrence = self.recurrence.as_ref()?;
+        let start = self.due_at.unwrap_or(self.updated_at);
+        recurrence.next_after(start, start.max(self.updated_at))
+    }
+}
+
+#[derive(sqlx::FromRow, Deserialize, Serialize, ToSchema)]
 pub struct NewTask {
+    #[schema(min_length = 1, max_length = 255)]
     pub task: String,
+    #[serde(default)]
+    pub status: Status,
+    #[serde(default)]
+    #[schema(minimum = 0, maximum = 3)]
+    pub priority: i16,
+    pub due_at: Option<DateTime<Utc>>,
+    /// One of the caller's projects.
+    pub project_id: Option<i32>,
+    /// One of the caller's tasks, making this one a subtask of it.
+    pub parent_id: Option<i32>,
+    /// An RRULE with `FREQ` (`DAILY`, `WEEKLY` or `MONTHLY`), and optionally
+    /// `INTERVAL` and `BYDAY`.
+    #[schema(value_type = Option<String>, example = "FREQ=WEEKLY;BYDAY=MO")]
+    pub recurrence: Option<Recurrence>,
+}
+
+impl NewTask {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        validate(&self.task, self.priority)
+    }
 }
 
-#[derive(Deserialize, Serialize, sqlx::FromRow)]
+#[derive(Deserialize, Serialize, sqlx::FromRow, ToSchema)]
 pub struct UpdateTask {
+    #[schema(min_length = 1, max_length = 255)]
     pub task: String,
+    #[serde(default)]
+    pub status: Status,
+    #[serde(default)]
+    #[schema(minimum = 0, maximum = 3)]
+    pub priority: i16,
+    pub due_at: Option<DateTime<Utc>>,
+    /// One of the caller's projects. Absent takes the task out of its project.
+    pub project_id: Option<i32>,
+    /// Absent stops the task from recurring.
+    #[schema(value_type = Option<String>, example = "FREQ=WEEKLY;BYDAY=MO")]
+    pub recurrence: Option<Recurrence>,
+}
+
+impl UpdateTask {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        validate(&self.task, self.priority)
+    }
+}
+
+/// A JSON Merge Patch (RFC 7396) over the writable fields of a [`Task`].
+#[derive(Deserialize, Default, ToSchema)]
+pub struct TaskPatch {
+    // The outer `Option` tells whether a key was present, the inner one whether it was `null`.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = String, min_length = 1, max_length = 255)]
+    pub task: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Status)]
+    pub status: Option<Option<Status>>,
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = i16, minimum = 0, maximum = 3)]
+    pub priority: Option<Option<i16>>,
+    /// `null` removes the due date.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    /// `null` takes the task out of its project.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<i32>)]
+    pub project_id: Option<Option<i32>>,
+    /// `null` stops the task from recurring.
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>)]
+    pub recurrence: Option<Option<Recurrence>>,
+}
+
+impl TaskPatch {
+    /// Applies the patch on top of `task`, returning the full set of fields to store.
+    pub fn apply(self, task: &Task) -> Result<UpdateTask, CustomError> {
+        let update = UpdateTask {
+            // Only the optional fields are nullable, so removing any other member is an error.
+            task: match self.task {
+                None => task.task.clone(),
+                Some(value) => value.ok_or_else(|| must_not_be_null("task"))?,
+            },
+            status: match self.status {
+                None => task.status,
+                Some(value) => value.ok_or_else(|| must_not_be_null("status"))?,
+            },
+            priority: match self.priority {
+                None => task.priority,
+                Some(value) => value.ok_or_else(|| must_not_be_null("priority"))?,
+            },
+            due_at: self.due_at.unwrap_or(task.due_at),
+            project_id: self.project_id.unwrap_or(task.project_id),
+            recurrence: self.recurrence.unwrap_or_else(|| task.recurrence.clone()),
+        };
+        update.validate()?;
+        Ok(update)
+    }
+}
+
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Default, Clone, Copy, ToSchema)]
+pub enum Sort {
+    #[default]
+    #[serde(rename = "id")]
+    IdAsc,
+    #[serde(rename = "-id")]
+    IdDesc,
+    #[serde(rename = "task")]
+    Task,
+}
+
+/// Which tasks to list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scope {
+    #[default]
+    Active,
+    /// Those in the trash, until they are restored or purged.
+    Trash,
+}
+
+/// Query parameters of `GET /tasks`, `GET /tasks/trash` and the lists of
+/// tasks nested in a project or task.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListParams {
+    /// Page size, 50 by default.
+    #[param(minimum = 1, maximum = 500)]
+    pub limit: Option<i64>,
+    #[param(minimum = 0)]
+    pub offset: Option<i64>,
+    /// Keyset pagination: the `next_cursor` returned by the previous page.
+    /// With `sort=task`, rejected once that task is purged.
+    pub cursor: Option<i32>,
+    /// Case-insensitive substring filter on `task`.
+    pub q: Option<String>,
+    /// Only the tasks of this project.
+    pub project: Option<i32>,
+    /// Only the subtasks of this task.
+    pub parent: Option<i32>,
+    /// Only the tasks with the tag of this name.
+    pub tag: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: Sort,
+}
+
+impl ListParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    /// The substring filter, if there is anything to filter on.
+    pub fn q(&self) -> Option<&str> {
+        self.q.as_deref().filter(|q| !q.is_empty())
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit()) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if self.offset.is_some_and(|o| o < 0) {
+            errors.push(FieldError::new("offset", "must not be negative"));
+        }
+        // Offsets and cursors are two ways of saying where the page starts.
+        if self.offset.is_some() && self.cursor.is_some() {
+            errors.push(FieldError::new("cursor", "cannot be combined with offset"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CustomError::Validation(errors))
+        }
+    }
+}
+
+/// Query parameters of `DELETE /task/{id}`.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteParams {
+    /// Deletes the task for good instead of moving it to the trash. Also
+    /// works on tasks already in the trash.
+    #[serde(default)]
+    pub purge: bool,
+}
+
+fn must_not_be_null(field: &'static str) -> CustomError {
+    CustomError::Validation(vec![FieldError::new(field, "must not be null")])
+}
+
+fn validate(task: &str, priority: i16) -> Result<(), CustomError> {
+    let mut errors = Vec::new();
+    if task.trim().is_empty() {
+        errors.push(FieldError::new("task", "must not be empty"));
+    } else if task.chars().count() > MAX_TASK_LEN {
+        errors.push(FieldError::new(
+            "task",
+            format!("must be at most {MAX_TASK_LEN} characters"),
+        ));
+    }
+    if !(0..=MAX_PRIORITY).contains(&priority) {
+        errors.push(FieldError::new(
+            "priority",
+            format!("must be between 0 and {MAX_PRIORITY}"),
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CustomError::Validation(errors))
+    }
 }
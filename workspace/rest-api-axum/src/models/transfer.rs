@@ -0,0 +1,269 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::{IntoParams, ToSchema};
+
+use super::task::{NewTask, Task};
+use crate::csv;
+use crate::errors::{CustomError, Problem};
+
+/// The columns of CSV exports. Imports only read those of a [`NewTask`], the
+/// others being set anew.
+pub const COLUMNS: [&str; 10] = [
+    "id",
+    "task",
+    "status",
+    "priority",
+    "due_at",
+    "created_at",
+    "updated_at",
+    "project_id",
+    "parent_id",
+    "recurrence",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// With a header row naming the columns.
+    Csv,
+    /// One task per line.
+    Ndjson,
+    /// A single array of tasks.
+    #[default]
+    Json,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+            Self::Json => "application/json",
+        }
+    }
+
+    /// The format of an upload, from its `Content-Type`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next()?.trim();
+        match essence.to_ascii_lowercase().as_str() {
+            "text/csv" => Some(Self::Csv),
+            "application/x-ndjson" => Some(Self::Ndjson),
+            "application/json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Json => "json",
+        }
+    }
+
+    /// What comes before the first task.
+    pub fn header(self) -> String {
+        match self {
+            Self::Csv => csv::record(COLUMNS),
+            Self::Ndjson => String::new(),
+            Self::Json => "[".to_owned(),
+        }
+    }
+
+    /// `tasks`, the first ones of the export when `first`.
+    pub fn rows(self, tasks: &[Task], first: bool) -> String {
+        let mut out = String::new();
+        for (i, task) in tasks.iter().enumerate() {
+            match self {
+                Self::Csv => out.push_str(&csv_record(task)),
+                Self::Ndjson => {
+                    out.push_str(&serde_json::to_string(task).unwrap());
+                    out.push('\n');
+                }
+                Self::Json => {
+                    if !(first && i == 0) {
+                        out.push(',');
+                    }
+                    out.push_str(&serde_json::to_string(task).unwrap());
+                }
+            }
+        }
+        out
+    }
+
+    /// What comes after the last task.
+    pub fn footer(self) -> String {
+        match self {
+            Self::Csv | Self::Ndjson => String::new(),
+            Self::Json => "]".to_owned(),
+        }
+    }
+
+    /// The tasks of an upload, each with the line it starts on. Rows that are
+    /// malformed or invalid are rejected one by one, but an upload that cannot
+    /// be split into rows is rejected as a whole.
+    pub fn parse(self, body: &str) -> Result<Vec<Row>, CustomError> {
+        let rows = match self {
+            Self::Csv => parse_csv(body)?,
+            Self::Ndjson => body
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, line)| {
+                    let value = serde_json::from_str(line)
+                        .map_err(|err| CustomError::BadRequest(err.to_string()));
+                    (i + 1, value)
+                })
+                .collect(),
+            Self::Json => parse_array(body)?,
+        };
+        Ok(rows
+            .into_iter()
+            .map(|(line, value)| Row {
+                line,
+                task: value.and_then(new_task),
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParams {
+    /// Defaults to `json`.
+    #[serde(default)]
+    pub format: Format,
+}
+
+/// A task of an upload.
+pub struct Row {
+    pub line: usize,
+    pub task: Result<NewTask, CustomError>,
+}
+
+/// Body of the response to `POST /tasks/import`.
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+    /// Number of tasks created.
+    pub imported: usize,
+    /// The rows that were not imported, in order.
+    pub rejected: Vec<RejectedRow>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RejectedRow {
+    /// The line of the upload the row starts on, from 1. In CSV uploads, the
+    /// header row is line 1.
+    pub line: usize,
+    pub error: Problem,
+}
+
+fn csv_record(task: &Task) -> String {
+    let status = serde_json::to_value(task.status).unwrap();
+    let fields = [
+        task.id.to_string(),
+        task.task.clone(),
+        status.as_str().unwrap_or_default().to_owned(),
+        task.priority.to_string(),
+        task.due_at.map(timestamp).unwrap_or_default(),
+        timestamp(task.created_at),
+        timestamp(task.updated_at),
+        task.project_id.map(|id| id.to_string()).unwrap_or_default(),
+        task.parent_id.map(|id| id.to_string()).unwrap_or_default(),
+        task.recurrence
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+    ];
+    csv::record(fields.iter().map(String::as_str))
+}
+
+/// Like the JSON representation of times.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+type Parsed = (usize, Result<Value, CustomError>);
+
+/// Turns each CSV record into the JSON object it stands for, empty cells
+/// being left out but for the required `task`.
+fn parse_csv(body: &str) -> Result<Vec<Parsed>, CustomError> {
+    let mut records = csv::records(body)
+        .map_err(CustomError::BadRequest)?
+        .into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    if !header.iter().any(|column| column == "task") {
+        return Err(CustomError::BadRequest(
+            "the header row has no task column".to_owned(),
+        ));
+    }
+    Ok(records
+        .map(|(line, fields)| {
+            if fields.len() != header.len() {
+                let message = format!("expected {} fields, found {}", header.len(), fields.len());
+                return (line, Err(CustomError::BadRequest(message)));
+            }
+            let object: Map<String, Value> = header
+                .iter()
+                .zip(fields)
+                .filter(|(column, field)| !field.is_empty() || *column == "task")
+                .map(|(column, field)| {
+                    let value = match column.as_str() {
+                        "priority" | "project_id" | "parent_id" => field
+                            .parse::<i64>()
+                            .map_or(Value::String(field), Value::from),
+                        _ => Value::String(field),
+                    };
+                    (column.clone(), value)
+                })
+                .collect();
+            (line, Ok(Value::Object(object)))
+        })
+        .collect())
+}
+
+/// Splits a JSON array into its items, without reading them as tasks yet.
+fn parse_array(body: &str) -> Result<Vec<Parsed>, CustomError> {
+    let malformed =
+        |message: &str| CustomError::BadRequest(format!("expected a JSON array, {message}"));
+    let mut rest = body.trim_start();
+    rest = rest
+        .strip_prefix('[')
+        .ok_or_else(|| malformed("found no opening bracket"))?;
+    let mut items = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix(']') {
+            if !after.trim().is_empty() {
+                return Err(malformed("found more after the closing bracket"));
+            }
+            return Ok(items);
+        }
+        if !items.is_empty() {
+            rest = rest
+                .strip_prefix(',')
+                .ok_or_else(|| malformed("found no comma between items"))?
+                .trim_start();
+        }
+        let line = body[..body.len() - rest.len()].matches('\n').count() + 1;
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        let value = match stream.next() {
+            Some(Ok(value)) => value,
+            Some(Err(err)) => return Err(CustomError::BadRequest(format!("line {line}: {err}"))),
+            None => return Err(malformed("found no closing bracket")),
+        };
+        rest = &rest[stream.byte_offset()..];
+        items.push((line, Ok(value)));
+    }
+}
+
+/// Reads `value` as a task to create, checked like those of `POST /task`.
+fn new_task(value: Value) -> Result<NewTask, CustomError> {
+    let task: NewTask =
+        serde_json::from_value(value).map_err(|err| CustomError::BadRequest(err.to_string()))?;
+    task.validate()?;
+    Ok(task)
+}
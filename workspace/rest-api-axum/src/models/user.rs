@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::errors::{CustomError, FieldError};
+
+pub const MAX_EMAIL_LEN: usize = 255;
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct User {
+    pub id: i32,
+    pub email: String,
+    #[serde(skip)]
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of both `POST /auth/register` and `POST /auth/login`.
+#[derive(Deserialize, ToSchema)]
+pub struct Credentials {
+    #[schema(max_length = 255)]
+    pub email: String,
+    #[schema(min_length = 8)]
+    pub password: String,
+}
+
+impl Credentials {
+    pub fn email(&self) -> String {
+        normalize_email(&self.email)
+    }
+
+    /// Checks the rules for new accounts. Login does not, so that tightening
+    /// them never locks existing users out.
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let mut errors = Vec::new();
+        let email = self.email();
+        if email.len() > MAX_EMAIL_LEN || !email.contains('@') {
+            errors.push(FieldError::new("email", "must be a valid email address"));
+        }
+        if self.password.chars().count() < MIN_PASSWORD_LEN {
+            errors.push(FieldError::new(
+                "password",
+                format!("must be at least {MIN_PASSWORD_LEN} characters"),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CustomError::Validation(errors))
+        }
+    }
+}
+
+/// Emails are compared case-insensitively, so they are stored in lowercase.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    #[schema(example = "Bearer")]
+    pub token_type: &'static str,
+    /// Lifetime of the access token, in seconds.
+    pub expires_in: u64,
+}
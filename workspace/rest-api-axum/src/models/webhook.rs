@@ -0,0 +1,221 @@
+use axum::http::Uri;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use utoipa::{IntoParams, ToSchema};
+
+use super::event::{EventKind, TaskEvent};
+use super::task::{Task, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::errors::{CustomError, FieldError};
+
+/// Longest `url` accepted.
+pub const MAX_URL_LEN: usize = 2048;
+
+/// An endpoint the events of the tasks of its owner are sent to, see
+/// [`crate::webhooks`].
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct Webhook {
+    pub id: i32,
+    #[serde(skip)]
+    pub owner_id: i32,
+    /// Where the events are POSTed.
+    #[schema(example = "https://example.com/hooks/tasks")]
+    pub url: String,
+    /// The kinds of events sent, any other is left out.
+    #[schema(value_type = Vec<EventKind>)]
+    pub events: Json<Vec<EventKind>>,
+    /// Signs every delivery, only ever returned on creation.
+    #[serde(skip)]
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The response to `POST /webhooks`: the webhook, along with its secret.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// The key of the HMAC-SHA256 signature in the `X-Webhook-Signature` of
+    /// every delivery. It cannot be retrieved later.
+    pub secret: String,
+}
+
+/// Body of `POST /webhooks`.
+#[derive(Deserialize, ToSchema)]
+pub struct NewWebhook {
+    /// An `http` or `https` URL, on a public address.
+    #[schema(max_length = 2048, example = "https://example.com/hooks/tasks")]
+    pub url: String,
+    /// The kinds of events to send, every kind by default.
+    pub events: Option<Vec<EventKind>>,
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let mut errors = Vec::new();
+        if self.url.len() > MAX_URL_LEN {
+            errors.push(FieldError::new(
+                "url",
+                format!("must be at most {MAX_URL_LEN} bytes"),
+            ));
+        } else if !self.url.parse::<Uri>().is_ok_and(|uri| {
+            matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+        }) {
+            errors.push(FieldError::new(
+                "url",
+                "must be an absolute http or https URL",
+            ));
+        }
+        if self.events.as_ref().is_some_and(Vec::is_empty) {
+            errors.push(FieldError::new("events", "must not be empty"));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CustomError::Validation(errors))
+        }
+    }
+
+    /// The kinds of events to send, without duplicates.
+    pub fn events(&self) -> Vec<EventKind> {
+        let mut events = Vec::new();
+        for kind in self.events.as_deref().unwrap_or(&[
+            EventKind::Created,
+            EventKind::Updated,
+            EventKind::Deleted,
+        ]) {
+            if !events.contains(kind) {
+                events.push(*kind);
+            }
+        }
+        events
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Still to be sent, or sent again after a failure.
+    Pending,
+    /// Accepted by the webhook with a 2xx response.
+    Delivered,
+    /// Given up on after too many failures, until redelivered.
+    Dead,
+}
+
+/// The sending of an event to a webhook.
+#[derive(Clone, Debug, sqlx::FromRow, Serialize, ToSchema)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    /// The `id` of the event sent.
+    pub event_id: i64,
+    pub kind: EventKind,
+    pub task_id: i32,
+    pub status: DeliveryStatus,
+    /// How many times the event was sent since it was last (re)delivered.
+    pub attempts: i32,
+    /// When the event is sent next, while pending.
+    pub next_attempt_at: DateTime<Utc>,
+    /// The status code of the last response, absent if there was none.
+    pub response_status: Option<i32>,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A delivery claimed to be sent, with everything needed to send it.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub attempts: i32,
+    pub event_id: i64,
+    pub kind: EventKind,
+    pub task_id: i32,
+    pub task: Option<Json<Task>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DueDelivery {
+    /// The event to send, as the feed of events has it.
+    pub fn event(&self) -> TaskEvent {
+        TaskEvent {
+            id: self.event_id,
+            kind: self.kind,
+            task_id: self.task_id,
+            owner_id: None,
+            task: self.task.clone(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// The outcome of sending a delivery, to be recorded.
+#[derive(Clone, Debug)]
+pub struct Attempt {
+    /// `Pending` to try again at `next_attempt_at`.
+    pub status: DeliveryStatus,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Query parameters of `GET /webhooks/{id}/deliveries`.
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryParams {
+    /// Only the deliveries in this state, e.g. `dead` for those given up on.
+    pub status: Option<DeliveryStatus>,
+    /// Page size, 50 by default.
+    #[param(minimum = 1, maximum = 500)]
+    pub limit: Option<i64>,
+    /// The `next_cursor` returned by the previous page.
+    pub cursor: Option<i64>,
+}
+
+impl DeliveryParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    pub fn validate(&self) -> Result<(), CustomError> {
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit()) {
+            return Err(CustomError::Validation(vec![FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            )]));
+        }
+        Ok(())
+    }
+}
+
+/// A page of the deliveries of a webhook, newest first.
+#[derive(Serialize, ToSchema)]
+pub struct DeliveryPage {
+    pub items: Vec<Delivery>,
+    /// Pass as `cursor` to get the next page. `None` when this is the last page.
+    pub next_cursor: Option<i64>,
+}
+
+impl DeliveryPage {
+    /// Builds a page out of up to `limit + 1` deliveries, the extra one only
+    /// telling that there is a next page.
+    pub fn from_rows(mut rows: Vec<Delivery>, limit: i64) -> Self {
+        let next_cursor = if rows.len() as i64 > limit {
+            rows.truncate(limit as usize);
+            rows.last().map(|delivery| delivery.id)
+        } else {
+            None
+        };
+        Self {
+            items: rows,
+            next_cursor,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// The OpenAPI 3 description of the API, served at `/openapi.json` and
+// browsable at `/docs`.
+//
+// Operations are described next to their handler with `#[utoipa::path]` and
+// schemas are derived from the models, so both change along with the code.
+// Operations refer to schemas by their bare name (`body = Task`), which needs
+// no import: utoipa turns a path into the name of the schema as written.
+// `tests/openapi.rs` keeps the checked-in `openapi.json` in sync for the
+// clients generated from it.
+
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::errors::{FieldError, Problem};
+use crate::models::audit::{AuditAction, AuditEntry, AuditPage};
+use crate::models::bulk::{BulkMode, BulkRequest, BulkResponse, BulkResult, Operation};
+use crate::models::event::{EventKind, TaskEvent};
+use crate::models::page::TaskPage;
+use crate::models::project::{NewProject, Project};
+use crate::models::search::{SearchHit, SearchResults};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{NewTask, Status, Task, TaskPatch, UpdateTask};
+use crate::models::transfer::{Format, ImportReport, RejectedRow};
+use crate::models::user::{Credentials, RefreshRequest, TokenResponse, User};
+use crate::models::webhook::{
+    CreatedWebhook, Delivery, DeliveryPage, DeliveryStatus, NewWebhook, Webhook,
+};
+use crate::routes;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Tasks API",
+        description = "A simple REST API using axum and sqlx. Every error is an RFC 7807 \
+                       `application/problem+json` body, which may also be a 500, 503 or 504 \
+                       when the database fails. Clients going over their rate limit get a 429 \
+                       with `Retry-After`, and every limited response tells where the client \
+                       stands in `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset`. \
+                       Every response carries the `X-Request-Id` of its request, the one the \
+                       client sent or else a generated one, to look it up in the logs.",
+    ),
+    paths(
+        crate::root,
+        routes::auth::register::handler,
+        routes::auth::login::handler,
+        routes::auth::refresh::handler,
+        routes::tasks::get_tasks::handler,
+        routes::tasks::create_task::handler,
+        routes::tasks::search_tasks::handler,
+        routes::tasks::get_trash::handler,
+        routes::tasks::bulk_tasks::handler,
+        routes::tasks::export_tasks::handler,
+        routes::tasks::import_tasks::handler,
+        routes::tasks::task_events::handler,
+        routes::tasks::task_socket::handler,
+        routes::tasks::get_task::handler,
+        routes::tasks::update_task::handler,
+        routes::tasks::patch_task::handler,
+        routes::tasks::delete_task::handler,
+        routes::tasks::restore_task::handler,
+        routes::tasks::task_history::handler,
+        routes::tasks::get_subtasks::handler,
+        routes::tasks::create_subtask::handler,
+        routes::tasks::get_task_tags::handler,
+        routes::tasks::tag_task::handler,
+        routes::tasks::untag_task::handler,
+        routes::projects::get_projects::handler,
+        routes::projects::create_project::handler,
+        routes::projects::get_project::handler,
+        routes::projects::update_project::handler,
+        routes::projects::delete_project::handler,
+        routes::projects::get_project_tasks::handler,
+        routes::projects::create_project_task::handler,
+        routes::tags::get_tags::handler,
+        routes::tags::create_tag::handler,
+        routes::tags::update_tag::handler,
+        routes::tags::delete_tag::handler,
+        routes::audit::get_audit::handler,
+        routes::webhooks::get_webhooks::handler,
+        routes::webhooks::create_webhook::handler,
+        routes::webhooks::delete_webhook::handler,
+        routes::webhooks::get_deliveries::handler,
+        routes::webhooks::redeliver::handler,
+        routes::health::healthz::handler,
+        routes::health::readyz::handler,
+        routes::health::metrics::handler,
+    ),
+    components(schemas(
+        Task,
+        NewTask,
+        UpdateTask,
+        TaskPatch,
+        TaskPage,
+        SearchResults,
+        SearchHit,
+        Status,
+        BulkRequest,
+        BulkMode,
+        Operation,
+        BulkResponse,
+        BulkResult,
+        Format,
+        ImportReport,
+        RejectedRow,
+        TaskEvent,
+        EventKind,
+        AuditPage,
+        AuditEntry,
+        AuditAction,
+        Webhook,
+        NewWebhook,
+        CreatedWebhook,
+        Delivery,
+        DeliveryPage,
+        DeliveryStatus,
+        Project,
+        NewProject,
+        Tag,
+        NewTag,
+        User,
+        Credentials,
+        RefreshRequest,
+        TokenResponse,
+        Problem,
+        FieldError,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Accounts and access tokens"),
+        (name = "tasks", description = "The tasks of the authenticated user"),
+        (name = "projects", description = "The projects of the authenticated user and their tasks"),
+        (name = "tags", description = "The tags of the authenticated user"),
+        (name = "audit", description = "Who changed the tasks of the authenticated user, and how"),
+        (name = "webhooks", description = "Endpoints the events of the tasks of the authenticated user are POSTed to, and their deliveries"),
+        (name = "health", description = "Probes and metrics for operators, neither authenticated nor rate limited"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// Declares the `bearer` scheme referenced by the `security` of operations.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Rate limiting, so that no single client can take up the whole connection pool.
+//
+// Every client has a token bucket per limit: one for the routes without a
+// rule of their own (`RATE_LIMIT`), and one per rule (`RATE_LIMIT_ROUTES`).
+// Each request takes a token, or is turned down with a 429 when there is none
+// left, and tokens come back continuously, the whole allowance over the
+// period of the limit. Clients are told apart by their user when they send a
+// valid access token, and by their IP address otherwise.
+//
+// Buckets live in the memory of each instance or, with `RATE_LIMIT_SHARED`,
+// in the database for all instances to share.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, MatchedPath, State};
+use axum::http::header::{HeaderName, HeaderValue};
+use axum::http::{Method, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+
+use crate::auth::CurrentUser;
+use crate::config::Config;
+use crate::errors::CustomError;
+use crate::models::rate_limit::{Bucket, Decision, Limit, Rule};
+use crate::repository::Store;
+
+/// How often buckets left alone long enough to be full again are forgotten.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+pub const RATELIMIT_POLICY: HeaderName = HeaderName::from_static("ratelimit-policy");
+
+pub struct RateLimiter {
+    default: Option<Limit>,
+    rules: Vec<Rule>,
+    /// Where the buckets are shared, if they are.
+    store: Option<Arc<dyn Store>>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Also starts pruning the buckets in the background, so it must be
+    /// called within a Tokio runtime.
+    pub fn start(config: &Config, store: Arc<dyn Store>) -> Arc<Self> {
+        let limiter = Arc::new(Self {
+            default: config.rate_limit,
+            rules: config.rate_limit_routes.clone(),
+            store: config.rate_limit_shared.then_some(store),
+            buckets: Mutex::default(),
+        });
+        if let Some(longest) = limiter.longest_period() {
+            tokio::spawn(limiter.clone().prune(longest));
+        }
+        limiter
+    }
+
+    /// The limit of the route `path` for `method`, if any, along with what it
+    /// applies to.
+    fn limit_of(&self, method: &Method, path: Option<&str>) -> Option<(String, Limit)> {
+        let rule = path.and_then(|path| self.rules.iter().find(|r| r.matches(method, path)));
+        match rule {
+            Some(rule) => Some((rule.scope(), rule.limit?)),
+            None => Some(("*".to_owned(), self.default?)),
+        }
+    }
+
+    /// Takes a token from the bucket `key`.
+    async fn take(&self, key: &str, limit: Limit) -> Result<Decision, CustomError> {
+        let now = Utc::now();
+        if let Some(store) = &self.store {
+            return store.acquire().await?.take_token(key, limit, now).await;
+        }
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let (bucket, decision) = Bucket::take(buckets.get(key).copied(), limit, now);
+        buckets.insert(key.to_owned(), bucket);
+        Ok(decision)
+    }
+
+    fn longest_period(&self) -> Option<Duration> {
+        let rules = self.rules.iter().filter_map(|rule| rule.limit);
+        self.default
+            .into_iter()
+            .chain(rules)
+            .map(|l| l.period)
+            .max()
+    }
+
+    /// Forgets the buckets untouched for `longest` period, which are full.
+    async fn prune(self: Arc<Self>, longest: Duration) {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let before = Utc::now() - chrono::Duration::from_std(longest).unwrap_or_default();
+            match &self.store {
+                Some(store) => {
+                    let pruned = async { store.acquire().await?.prune_buckets(before).await };
+                    if let Err(err) = pruned.await {
+                        tracing::error!("could not prune rate limits: {err}");
+                    }
+                }
+                None => self
+                    .buckets
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .retain(|_, bucket| bucket.updated_at >= before),
+            }
+        }
+    }
+}
+
+/// Middleware applying the limits of `limiter` to each request, and telling
+/// clients where they stand in `RateLimit-*` headers.
+pub async fn limit<B>(
+    State(limiter): State<Arc<RateLimiter>>,
+    user: Option<CurrentUser>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    path: Option<MatchedPath>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some((scope, limit)) =
+        limiter.limit_of(request.method(), path.as_ref().map(|p| p.as_str()))
+    else {
+        return next.run(request).await;
+    };
+    let client = match (user, connect_info) {
+        (Some(user), _) => format!("user:{}", user.id),
+        (None, Some(ConnectInfo(addr))) => format!("ip:{}", addr.ip()),
+        // Only when the router is not served over a socket, as in tests.
+        (None, None) => "ip:unknown".to_owned(),
+    };
+
+    let decision = match limiter.take(&format!("{client} {scope}"), limit).await {
+        Ok(decision) => decision,
+        Err(err) => {
+            // Better let clients through than turn everybody down.
+            tracing::error!("could not check the rate limit: {err}");
+            return next.run(request).await;
+        }
+    };
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        CustomError::TooManyRequests(decision.retry_after).into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(limit.requests));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(decision.reset.as_secs()));
+    let policy = format!("{};w={}", limit.requests, limit.period.as_secs());
+    if let Ok(policy) = HeaderValue::from_str(&policy) {
+        headers.insert(RATELIMIT_POLICY, policy);
+    }
+    response
+}
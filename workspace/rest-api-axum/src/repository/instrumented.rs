@@ -0,0 +1,164 @@
+// A repository timing every operation of another one, for the metrics of
+// the handler it was acquired for, and running each in a `query` span for the
+// logs. Handlers get one from `DatabaseConnection`.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::Instrument;
+
+use super::{
+    AuditRepository, EventRepository, IdempotencyRepository, ProjectRepository,
+    RateLimitRepository, Repository, TagRepository, TaskRepository, UserRepository,
+    WebhookRepository,
+};
+use crate::errors::CustomError;
+use crate::metrics::Metrics;
+use crate::models::audit::{Actor, AuditPage, AuditParams};
+use crate::models::bulk::{Applied, BulkMode, Operation};
+use crate::models::event::{EventCursor, TaskEvent};
+use crate::models::idempotency::{IdempotencyRecord, StoredResponse};
+use crate::models::page::Page;
+use crate::models::project::{NewProject, Project};
+use crate::models::rate_limit::{Decision, Limit};
+use crate::models::search::{SearchHit, SearchParams};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{ListParams, NewTask, Scope, Task, UpdateTask};
+use crate::models::user::User;
+use crate::models::webhook::{
+    Attempt, Delivery, DeliveryPage, DeliveryParams, DueDelivery, NewWebhook, Webhook,
+};
+
+pub struct Instrumented {
+    inner: Box<dyn Repository>,
+    /// The route of the handler, e.g. `GET /task/:id`.
+    handler: String,
+    metrics: Arc<Metrics>,
+}
+
+impl Instrumented {
+    pub fn new(inner: Box<dyn Repository>, handler: String, metrics: Arc<Metrics>) -> Self {
+        Self {
+            inner,
+            handler,
+            metrics,
+        }
+    }
+}
+
+/// Implements each repository trait by timing the same operation of `inner`.
+macro_rules! instrument {
+    ($($trait:ident {
+        $(fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*
+    })*) => {$(
+        #[async_trait]
+        impl $trait for Instrumented {
+            $(async fn $name(&mut self $(, $arg: $ty)*) -> Result<$ret, CustomError> {
+                let span = tracing::debug_span!(
+                    "query",
+                    handler = %self.handler,
+                    operation = stringify!($name),
+                );
+                let start = Instant::now();
+                let result = self.inner.$name($($arg),*).instrument(span.clone()).await;
+                let elapsed = start.elapsed();
+                self.metrics
+                    .observe_query(&self.handler, stringify!($name), elapsed);
+                span.in_scope(|| {
+                    tracing::trace!(latency = ?elapsed, ok = result.is_ok(), "finished query")
+                });
+                result
+            })*
+        }
+    )*};
+}
+
+instrument! {
+    TaskRepository {
+        fn list(&mut self, owner: i32, params: &ListParams, scope: Scope) -> Page<Task>;
+        fn search(&mut self, owner: i32, params: &SearchParams) -> Vec<SearchHit>;
+        fn export(&mut self, owner: i32, after: i32, limit: i64) -> Vec<Task>;
+        fn get(&mut self, owner: i32, id: i32) -> Option<Task>;
+        fn create(&mut self, actor: &Actor, task: &NewTask) -> Task;
+        fn create_many(&mut self, actor: &Actor, tasks: &[NewTask]) -> Vec<Task>;
+        fn update(&mut self, actor: &Actor, id: i32, version: i32, task: &UpdateTask)
+            -> Option<Task>;
+        fn delete(&mut self, actor: &Actor, id: i32) -> bool;
+        fn restore(&mut self, actor: &Actor, id: i32) -> Option<Task>;
+        fn purge(&mut self, actor: &Actor, id: i32) -> bool;
+        fn purge_trash(&mut self, before: DateTime<Utc>) -> u64;
+        fn create_next_occurrences(&mut self, limit: i64) -> Vec<Option<Task>>;
+        fn bulk(&mut self, actor: &Actor, operations: &[Operation], mode: BulkMode)
+            -> Vec<Result<Applied, CustomError>>;
+    }
+    ProjectRepository {
+        fn list_projects(&mut self, owner: i32) -> Vec<Project>;
+        fn get_project(&mut self, owner: i32, id: i32) -> Option<Project>;
+        fn create_project(&mut self, owner: i32, project: &NewProject) -> Project;
+        fn update_project(&mut self, owner: i32, id: i32, project: &NewProject)
+            -> Option<Project>;
+        fn delete_project(&mut self, actor: &Actor, id: i32) -> bool;
+    }
+    TagRepository {
+        fn list_tags(&mut self, owner: i32) -> Vec<Tag>;
+        fn create_tag(&mut self, owner: i32, tag: &NewTag) -> Tag;
+        fn update_tag(&mut self, owner: i32, id: i32, tag: &NewTag) -> Option<Tag>;
+        fn delete_tag(&mut self, owner: i32, id: i32) -> bool;
+        fn task_tags(&mut self, owner: i32, task_id: i32) -> Vec<Tag>;
+        fn add_task_tag(&mut self, owner: i32, task_id: i32, tag_id: i32) -> bool;
+        fn remove_task_tag(&mut self, owner: i32, task_id: i32, tag_id: i32) -> bool;
+    }
+    UserRepository {
+        fn create_user(&mut self, email: &str, password_hash: &str) -> User;
+        fn get_user(&mut self, id: i32) -> Option<User>;
+        fn find_user_by_email(&mut self, email: &str) -> Option<User>;
+    }
+    EventRepository {
+        fn events_after(&mut self, owner: Option<i32>, after: i64, limit: i64) -> Vec<TaskEvent>;
+        fn follow_events(&mut self, cursor: &mut EventCursor, limit: i64) -> Vec<TaskEvent>;
+        fn event_cursor(&mut self) -> EventCursor;
+        fn last_event_id(&mut self, owner: i32) -> i64;
+        fn prune_events(&mut self, before: DateTime<Utc>) -> u64;
+    }
+    AuditRepository {
+        fn audit_log(&mut self, owner: i32, params: &AuditParams) -> AuditPage;
+    }
+    RateLimitRepository {
+        fn take_token(&mut self, key: &str, limit: Limit, now: DateTime<Utc>) -> Decision;
+        fn prune_buckets(&mut self, before: DateTime<Utc>) -> u64;
+    }
+    IdempotencyRepository {
+        fn claim_idempotency_key(
+            &mut self,
+            owner: i32,
+            key: &str,
+            fingerprint: &str,
+            now: DateTime<Utc>,
+            locked_until: DateTime<Utc>,
+            expires_at: DateTime<Utc>
+        ) -> Option<IdempotencyRecord>;
+        fn save_idempotent_response(&mut self, owner: i32, key: &str, response: &StoredResponse)
+            -> ();
+        fn release_idempotency_key(&mut self, owner: i32, key: &str) -> ();
+        fn prune_idempotency_keys(&mut self, before: DateTime<Utc>) -> u64;
+    }
+    WebhookRepository {
+        fn list_webhooks(&mut self, owner: i32) -> Vec<Webhook>;
+        fn create_webhook(&mut self, owner: i32, webhook: &NewWebhook, secret: &str) -> Webhook;
+        fn delete_webhook(&mut self, owner: i32, id: i32) -> bool;
+        fn list_deliveries(&mut self, owner: i32, webhook_id: i32, params: &DeliveryParams)
+            -> Option<DeliveryPage>;
+        fn redeliver(&mut self, owner: i32, webhook_id: i32, id: i64, now: DateTime<Utc>)
+            -> Option<Delivery>;
+        fn claim_deliveries(
+            &mut self,
+            now: DateTime<Utc>,
+            lease_until: DateTime<Utc>,
+            limit: i64
+        ) -> Vec<DueDelivery>;
+        fn record_attempt(&mut self, id: i64, attempt: &Attempt) -> ();
+        fn prune_deliveries(&mut self, before: DateTime<Utc>) -> u64;
+    }
+}
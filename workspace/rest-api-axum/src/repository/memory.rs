@@ -0,0 +1,1268 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use tokio::sync::watch;
+
+use super::{
+    invalid_reference, parent_in_trash, AuditRepository, EventRepository, IdempotencyRepository,
+    Listener, ProjectRepository, RateLimitRepository, Repository, Store, TagRepository,
+    TaskRepository, UserRepository, WebhookRepository,
+};
+use crate::errors::CustomError;
+use crate::models::audit::{Actor, AuditAction, AuditEntry, AuditPage, AuditParams};
+use crate::models::bulk::{Applied, BulkMode, Operation};
+use crate::models::event::{EventCursor, EventKind, TaskEvent};
+use crate::models::idempotency::{IdempotencyRecord, StoredResponse};
+use crate::models::page::Page;
+use crate::models::project::{NewProject, Project};
+use crate::models::rate_limit::{Bucket, Decision, Limit};
+use crate::models::search::{self, SearchHit, SearchParams};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{ListParams, NewTask, Scope, Sort, Status, Task, UpdateTask};
+use crate::models::user::User;
+use crate::models::webhook::{
+    Attempt, Delivery, DeliveryPage, DeliveryParams, DeliveryStatus, DueDelivery, NewWebhook,
+    Webhook,
+};
+
+/// Keeps everything in process memory. Data is lost when the process exits.
+#[derive(Clone)]
+pub struct MemoryStore {
+    state: Arc<Mutex<State>>,
+    /// Poked after every write.
+    changes: Arc<watch::Sender<()>>,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    last_id: i32,
+    tasks: BTreeMap<i32, Task>,
+    last_user_id: i32,
+    users: BTreeMap<i32, User>,
+    last_event_id: i64,
+    events: Vec<TaskEvent>,
+    last_audit_id: i64,
+    audit_log: Vec<AuditEntry>,
+    last_project_id: i32,
+    projects: BTreeMap<i32, Project>,
+    last_tag_id: i32,
+    tags: BTreeMap<i32, Tag>,
+    /// Pairs of task and tag ids.
+    task_tags: BTreeSet<(i32, i32)>,
+    /// The recurring tasks whose next occurrence was created.
+    recurred: BTreeSet<i32>,
+    buckets: HashMap<String, Bucket>,
+    /// By owner and key.
+    idempotency_keys: HashMap<(i32, String), IdempotencyKey>,
+    last_webhook_id: i32,
+    webhooks: BTreeMap<i32, Webhook>,
+    last_delivery_id: i64,
+    /// Along with the event they send.
+    deliveries: BTreeMap<i64, (Delivery, TaskEvent)>,
+}
+
+#[derive(Clone)]
+struct IdempotencyKey {
+    record: IdempotencyRecord,
+    locked_until: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            changes: Arc::new(watch::channel(()).0),
+        }
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn acquire(&self) -> Result<Box<dyn Repository>, CustomError> {
+        Ok(Box::new(self.clone()))
+    }
+
+    async fn listen(&self) -> Result<Box<dyn Listener>, CustomError> {
+        Ok(Box::new(self.changes.subscribe()))
+    }
+
+    async fn ping(&self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+impl MemoryStore {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panic while holding the lock cannot leave a half-applied change
+        // behind, as every change is a single insert or remove.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Wakes up the listeners once a write is done.
+    fn changed(&self) {
+        self.changes.send_replace(());
+    }
+}
+
+impl State {
+    fn insert(&mut self, actor: &Actor, task: &NewTask) -> Result<Task, CustomError> {
+        self.check_project(actor.user_id, task.project_id)?;
+        if let Some(parent_id) = task.parent_id {
+            if self.get(actor.user_id, parent_id).is_none() {
+                return Err(invalid_reference("parent_id", "task"));
+            }
+        }
+        self.last_id += 1;
+        let now = Utc::now();
+        let task = Task {
+            id: self.last_id,
+            task: task.task.clone(),
+            status: task.status,
+            priority: task.priority,
+            due_at: task.due_at,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            owner_id: Some(actor.user_id),
+            deleted_at: None,
+            project_id: task.project_id,
+            parent_id: task.parent_id,
+            recurrence: task.recurrence.clone(),
+        };
+        self.tasks.insert(task.id, task.clone());
+        self.record(EventKind::Created, &task);
+        self.audit(Some(actor), AuditAction::Create, None, Some(&task));
+        Ok(task)
+    }
+
+    /// Creates the occurrence of the recurring `task` due at `due_at`, a copy
+    /// of it with its tags that is still to do.
+    fn insert_occurrence(&mut self, task: &Task, due_at: DateTime<Utc>) -> Task {
+        self.last_id += 1;
+        let now = Utc::now();
+        let occurrence = Task {
+            id: self.last_id,
+            status: Status::Todo,
+            due_at: Some(due_at),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            ..task.clone()
+        };
+        self.tasks.insert(occurrence.id, occurrence.clone());
+        let tags: Vec<i32> = self
+            .task_tags
+            .iter()
+            .filter(|&&(task_id, _)| task_id == task.id)
+            .map(|&(_, tag_id)| tag_id)
+            .collect();
+        self.task_tags
+            .extend(tags.into_iter().map(|tag_id| (occurrence.id, tag_id)));
+        self.record(EventKind::Created, &occurrence);
+        self.audit(None, AuditAction::Create, None, Some(&occurrence));
+        occurrence
+    }
+
+    fn get(&self, owner: i32, id: i32) -> Option<&Task> {
+        self.tasks
+            .get(&id)
+            .filter(|t| t.owner_id == Some(owner) && t.deleted_at.is_none())
+    }
+
+    /// Like [`get`](Self::get), but for tasks in the trash too.
+    fn get_mut(&mut self, owner: i32, id: i32) -> Option<&mut Task> {
+        self.tasks
+            .get_mut(&id)
+            .filter(|t| t.owner_id == Some(owner))
+    }
+
+    fn project(&self, owner: i32, id: i32) -> Option<&Project> {
+        self.projects.get(&id).filter(|p| p.owner_id == owner)
+    }
+
+    fn tag(&self, owner: i32, id: i32) -> Option<&Tag> {
+        self.tags.get(&id).filter(|t| t.owner_id == owner)
+    }
+
+    /// Checks that `project_id`, if any, is a project of `owner`.
+    fn check_project(&self, owner: i32, project_id: Option<i32>) -> Result<(), CustomError> {
+        match project_id {
+            Some(id) if self.project(owner, id).is_none() => {
+                Err(invalid_reference("project_id", "project"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The ids of the subtasks of `id`, of every level, in the trash or not,
+    /// that `follow` lets through along with their own subtasks.
+    fn subtasks(&self, id: i32, follow: impl Fn(&Task) -> bool) -> Vec<i32> {
+        let mut ids = Vec::new();
+        let mut parents = vec![id];
+        while let Some(parent) = parents.pop() {
+            for task in self.tasks.values() {
+                if task.parent_id == Some(parent) && follow(task) {
+                    ids.push(task.id);
+                    parents.push(task.id);
+                }
+            }
+        }
+        ids.sort_unstable();
+        ids
+    }
+
+    fn update(
+        &mut self,
+        actor: &Actor,
+        id: i32,
+        version: i32,
+        task: &UpdateTask,
+    ) -> Result<Option<Task>, CustomError> {
+        self.check_project(actor.user_id, task.project_id)?;
+        let Some(current) = self
+            .get_mut(actor.user_id, id)
+            .filter(|t| t.deleted_at.is_none() && t.version == version)
+        else {
+            return Ok(None);
+        };
+        let before = current.clone();
+        current.task = task.task.clone();
+        current.status = task.status;
+        current.priority = task.priority;
+        current.due_at = task.due_at;
+        current.project_id = task.project_id;
+        current.recurrence = task.recurrence.clone();
+        current.updated_at = Utc::now();
+        current.version += 1;
+        let task = current.clone();
+        self.record(EventKind::Updated, &task);
+        self.audit(Some(actor), AuditAction::Update, Some(&before), Some(&task));
+        Ok(Some(task))
+    }
+
+    /// Moves a task to the trash, along with its subtasks.
+    fn delete(&mut self, actor: &Actor, id: i32) -> bool {
+        if self.get(actor.user_id, id).is_none() {
+            return false;
+        }
+        let now = Utc::now();
+        let mut ids = vec![id];
+        ids.extend(self.subtasks(id, |t| t.deleted_at.is_none()));
+        for id in ids {
+            let current = self.tasks.get_mut(&id).expect("the subtasks exist");
+            let before = current.clone();
+            current.deleted_at = Some(now);
+            current.updated_at = now;
+            current.version += 1;
+            let task = current.clone();
+            self.record(EventKind::Deleted, &task);
+            self.audit(Some(actor), AuditAction::Delete, Some(&before), Some(&task));
+        }
+        true
+    }
+
+    /// Takes a task out of the trash, along with the subtasks that went there
+    /// with it.
+    fn restore(&mut self, actor: &Actor, id: i32) -> Result<Option<Task>, CustomError> {
+        let Some(deleted_at) = self.get_mut(actor.user_id, id).and_then(|t| t.deleted_at) else {
+            return Ok(None);
+        };
+        let parent = self.tasks[&id].parent_id;
+        if parent.is_some_and(|parent| self.tasks[&parent].deleted_at.is_some()) {
+            return Err(parent_in_trash());
+        }
+        let mut ids = vec![id];
+        ids.extend(self.subtasks(id, |t| t.deleted_at == Some(deleted_at)));
+        let now = Utc::now();
+        let mut restored = Vec::with_capacity(ids.len());
+        for id in ids {
+            let current = self.tasks.get_mut(&id).expect("the subtasks exist");
+            let before = current.clone();
+            current.deleted_at = None;
+            current.updated_at = now;
+            current.version += 1;
+            let task = current.clone();
+            // Back from the trash, the task looks new to readers of the events.
+            self.record(EventKind::Created, &task);
+            self.audit(
+                Some(actor),
+                AuditAction::Restore,
+                Some(&before),
+                Some(&task),
+            );
+            restored.push(task);
+        }
+        Ok(restored.into_iter().next())
+    }
+
+    /// Deletes a task for good, along with its subtasks.
+    fn purge(&mut self, actor: &Actor, id: i32) -> bool {
+        if self.get_mut(actor.user_id, id).is_none() {
+            return false;
+        }
+        self.remove(Some(actor), id);
+        true
+    }
+
+    /// Removes a task and its subtasks from the store, recording it.
+    fn remove(&mut self, actor: Option<&Actor>, id: i32) {
+        let mut ids = vec![id];
+        ids.extend(self.subtasks(id, |_| true));
+        for id in ids {
+            let task = self.tasks.remove(&id).expect("the subtasks exist");
+            // Tasks in the trash were already reported deleted.
+            if task.deleted_at.is_none() {
+                self.record(EventKind::Deleted, &task);
+            }
+            self.audit(actor, AuditAction::Purge, Some(&task), None);
+            self.task_tags.retain(|&(task_id, _)| task_id != id);
+            self.recurred.remove(&id);
+        }
+    }
+
+    /// The webhook `id` of `owner`.
+    fn webhook(&self, owner: i32, id: i32) -> Option<&Webhook> {
+        self.webhooks
+            .get(&id)
+            .filter(|webhook| webhook.owner_id == owner)
+    }
+
+    /// Appends a change of `task` to the log of events, and a delivery of
+    /// it to every webhook of its owner that wants it.
+    fn record(&mut self, kind: EventKind, task: &Task) {
+        self.last_event_id += 1;
+        let event = TaskEvent {
+            id: self.last_event_id,
+            kind,
+            task_id: task.id,
+            owner_id: task.owner_id,
+            task: (kind != EventKind::Deleted).then(|| Json(task.clone())),
+            created_at: Utc::now(),
+        };
+        let webhook_ids: Vec<i32> = self
+            .webhooks
+            .values()
+            .filter(|webhook| Some(webhook.owner_id) == task.owner_id)
+            .filter(|webhook| webhook.events.contains(&kind))
+            .map(|webhook| webhook.id)
+            .collect();
+        for webhook_id in webhook_ids {
+            self.last_delivery_id += 1;
+            let delivery = Delivery {
+                id: self.last_delivery_id,
+                webhook_id,
+                event_id: event.id,
+                kind,
+                task_id: task.id,
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                next_attempt_at: event.created_at,
+                response_status: None,
+                last_error: None,
+                created_at: event.created_at,
+                delivered_at: None,
+            };
+            self.deliveries
+                .insert(delivery.id, (delivery, event.clone()));
+        }
+        self.events.push(event);
+    }
+
+    /// Appends a change of a task to the audit log. `before` and `after`
+    /// cannot both be absent.
+    fn audit(
+        &mut self,
+        actor: Option<&Actor>,
+        action: AuditAction,
+        before: Option<&Task>,
+        after: Option<&Task>,
+    ) {
+        let task = before
+            .or(after)
+            .expect("a change has a task before or after it");
+        self.last_audit_id += 1;
+        self.audit_log.push(AuditEntry {
+            id: self.last_audit_id,
+            action,
+            task_id: task.id,
+            owner_id: task.owner_id,
+            actor_id: actor.map(|actor| actor.user_id),
+            request_id: actor.and_then(|actor| actor.request_id.clone()),
+            before: before.cloned().map(Json),
+            after: after.cloned().map(Json),
+            created_at: Utc::now(),
+        });
+    }
+
+    /// Applies a single operation of a bulk request.
+    fn apply(&mut self, actor: &Actor, op: &Operation) -> Result<Applied, CustomError> {
+        match op {
+            Operation::Create { task } => Ok(Applied::Created(self.insert(actor, task)?)),
+            Operation::Update { id, version, task } => {
+                let current = self
+                    .get(actor.user_id, *id)
+                    .ok_or_else(|| CustomError::not_found("task", id))?;
+                if version.is_some_and(|version| version != current.version) {
+                    return Err(CustomError::PreconditionFailed);
+                }
+                let version = current.version;
+                let task = self
+                    .update(actor, *id, version, task)?
+                    .ok_or(CustomError::PreconditionFailed)?;
+                Ok(Applied::Updated(task))
+            }
+            Operation::Delete { id } => {
+                if !self.delete(actor, *id) {
+                    return Err(CustomError::not_found("task", id));
+                }
+                Ok(Applied::Deleted)
+            }
+        }
+    }
+
+    /// The ids of the tasks with the tag `name`.
+    fn tagged(&self, owner: i32, name: &str) -> BTreeSet<i32> {
+        let Some(tag) = self
+            .tags
+            .values()
+            .find(|t| t.owner_id == owner && t.name == name)
+        else {
+            return BTreeSet::new();
+        };
+        self.task_tags
+            .iter()
+            .filter(|&&(_, tag_id)| tag_id == tag.id)
+            .map(|&(task_id, _)| task_id)
+            .collect()
+    }
+
+    fn owned_by(&self, owner: i32, scope: Scope) -> impl DoubleEndedIterator<Item = &Task> {
+        let trashed = scope == Scope::Trash;
+        self.tasks
+            .values()
+            .filter(move |t| t.owner_id == Some(owner) && t.deleted_at.is_some() == trashed)
+    }
+}
+
+#[async_trait]
+impl TaskRepository for MemoryStore {
+    async fn list(
+        &mut self,
+        owner: i32,
+        params: &ListParams,
+        scope: Scope,
+    ) -> Result<Page<Task>, CustomError> {
+        let state = self.lock();
+        let q = params.q().map(str::to_lowercase);
+        let tagged = params.tag.as_ref().map(|name| state.tagged(owner, name));
+        let mut tasks: Vec<&Task> = state
+            .owned_by(owner, scope)
+            .filter(|t| q.as_ref().is_none_or(|q| t.task.to_lowercase().contains(q)))
+            .filter(|t| params.project.is_none_or(|id| t.project_id == Some(id)))
+            .filter(|t| params.parent.is_none_or(|id| t.parent_id == Some(id)))
+            .filter(|t| tagged.as_ref().is_none_or(|ids| ids.contains(&t.id)))
+            .collect();
+        let total = tasks.len() as i64;
+
+        match params.sort {
+            Sort::IdAsc => {}
+            Sort::IdDesc => tasks.reverse(),
+            Sort::Task => tasks.sort_by(|a, b| (&a.task, a.id).cmp(&(&b.task, b.id))),
+        }
+
+        if let Some(cursor) = params.cursor {
+            let after = match params.sort {
+                Sort::IdAsc => tasks.iter().position(|t| t.id > cursor),
+                Sort::IdDesc => tasks.iter().position(|t| t.id < cursor),
+                Sort::Task => {
+                    let c = state
+                        .tasks
+                        .get(&cursor)
+                        .filter(|c| c.owner_id == Some(owner))
+                        .ok_or_else(|| invalid_reference("cursor", "task"))?;
+                    tasks.iter().position(|t| (&t.task, t.id) > (&c.task, c.id))
+                }
+            };
+            tasks.drain(..after.unwrap_or(tasks.len()));
+        }
+
+        let rows = tasks
+            .into_iter()
+            .skip(params.offset.unwrap_or(0) as usize)
+            .take(params.limit() as usize + 1)
+            .cloned()
+            .collect();
+
+        Ok(Page::from_rows(rows, params.limit(), total, |t| t.id))
+    }
+
+    async fn search(
+        &mut self,
+        owner: i32,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchHit>, CustomError> {
+        let terms = params.terms();
+        let lowercase: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+        let state = self.lock();
+        Ok(state
+            .owned_by(owner, Scope::Active)
+            .rev()
+            .filter(|t| {
+                let text = t.task.to_lowercase();
+                lowercase.iter().all(|term| text.contains(term))
+            })
+            .take(params.limit() as usize)
+            .map(|task| SearchHit {
+                task: task.clone(),
+                rank: None,
+                snippet: search::snippet_html(&search::mark_terms(&task.task, &terms)),
+            })
+            .collect())
+    }
+
+    async fn export(
+        &mut self,
+        owner: i32,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<Task>, CustomError> {
+        Ok(self
+            .lock()
+            .owned_by(owner, Scope::Active)
+            .filter(|t| t.id > after)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&mut self, owner: i32, id: i32) -> Result<Option<Task>, CustomError> {
+        Ok(self.lock().get(owner, id).cloned())
+    }
+
+    async fn create(&mut self, actor: &Actor, task: &NewTask) -> Result<Task, CustomError> {
+        let task = self.lock().insert(actor, task)?;
+        self.changed();
+        Ok(task)
+    }
+
+    async fn create_many(
+        &mut self,
+        actor: &Actor,
+        tasks: &[NewTask],
+    ) -> Result<Vec<Task>, CustomError> {
+        let created = {
+            let mut state = self.lock();
+            // Work on a copy, only kept if every task is valid.
+            let mut draft = state.clone();
+            let created = tasks
+                .iter()
+                .map(|task| draft.insert(actor, task))
+                .collect::<Result<_, _>>()?;
+            *state = draft;
+            created
+        };
+        self.changed();
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        actor: &Actor,
+        id: i32,
+        version: i32,
+        task: &UpdateTask,
+    ) -> Result<Option<Task>, CustomError> {
+        let task = self.lock().update(actor, id, version, task)?;
+        self.changed();
+        Ok(task)
+    }
+
+    async fn delete(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let deleted = self.lock().delete(actor, id);
+        self.changed();
+        Ok(deleted)
+    }
+
+    async fn restore(&mut self, actor: &Actor, id: i32) -> Result<Option<Task>, CustomError> {
+        let task = self.lock().restore(actor, id)?;
+        self.changed();
+        Ok(task)
+    }
+
+    async fn purge(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let purged = self.lock().purge(actor, id);
+        self.changed();
+        Ok(purged)
+    }
+
+    async fn purge_trash(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut state = self.lock();
+        let expired: Vec<i32> = state
+            .tasks
+            .values()
+            .filter(|t| t.deleted_at.is_some_and(|at| at < before))
+            .map(|t| t.id)
+            .collect();
+        let count = state.tasks.len();
+        for id in expired {
+            // Subtasks go to the trash with their parent, so they may be gone already.
+            if state.tasks.contains_key(&id) {
+                state.remove(None, id);
+            }
+        }
+        Ok((count - state.tasks.len()) as u64)
+    }
+
+    async fn create_next_occurrences(
+        &mut self,
+        limit: i64,
+    ) -> Result<Vec<Option<Task>>, CustomError> {
+        let mut state = self.lock();
+        let done: Vec<Task> = state
+            .tasks
+            .values()
+            .filter(|t| {
+                t.recurrence.is_some()
+                    && t.status == Status::Done
+                    && t.deleted_at.is_none()
+                    && !state.recurred.contains(&t.id)
+            })
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        let mut created = Vec::with_capacity(done.len());
+        for task in &done {
+            state.recurred.insert(task.id);
+            let next = task
+                .next_due_at()
+                .map(|due_at| state.insert_occurrence(task, due_at));
+            created.push(next);
+        }
+        drop(state);
+        if !done.is_empty() {
+            self.changed();
+        }
+        Ok(created)
+    }
+
+    async fn bulk(
+        &mut self,
+        actor: &Actor,
+        operations: &[Operation],
+        mode: BulkMode,
+    ) -> Result<Vec<Result<Applied, CustomError>>, CustomError> {
+        let mut state = self.lock();
+        // Work on a copy, only kept if the batch goes through.
+        let mut draft = state.clone();
+        let mut results = Vec::with_capacity(operations.len());
+        for op in operations {
+            let result = draft.apply(actor, op);
+            let failed = result.is_err();
+            results.push(result);
+            if failed && mode == BulkMode::Atomic {
+                return Ok(results);
+            }
+        }
+        *state = draft;
+        drop(state);
+        self.changed();
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for MemoryStore {
+    async fn list_projects(&mut self, owner: i32) -> Result<Vec<Project>, CustomError> {
+        let mut projects: Vec<Project> = self
+            .lock()
+            .projects
+            .values()
+            .filter(|p| p.owner_id == owner)
+            .cloned()
+            .collect();
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(projects)
+    }
+
+    async fn get_project(&mut self, owner: i32, id: i32) -> Result<Option<Project>, CustomError> {
+        Ok(self
+            .lock()
+            .projects
+            .get(&id)
+            .filter(|p| p.owner_id == owner)
+            .cloned())
+    }
+
+    async fn create_project(
+        &mut self,
+        owner: i32,
+        project: &NewProject,
+    ) -> Result<Project, CustomError> {
+        let mut state = self.lock();
+        if state
+            .projects
+            .values()
+            .any(|p| p.owner_id == owner && p.name == project.name)
+        {
+            return Err(project_name_taken());
+        }
+        state.last_project_id += 1;
+        let project = Project {
+            id: state.last_project_id,
+            owner_id: owner,
+            name: project.name.clone(),
+            created_at: Utc::now(),
+        };
+        state.projects.insert(project.id, project.clone());
+        Ok(project)
+    }
+
+    async fn update_project(
+        &mut self,
+        owner: i32,
+        id: i32,
+        project: &NewProject,
+    ) -> Result<Option<Project>, CustomError> {
+        let mut state = self.lock();
+        if state
+            .projects
+            .values()
+            .any(|p| p.owner_id == owner && p.name == project.name && p.id != id)
+        {
+            return Err(project_name_taken());
+        }
+        let Some(current) = state.projects.get_mut(&id).filter(|p| p.owner_id == owner) else {
+            return Ok(None);
+        };
+        current.name = project.name.clone();
+        Ok(Some(current.clone()))
+    }
+
+    async fn delete_project(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut state = self.lock();
+        if state.project(actor.user_id, id).is_none() {
+            return Ok(false);
+        }
+        let tasks: Vec<i32> = state
+            .tasks
+            .values()
+            .filter(|t| t.project_id == Some(id))
+            .map(|t| t.id)
+            .collect();
+        for task in tasks {
+            // Subtasks of a task of the project may be gone along with it.
+            if state.tasks.contains_key(&task) {
+                state.remove(Some(actor), task);
+            }
+        }
+        state.projects.remove(&id);
+        drop(state);
+        self.changed();
+        Ok(true)
+    }
+}
+
+fn project_name_taken() -> CustomError {
+    CustomError::UniqueViolation {
+        constraint: Some("project_owner_id_name_key".to_owned()),
+    }
+}
+
+#[async_trait]
+impl TagRepository for MemoryStore {
+    async fn list_tags(&mut self, owner: i32) -> Result<Vec<Tag>, CustomError> {
+        let mut tags: Vec<Tag> = self
+            .lock()
+            .tags
+            .values()
+            .filter(|t| t.owner_id == owner)
+            .cloned()
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    async fn create_tag(&mut self, owner: i32, tag: &NewTag) -> Result<Tag, CustomError> {
+        let mut state = self.lock();
+        if state
+            .tags
+            .values()
+            .any(|t| t.owner_id == owner && t.name == tag.name)
+        {
+            return Err(tag_name_taken());
+        }
+        state.last_tag_id += 1;
+        let tag = Tag {
+            id: state.last_tag_id,
+            owner_id: owner,
+            name: tag.name.clone(),
+            created_at: Utc::now(),
+        };
+        state.tags.insert(tag.id, tag.clone());
+        Ok(tag)
+    }
+
+    async fn update_tag(
+        &mut self,
+        owner: i32,
+        id: i32,
+        tag: &NewTag,
+    ) -> Result<Option<Tag>, CustomError> {
+        let mut state = self.lock();
+        if state
+            .tags
+            .values()
+            .any(|t| t.owner_id == owner && t.name == tag.name && t.id != id)
+        {
+            return Err(tag_name_taken());
+        }
+        let Some(current) = state.tags.get_mut(&id).filter(|t| t.owner_id == owner) else {
+            return Ok(None);
+        };
+        current.name = tag.name.clone();
+        Ok(Some(current.clone()))
+    }
+
+    async fn delete_tag(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        let mut state = self.lock();
+        if state.tag(owner, id).is_none() {
+            return Ok(false);
+        }
+        state.tags.remove(&id);
+        state.task_tags.retain(|&(_, tag_id)| tag_id != id);
+        Ok(true)
+    }
+
+    async fn task_tags(&mut self, owner: i32, task_id: i32) -> Result<Vec<Tag>, CustomError> {
+        let state = self.lock();
+        if state.get(owner, task_id).is_none() {
+            return Ok(Vec::new());
+        }
+        let mut tags: Vec<Tag> = state
+            .task_tags
+            .range((task_id, i32::MIN)..=(task_id, i32::MAX))
+            .map(|(_, tag_id)| state.tags[tag_id].clone())
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    async fn add_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let mut state = self.lock();
+        if state.get(owner, task_id).is_none() || state.tag(owner, tag_id).is_none() {
+            return Ok(false);
+        }
+        state.task_tags.insert((task_id, tag_id));
+        Ok(true)
+    }
+
+    async fn remove_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let mut state = self.lock();
+        if state.get(owner, task_id).is_none() {
+            return Ok(false);
+        }
+        Ok(state.task_tags.remove(&(task_id, tag_id)))
+    }
+}
+
+fn tag_name_taken() -> CustomError {
+    CustomError::UniqueViolation {
+        constraint: Some("tag_owner_id_name_key".to_owned()),
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryStore {
+    async fn create_user(&mut self, email: &str, password_hash: &str) -> Result<User, CustomError> {
+        let mut state = self.lock();
+        if state.users.values().any(|u| u.email == email) {
+            return Err(CustomError::UniqueViolation {
+                constraint: Some("users_email_key".to_owned()),
+            });
+        }
+        state.last_user_id += 1;
+        let user = User {
+            id: state.last_user_id,
+            email: email.to_owned(),
+            password_hash: password_hash.to_owned(),
+            created_at: Utc::now(),
+        };
+        state.users.insert(user.id, user.clone());
+        Ok(user)
+    }
+
+    async fn get_user(&mut self, id: i32) -> Result<Option<User>, CustomError> {
+        Ok(self.lock().users.get(&id).cloned())
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<User>, CustomError> {
+        Ok(self
+            .lock()
+            .users
+            .values()
+            .find(|u| u.email == email)
+            .cloned())
+    }
+}
+
+#[async_trait]
+impl EventRepository for MemoryStore {
+    async fn events_after(
+        &mut self,
+        owner: Option<i32>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        let state = self.lock();
+        // Events are kept in id order.
+        let start = state.events.partition_point(|event| event.id <= after);
+        Ok(state.events[start..]
+            .iter()
+            .filter(|event| owner.is_none() || event.owner_id == owner)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn follow_events(
+        &mut self,
+        cursor: &mut EventCursor,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        // Recorded in id order, under the lock.
+        let events = self.events_after(None, cursor.after, limit).await?;
+        if let Some(last) = events.last() {
+            cursor.after = last.id;
+        }
+        Ok(events)
+    }
+
+    async fn event_cursor(&mut self) -> Result<EventCursor, CustomError> {
+        Ok(EventCursor {
+            after: self.lock().last_event_id,
+            in_flight: Vec::new(),
+        })
+    }
+
+    async fn last_event_id(&mut self, owner: i32) -> Result<i64, CustomError> {
+        let state = self.lock();
+        let last = state
+            .events
+            .iter()
+            .rev()
+            .find(|event| event.owner_id == Some(owner));
+        Ok(last.map_or(0, |event| event.id))
+    }
+
+    async fn prune_events(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut state = self.lock();
+        let count = state
+            .events
+            .partition_point(|event| event.created_at < before);
+        state.events.drain(..count);
+        Ok(count as u64)
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryStore {
+    async fn audit_log(
+        &mut self,
+        owner: i32,
+        params: &AuditParams,
+    ) -> Result<AuditPage, CustomError> {
+        let state = self.lock();
+        let entries = state
+            .audit_log
+            .iter()
+            .rev()
+            .filter(|entry| {
+                entry.owner_id == Some(owner)
+                    && params.task_id.is_none_or(|id| entry.task_id == id)
+                    && params.action.is_none_or(|action| entry.action == action)
+                    && params.actor_id.is_none_or(|id| entry.actor_id == Some(id))
+                    && params
+                        .request_id
+                        .as_ref()
+                        .is_none_or(|id| entry.request_id.as_ref() == Some(id))
+                    && params.since.is_none_or(|since| entry.created_at >= since)
+                    && params.until.is_none_or(|until| entry.created_at < until)
+                    && params.cursor.is_none_or(|cursor| entry.id < cursor)
+            })
+            .take(params.limit() as usize + 1)
+            .cloned()
+            .collect();
+        Ok(AuditPage::from_rows(entries, params.limit()))
+    }
+}
+
+#[async_trait]
+impl RateLimitRepository for MemoryStore {
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: Limit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, CustomError> {
+        let mut state = self.lock();
+        let (bucket, decision) = Bucket::take(state.buckets.get(key).copied(), limit, now);
+        state.buckets.insert(key.to_owned(), bucket);
+        Ok(decision)
+    }
+
+    async fn prune_buckets(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut state = self.lock();
+        let count = state.buckets.len();
+        state
+            .buckets
+            .retain(|_, bucket| bucket.updated_at >= before);
+        Ok((count - state.buckets.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for MemoryStore {
+    async fn claim_idempotency_key(
+        &mut self,
+        owner: i32,
+        key: &str,
+        fingerprint: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, CustomError> {
+        let mut state = self.lock();
+        let entry = (owner, key.to_owned());
+        if let Some(held) = state.idempotency_keys.get(&entry) {
+            let in_progress = held.record.status.is_none() && held.locked_until > now;
+            if held.expires_at > now && (held.record.status.is_some() || in_progress) {
+                return Ok(Some(held.record.clone()));
+            }
+        }
+        let record = IdempotencyRecord {
+            fingerprint: fingerprint.to_owned(),
+            status: None,
+            content_type: None,
+            body: None,
+        };
+        let held = IdempotencyKey {
+            record,
+            locked_until,
+            expires_at,
+        };
+        state.idempotency_keys.insert(entry, held);
+        Ok(None)
+    }
+
+    async fn save_idempotent_response(
+        &mut self,
+        owner: i32,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<(), CustomError> {
+        let mut state = self.lock();
+        if let Some(held) = state.idempotency_keys.get_mut(&(owner, key.to_owned())) {
+            held.record.status = Some(response.status as i16);
+            held.record.content_type = response.content_type.clone();
+            held.record.body = Some(response.body.clone());
+        }
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&mut self, owner: i32, key: &str) -> Result<(), CustomError> {
+        let mut state = self.lock();
+        let entry = (owner, key.to_owned());
+        if state
+            .idempotency_keys
+            .get(&entry)
+            .is_some_and(|held| held.record.status.is_none())
+        {
+            state.idempotency_keys.remove(&entry);
+        }
+        Ok(())
+    }
+
+    async fn prune_idempotency_keys(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut state = self.lock();
+        let count = state.idempotency_keys.len();
+        state
+            .idempotency_keys
+            .retain(|_, held| held.expires_at >= before);
+        Ok((count - state.idempotency_keys.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for MemoryStore {
+    async fn list_webhooks(&mut self, owner: i32) -> Result<Vec<Webhook>, CustomError> {
+        Ok(self
+            .lock()
+            .webhooks
+            .values()
+            .filter(|webhook| webhook.owner_id == owner)
+            .cloned()
+            .collect())
+    }
+
+    async fn create_webhook(
+        &mut self,
+        owner: i32,
+        webhook: &NewWebhook,
+        secret: &str,
+    ) -> Result<Webhook, CustomError> {
+        let mut state = self.lock();
+        state.last_webhook_id += 1;
+        let webhook = Webhook {
+            id: state.last_webhook_id,
+            owner_id: owner,
+            url: webhook.url.clone(),
+            events: Json(webhook.events()),
+            secret: secret.to_owned(),
+            created_at: Utc::now(),
+        };
+        state.webhooks.insert(webhook.id, webhook.clone());
+        Ok(webhook)
+    }
+
+    async fn delete_webhook(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        let mut state = self.lock();
+        if state.webhook(owner, id).is_none() {
+            return Ok(false);
+        }
+        state.webhooks.remove(&id);
+        state
+            .deliveries
+            .retain(|_, (delivery, _)| delivery.webhook_id != id);
+        Ok(true)
+    }
+
+    async fn list_deliveries(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        params: &DeliveryParams,
+    ) -> Result<Option<DeliveryPage>, CustomError> {
+        let state = self.lock();
+        if state.webhook(owner, webhook_id).is_none() {
+            return Ok(None);
+        }
+        let rows = state
+            .deliveries
+            .values()
+            .rev()
+            .map(|(delivery, _)| delivery)
+            .filter(|delivery| delivery.webhook_id == webhook_id)
+            .filter(|delivery| params.status.is_none_or(|status| delivery.status == status))
+            .filter(|delivery| params.cursor.is_none_or(|cursor| delivery.id < cursor))
+            .take(params.limit() as usize + 1)
+            .cloned()
+            .collect();
+        Ok(Some(DeliveryPage::from_rows(rows, params.limit())))
+    }
+
+    async fn redeliver(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        id: i64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Delivery>, CustomError> {
+        let mut state = self.lock();
+        if state.webhook(owner, webhook_id).is_none() {
+            return Ok(None);
+        }
+        let Some((delivery, _)) = state
+            .deliveries
+            .get_mut(&id)
+            .filter(|(delivery, _)| delivery.webhook_id == webhook_id)
+        else {
+            return Ok(None);
+        };
+        delivery.status = DeliveryStatus::Pending;
+        delivery.attempts = 0;
+        delivery.next_attempt_at = now;
+        let delivery = delivery.clone();
+        drop(state);
+        self.changed();
+        Ok(Some(delivery))
+    }
+
+    async fn claim_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, CustomError> {
+        let mut state = self.lock();
+        let mut due: Vec<(DateTime<Utc>, i64)> = state
+            .deliveries
+            .values()
+            .map(|(delivery, _)| delivery)
+            .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+            .filter(|delivery| delivery.next_attempt_at <= now)
+            .map(|delivery| (delivery.next_attempt_at, delivery.id))
+            .collect();
+        due.sort();
+        due.truncate(limit as usize);
+        due.sort_by_key(|&(_, id)| id);
+
+        let mut claimed = Vec::new();
+        for (_, id) in due {
+            let (delivery, event) = state.deliveries.get_mut(&id).expect("the delivery exists");
+            delivery.next_attempt_at = lease_until;
+            let (delivery, event) = (delivery.clone(), event.clone());
+            let webhook = &state.webhooks[&delivery.webhook_id];
+            claimed.push(DueDelivery {
+                id,
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone(),
+                attempts: delivery.attempts,
+                event_id: event.id,
+                kind: event.kind,
+                task_id: event.task_id,
+                task: event.task,
+                created_at: event.created_at,
+            });
+        }
+        Ok(claimed)
+    }
+
+    async fn record_attempt(&mut self, id: i64, attempt: &Attempt) -> Result<(), CustomError> {
+        let mut state = self.lock();
+        if let Some((delivery, _)) = state.deliveries.get_mut(&id) {
+            delivery.status = attempt.status;
+            delivery.attempts += 1;
+            delivery.next_attempt_at = attempt.next_attempt_at;
+            delivery.response_status = attempt.response_status;
+            delivery.last_error = attempt.error.clone();
+            delivery.delivered_at =
+                (attempt.status == DeliveryStatus::Delivered).then_some(attempt.at);
+        }
+        Ok(())
+    }
+
+    async fn prune_deliveries(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut state = self.lock();
+        let count = state.deliveries.len();
+        state.deliveries.retain(|_, (delivery, _)| {
+            delivery.status == DeliveryStatus::Pending || delivery.created_at >= before
+        });
+        Ok((count - state.deliveries.len()) as u64)
+    }
+}
@@ -0,0 +1,456 @@
+// Storage of tasks and users, behind traits so the API can run on several backends:
+// - `postgres`: the production database
+// - `sqlite`: a file or in-memory SQLite database, handy for local development
+// - `memory`: plain data structures, for tests that need no database at all
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+
+use crate::errors::{CustomError, FieldError};
+use crate::models::audit::{Actor, AuditPage, AuditParams};
+use crate::models::bulk::{Applied, BulkMode, Operation};
+use crate::models::event::{EventCursor, TaskEvent};
+use crate::models::idempotency::{IdempotencyRecord, StoredResponse};
+use crate::models::page::Page;
+use crate::models::project::{NewProject, Project};
+use crate::models::rate_limit::{Decision, Limit};
+use crate::models::search::{SearchHit, SearchParams};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{ListParams, NewTask, Scope, Task, UpdateTask};
+use crate::models::user::User;
+use crate::models::webhook::{
+    Attempt, Delivery, DeliveryPage, DeliveryParams, DueDelivery, NewWebhook, Webhook,
+};
+
+pub mod instrumented;
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+/// A source of repositories, typically a connection pool.
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn acquire(&self) -> Result<Box<dyn Repository>, CustomError>;
+
+    /// Starts listening for changes to tasks. On Postgres these include the
+    /// changes made by other processes.
+    async fn listen(&self) -> Result<Box<dyn Listener>, CustomError>;
+
+    /// Makes a round trip to the backend, through the pool.
+    async fn ping(&self) -> Result<(), CustomError>;
+
+    /// The connections of the pool, for backends that have one.
+    fn pool_status(&self) -> Option<PoolStatus> {
+        None
+    }
+}
+
+/// A snapshot of the connections of a pool.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStatus {
+    /// Every open connection, idle or in use.
+    pub size: u32,
+    pub idle: u32,
+}
+
+/// Wakes up when new events may have been recorded.
+#[async_trait]
+pub trait Listener: Send {
+    /// Waits for the next change. May return without one, e.g. after a
+    /// reconnection, as changes may have been missed in the meantime.
+    async fn changed(&mut self) -> Result<(), CustomError>;
+}
+
+/// Backends without notifications of their own are poked by their
+/// repositories after every write.
+#[async_trait]
+impl Listener for watch::Receiver<()> {
+    async fn changed(&mut self) -> Result<(), CustomError> {
+        watch::Receiver::changed(self)
+            .await
+            .map_err(|_| CustomError::InternalServerError("the store was dropped".to_owned()))
+    }
+}
+
+/// Every operation, through a single connection to the backend.
+pub trait Repository:
+    TaskRepository
+    + ProjectRepository
+    + TagRepository
+    + UserRepository
+    + EventRepository
+    + AuditRepository
+    + RateLimitRepository
+    + IdempotencyRepository
+    + WebhookRepository
+{
+}
+
+impl<T> Repository for T where
+    T: TaskRepository
+        + ProjectRepository
+        + TagRepository
+        + UserRepository
+        + EventRepository
+        + AuditRepository
+        + RateLimitRepository
+        + IdempotencyRepository
+        + WebhookRepository
+{
+}
+
+/// Operations on tasks, through a single connection to the backend.
+///
+/// Every method but [`purge_trash`](Self::purge_trash) and
+/// [`create_next_occurrences`](Self::create_next_occurrences) is scoped to the
+/// tasks of `owner`, or of the `actor` making a change: those of other users
+/// are reported exactly like tasks that do not exist. So are tasks in the trash,
+/// unless stated otherwise.
+///
+/// Every change is recorded in the audit log, in the same transaction.
+///
+/// Subtasks follow their parent: they are moved to the trash along with it,
+/// restored along with it if they went to the trash together, and purged
+/// along with it. The `project_id` and `parent_id` of new or updated tasks
+/// must refer to a project and a task of the same user, or the change fails
+/// with a `Validation` error on that field.
+#[async_trait]
+pub trait TaskRepository: Send {
+    /// Lists a page of the tasks in `scope`. `params` must have been validated.
+    /// Sorted by task, the cursor must be a task of `owner`, in the trash or
+    /// not, or this fails with a `Validation` error on `cursor`.
+    async fn list(
+        &mut self,
+        owner: i32,
+        params: &ListParams,
+        scope: Scope,
+    ) -> Result<Page<Task>, CustomError>;
+
+    /// Finds the tasks matching `params.q`, the most relevant first when the
+    /// backend has full-text search. `params` must have been validated.
+    async fn search(
+        &mut self,
+        owner: i32,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchHit>, CustomError>;
+
+    /// The first `limit` tasks with an id above `after`, by id, to go through
+    /// all of them a batch at a time.
+    async fn export(
+        &mut self,
+        owner: i32,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<Task>, CustomError>;
+
+    async fn get(&mut self, owner: i32, id: i32) -> Result<Option<Task>, CustomError>;
+
+    async fn create(&mut self, actor: &Actor, task: &NewTask) -> Result<Task, CustomError>;
+
+    /// Creates all tasks or, if any of them fails, none.
+    async fn create_many(
+        &mut self,
+        actor: &Actor,
+        tasks: &[NewTask],
+    ) -> Result<Vec<Task>, CustomError>;
+
+    /// Stores `task` as the new content of the task `id`, as long as it is
+    /// still at `version`. Returns `None` otherwise.
+    async fn update(
+        &mut self,
+        actor: &Actor,
+        id: i32,
+        version: i32,
+        task: &UpdateTask,
+    ) -> Result<Option<Task>, CustomError>;
+
+    /// Moves a task to the trash. Returns whether there was a task to delete.
+    async fn delete(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError>;
+
+    /// Takes a task out of the trash. Returns `None` if it is not there, and
+    /// fails with `Conflict` if its parent still is.
+    async fn restore(&mut self, actor: &Actor, id: i32) -> Result<Option<Task>, CustomError>;
+
+    /// Deletes a task for good, whether it is in the trash or not. Returns
+    /// whether there was a task to delete.
+    async fn purge(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError>;
+
+    /// Deletes for good the tasks of every user moved to the trash before
+    /// `before`, returning how many. Recorded as made by nobody.
+    async fn purge_trash(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError>;
+
+    /// Creates the next occurrence of up to `limit` recurring tasks of every
+    /// user that are done, along with their tags, returning for each task its
+    /// occurrence, `None` if its rule has no next one. See [`Task::next_due_at`].
+    /// A task recurs only once, even if it is done again or instances sharing
+    /// the backend call this at the same time. Recorded as made by nobody.
+    async fn create_next_occurrences(
+        &mut self,
+        limit: i64,
+    ) -> Result<Vec<Option<Task>>, CustomError>;
+
+    /// Applies `operations` in order and in a single transaction, returning
+    /// the outcome of each. Deletes move tasks to the trash. `operations`
+    /// must have been validated.
+    ///
+    /// In atomic mode, stops at the first failure and rolls everything back,
+    /// so the results end with that failure. In best-effort mode, failed
+    /// operations are skipped and the others are committed.
+    async fn bulk(
+        &mut self,
+        actor: &Actor,
+        operations: &[Operation],
+        mode: BulkMode,
+    ) -> Result<Vec<Result<Applied, CustomError>>, CustomError>;
+}
+
+/// The error of a `field` that does not refer to a `resource` of the user.
+fn invalid_reference(field: &'static str, resource: &str) -> CustomError {
+    CustomError::Validation(vec![FieldError::new(field, format!("no such {resource}"))])
+}
+
+/// The error of restoring a subtask without its parent.
+fn parent_in_trash() -> CustomError {
+    CustomError::Conflict("the parent task is in the trash, restore it first".to_owned())
+}
+
+/// Operations on the projects of `owner`, which tasks refer to.
+#[async_trait]
+pub trait ProjectRepository: Send {
+    /// Every project, by name.
+    async fn list_projects(&mut self, owner: i32) -> Result<Vec<Project>, CustomError>;
+
+    async fn get_project(&mut self, owner: i32, id: i32) -> Result<Option<Project>, CustomError>;
+
+    /// Fails with `UniqueViolation` if the name is taken.
+    async fn create_project(
+        &mut self,
+        owner: i32,
+        project: &NewProject,
+    ) -> Result<Project, CustomError>;
+
+    /// Fails with `UniqueViolation` if the name is taken.
+    async fn update_project(
+        &mut self,
+        owner: i32,
+        id: i32,
+        project: &NewProject,
+    ) -> Result<Option<Project>, CustomError>;
+
+    /// Deletes a project along with its tasks and their subtasks, which are
+    /// purged and recorded as such in the audit log. Returns whether there
+    /// was a project to delete.
+    async fn delete_project(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError>;
+}
+
+/// Operations on the tags of `owner` and on the tags of their tasks. Tagging
+/// a task does not change it: it is neither versioned nor audited.
+#[async_trait]
+pub trait TagRepository: Send {
+    /// Every tag, by name.
+    async fn list_tags(&mut self, owner: i32) -> Result<Vec<Tag>, CustomError>;
+
+    /// Fails with `UniqueViolation` if the name is taken.
+    async fn create_tag(&mut self, owner: i32, tag: &NewTag) -> Result<Tag, CustomError>;
+
+    /// Fails with `UniqueViolation` if the name is taken.
+    async fn update_tag(
+        &mut self,
+        owner: i32,
+        id: i32,
+        tag: &NewTag,
+    ) -> Result<Option<Tag>, CustomError>;
+
+    /// Deletes a tag, taking it off its tasks. Returns whether there was a tag
+    /// to delete.
+    async fn delete_tag(&mut self, owner: i32, id: i32) -> Result<bool, CustomError>;
+
+    /// The tags of a task, by name.
+    async fn task_tags(&mut self, owner: i32, task_id: i32) -> Result<Vec<Tag>, CustomError>;
+
+    /// Puts a tag on a task, if it is not already there. Returns whether both
+    /// the task and the tag exist.
+    async fn add_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError>;
+
+    /// Takes a tag off a task. Returns whether the task had it.
+    async fn remove_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError>;
+}
+
+/// Operations on user accounts.
+#[async_trait]
+pub trait UserRepository: Send {
+    /// Fails with `UniqueViolation` if the email is already taken.
+    async fn create_user(&mut self, email: &str, password_hash: &str) -> Result<User, CustomError>;
+
+    async fn get_user(&mut self, id: i32) -> Result<Option<User>, CustomError>;
+
+    /// `email` must already be normalized, see [`crate::models::user::Credentials::email`].
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<User>, CustomError>;
+}
+
+/// The log of committed changes to tasks, recorded by the backend itself
+/// whichever way the tasks change.
+#[async_trait]
+pub trait EventRepository: Send {
+    /// Up to `limit` events after the event `after`, oldest first, of the
+    /// tasks of `owner` or, without one, of everybody.
+    async fn events_after(
+        &mut self,
+        owner: Option<i32>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError>;
+
+    /// Up to `limit` events of everybody recorded since `cursor`, oldest
+    /// first, moving it past them. Unlike [`events_after`](Self::events_after),
+    /// also returns the events committed since by transactions that were in
+    /// flight as the cursor moved past their ids.
+    async fn follow_events(
+        &mut self,
+        cursor: &mut EventCursor,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError>;
+
+    /// Where to follow the events of everybody from, to get those recorded
+    /// from now on.
+    async fn event_cursor(&mut self) -> Result<EventCursor, CustomError>;
+
+    /// The id of the latest event of the tasks of `owner`, 0 if there is none.
+    async fn last_event_id(&mut self, owner: i32) -> Result<i64, CustomError>;
+
+    /// Deletes the events recorded before `before`, returning how many.
+    async fn prune_events(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError>;
+}
+
+/// The audit log of the changes made to tasks, see [`TaskRepository`].
+#[async_trait]
+pub trait AuditRepository: Send {
+    /// A page of the entries of the tasks of `owner` matching `params`,
+    /// newest first. `params` must have been validated.
+    async fn audit_log(
+        &mut self,
+        owner: i32,
+        params: &AuditParams,
+    ) -> Result<AuditPage, CustomError>;
+}
+
+/// The token buckets of the rate limiter, shared by the instances using the
+/// same backend. See [`crate::rate_limit`].
+#[async_trait]
+pub trait RateLimitRepository: Send {
+    /// Takes a token from the bucket `key` as of `now`, atomically.
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: Limit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, CustomError>;
+
+    /// Deletes the buckets untouched since `before`, returning how many.
+    async fn prune_buckets(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError>;
+}
+
+/// The responses stored for idempotency keys, which are scoped to their
+/// owner. See [`crate::idempotency`].
+#[async_trait]
+pub trait IdempotencyRepository: Send {
+    /// Claims `key` for a request with `fingerprint`, atomically, unless
+    /// another request holds it: one still in progress until its own
+    /// `locked_until`, or one done until the key `expires_at`. Returns what
+    /// that request stored, or `None` when the claim succeeds.
+    async fn claim_idempotency_key(
+        &mut self,
+        owner: i32,
+        key: &str,
+        fingerprint: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, CustomError>;
+
+    /// Stores the response of the request holding `key`.
+    async fn save_idempotent_response(
+        &mut self,
+        owner: i32,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<(), CustomError>;
+
+    /// Lets go of `key` while its request is still in progress, for the
+    /// request to be retried.
+    async fn release_idempotency_key(&mut self, owner: i32, key: &str) -> Result<(), CustomError>;
+
+    /// Deletes the keys that expired before `before`, returning how many.
+    async fn prune_idempotency_keys(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError>;
+}
+
+/// The webhooks of `owner` and the outbox of their deliveries, see
+/// [`crate::webhooks`]. Backends add a pending delivery for every webhook
+/// of the owner of a task that wants its events, in the same transaction as
+/// the event itself.
+#[async_trait]
+pub trait WebhookRepository: Send {
+    /// Every webhook, by id.
+    async fn list_webhooks(&mut self, owner: i32) -> Result<Vec<Webhook>, CustomError>;
+
+    /// `webhook` must have been validated.
+    async fn create_webhook(
+        &mut self,
+        owner: i32,
+        webhook: &NewWebhook,
+        secret: &str,
+    ) -> Result<Webhook, CustomError>;
+
+    /// Deletes a webhook along with its deliveries, pending or not. Returns
+    /// whether there was a webhook to delete.
+    async fn delete_webhook(&mut self, owner: i32, id: i32) -> Result<bool, CustomError>;
+
+    /// A page of the deliveries of the webhook `webhook_id` matching
+    /// `params`, newest first, or `None` if there is no such webhook.
+    /// `params` must have been validated.
+    async fn list_deliveries(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        params: &DeliveryParams,
+    ) -> Result<Option<DeliveryPage>, CustomError>;
+
+    /// Makes a delivery of the webhook `webhook_id` pending again as of
+    /// `now`, with every attempt left, whatever its state. Returns `None` if
+    /// there is no such delivery.
+    async fn redeliver(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        id: i64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Delivery>, CustomError>;
+
+    /// Claims up to `limit` pending deliveries of every user due by `now`,
+    /// oldest first, by putting off their next attempt to `lease_until`:
+    /// workers sharing the backend skip them in the meantime.
+    async fn claim_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, CustomError>;
+
+    /// Records the outcome of sending the delivery `id`.
+    async fn record_attempt(&mut self, id: i64, attempt: &Attempt) -> Result<(), CustomError>;
+
+    /// Deletes the deliveries created before `before` that are no longer
+    /// pending, returning how many.
+    async fn prune_deliveries(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError>;
+}
@@ -0,0 +1,1407 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::PgListener;
+use sqlx::types::Json;
+use sqlx::{Acquire, PgConnection, PgPool, Postgres, QueryBuilder};
+
+use super::{
+    invalid_reference, parent_in_trash, AuditRepository, EventRepository, IdempotencyRepository,
+    Listener, PoolStatus, ProjectRepository, RateLimitRepository, Repository, Store, TagRepository,
+    TaskRepository, UserRepository, WebhookRepository,
+};
+use crate::errors::CustomError;
+use crate::models::audit::{Actor, AuditAction, AuditEntry, AuditPage, AuditParams};
+use crate::models::bulk::{Applied, BulkMode, Operation};
+use crate::models::event::{EventCursor, TaskEvent};
+use crate::models::idempotency::{IdempotencyRecord, StoredResponse};
+use crate::models::page::Page;
+use crate::models::project::{NewProject, Project};
+use crate::models::rate_limit::{Bucket, Decision, Limit};
+use crate::models::search::{self, SearchHit, SearchParams};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{ListParams, NewTask, Scope, Sort, Status, Task, UpdateTask};
+use crate::models::user::User;
+use crate::models::webhook::{
+    Attempt, Delivery, DeliveryPage, DeliveryParams, DeliveryStatus, DueDelivery, NewWebhook,
+    Webhook,
+};
+
+pub struct PgStore {
+    pub pool: PgPool,
+}
+
+#[async_trait]
+impl Store for PgStore {
+    async fn acquire(&self) -> Result<Box<dyn Repository>, CustomError> {
+        let conn = self.pool.acquire().await?;
+        Ok(Box::new(PgTaskRepository { conn }))
+    }
+
+    async fn listen(&self) -> Result<Box<dyn Listener>, CustomError> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(EVENTS_CHANNEL).await?;
+        Ok(Box::new(listener))
+    }
+
+    async fn ping(&self) -> Result<(), CustomError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    fn pool_status(&self) -> Option<PoolStatus> {
+        Some(PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+        })
+    }
+}
+
+/// Notified by the `record_task_event` trigger whenever events are committed,
+/// and on redeliveries for the delivery workers.
+const EVENTS_CHANNEL: &str = "task_events";
+
+#[async_trait]
+impl Listener for PgListener {
+    async fn changed(&mut self) -> Result<(), CustomError> {
+        // `None` means the connection was lost, and any notification with it.
+        // The next call reconnects.
+        self.try_recv().await?;
+        Ok(())
+    }
+}
+
+pub struct PgTaskRepository {
+    conn: PoolConnection<Postgres>,
+}
+
+#[async_trait]
+impl TaskRepository for PgTaskRepository {
+    async fn list(
+        &mut self,
+        owner: i32,
+        params: &ListParams,
+        scope: Scope,
+    ) -> Result<Page<Task>, CustomError> {
+        let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM task WHERE TRUE");
+        push_filter(&mut count, owner, params, scope);
+
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *self.conn)
+            .await?;
+
+        let mut select = QueryBuilder::<Postgres>::new("SELECT * FROM task WHERE TRUE");
+        push_filter(&mut select, owner, params, scope);
+
+        if let Some(cursor) = params.cursor {
+            match params.sort {
+                Sort::IdAsc => select.push(" AND id > ").push_bind(cursor),
+                Sort::IdDesc => select.push(" AND id < ").push_bind(cursor),
+                // The cursor row is the last one of the previous page, so continue
+                // right after it in (task, id) order. It may be in the trash by
+                // now, but not purged.
+                Sort::Task => {
+                    let task: String =
+                        sqlx::query_scalar("SELECT task FROM task WHERE id = $1 AND owner_id = $2")
+                            .bind(cursor)
+                            .bind(owner)
+                            .fetch_optional(&mut *self.conn)
+                            .await?
+                            .ok_or_else(|| invalid_reference("cursor", "task"))?;
+                    select
+                        .push(" AND (task, id) > (")
+                        .push_bind(task)
+                        .push(", ")
+                        .push_bind(cursor)
+                        .push(")")
+                }
+            };
+        }
+
+        select.push(match params.sort {
+            Sort::IdAsc => " ORDER BY id ASC",
+            Sort::IdDesc => " ORDER BY id DESC",
+            Sort::Task => " ORDER BY task ASC, id ASC",
+        });
+
+        // Fetch one extra row to know whether there is a next page.
+        select.push(" LIMIT ").push_bind(params.limit() + 1);
+        if let Some(offset) = params.offset {
+            select.push(" OFFSET ").push_bind(offset);
+        }
+
+        let tasks = select
+            .build_query_as::<Task>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        Ok(Page::from_rows(tasks, params.limit(), total, |t| t.id))
+    }
+
+    async fn search(
+        &mut self,
+        owner: i32,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchHit>, CustomError> {
+        // `search_vector` is maintained by a trigger, from the same text.
+        let mut hits: Vec<SearchHit> = sqlx::query_as(
+            "SELECT task.*, ts_rank(search_vector, query) AS rank, \
+             ts_headline('english', replace(task, '<', chr(1)), query, $1) AS snippet \
+             FROM task, websearch_to_tsquery('english', $2) AS query \
+             WHERE owner_id = $3 AND deleted_at IS NULL AND search_vector @@ query \
+             ORDER BY rank DESC, id DESC LIMIT $4",
+        )
+        .bind(format!(
+            "StartSel={}, StopSel={}",
+            search::MATCH_START,
+            search::MATCH_END
+        ))
+        .bind(&params.q)
+        .bind(owner)
+        .bind(params.limit())
+        .fetch_all(&mut *self.conn)
+        .await?;
+        for hit in &mut hits {
+            hit.snippet = search::snippet_html(&hit.snippet);
+        }
+        Ok(hits)
+    }
+
+    async fn export(
+        &mut self,
+        owner: i32,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<Task>, CustomError> {
+        let tasks = sqlx::query_as::<_, Task>(
+            "SELECT * FROM task WHERE owner_id = $1 AND deleted_at IS NULL AND id > $2 \
+             ORDER BY id LIMIT $3",
+        )
+        .bind(owner)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(tasks)
+    }
+
+    async fn get(&mut self, owner: i32, id: i32) -> Result<Option<Task>, CustomError> {
+        select(&mut self.conn, owner, id).await
+    }
+
+    async fn create(&mut self, actor: &Actor, task: &NewTask) -> Result<Task, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let task = insert(&mut tx, actor, task).await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn create_many(
+        &mut self,
+        actor: &Actor,
+        tasks: &[NewTask],
+    ) -> Result<Vec<Task>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let created = insert_many(&mut tx, actor, &tasks.iter().collect::<Vec<_>>()).await?;
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        actor: &Actor,
+        id: i32,
+        version: i32,
+        task: &UpdateTask,
+    ) -> Result<Option<Task>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let Some(current) = lock(&mut tx, actor.user_id, id)
+            .await?
+            .filter(|t| t.deleted_at.is_none() && t.version == version)
+        else {
+            return Ok(None);
+        };
+        let task = update(&mut tx, actor, &current, task).await?;
+        tx.commit().await?;
+        Ok(Some(task))
+    }
+
+    async fn delete(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let deleted = delete(&mut tx, actor, id).await?;
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    async fn restore(&mut self, actor: &Actor, id: i32) -> Result<Option<Task>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let Some(current) = lock(&mut tx, actor.user_id, id)
+            .await?
+            .filter(|t| t.deleted_at.is_some())
+        else {
+            return Ok(None);
+        };
+        if let Some(parent) = current.parent_id {
+            let trashed: bool =
+                sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM task WHERE id=$1")
+                    .bind(parent)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            if trashed {
+                return Err(parent_in_trash());
+            }
+        }
+        // The subtasks that went to the trash along with the task.
+        let ids = subtree(
+            &mut tx,
+            "id = $1",
+            id,
+            "task.deleted_at = subtree.deleted_at",
+        )
+        .await?;
+        let before = lock_all(&mut tx, &ids).await?;
+        let mut after: Vec<Task> =
+            sqlx::query_as("UPDATE task SET deleted_at = NULL WHERE id = ANY($1) RETURNING *")
+                .bind(&ids)
+                .fetch_all(&mut *tx)
+                .await?;
+        after.sort_by_key(|task| task.id);
+        record_all(
+            &mut tx,
+            Some(actor),
+            AuditAction::Restore,
+            &pairs(&before, &after),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(after.into_iter().find(|task| task.id == id))
+    }
+
+    async fn purge(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        if lock(&mut tx, actor.user_id, id).await?.is_none() {
+            return Ok(false);
+        }
+        let ids = subtree(&mut tx, "id = $1", id, "TRUE").await?;
+        purge_all(&mut tx, Some(actor), &ids).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn purge_trash(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let purged: Vec<Task> =
+            sqlx::query_as("DELETE FROM task WHERE deleted_at < $1 RETURNING *")
+                .bind(before)
+                .fetch_all(&mut *tx)
+                .await?;
+        let changes: Vec<_> = purged.iter().map(|task| (Some(task), None)).collect();
+        record_all(&mut tx, None, AuditAction::Purge, &changes).await?;
+        tx.commit().await?;
+        Ok(purged.len() as u64)
+    }
+
+    async fn create_next_occurrences(
+        &mut self,
+        limit: i64,
+    ) -> Result<Vec<Option<Task>>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        // One instance at a time, the others would only wait for its locks
+        // to find nothing left to do.
+        let locked: bool =
+            sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtext('task_occurrence'))")
+                .fetch_one(&mut *tx)
+                .await?;
+        if !locked {
+            return Ok(Vec::new());
+        }
+        let done: Vec<Task> = sqlx::query_as(
+            "SELECT * FROM task \
+             WHERE recurrence IS NOT NULL AND status = 'done' AND deleted_at IS NULL \
+             AND NOT EXISTS (SELECT 1 FROM task_occurrence WHERE task_id = task.id) \
+             ORDER BY id LIMIT $1 FOR UPDATE",
+        )
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut created = Vec::with_capacity(done.len());
+        for task in &done {
+            let next = match task.next_due_at() {
+                Some(due_at) => Some(insert_occurrence(&mut tx, task, due_at).await?),
+                None => None,
+            };
+            sqlx::query("INSERT INTO task_occurrence (task_id, next_id) VALUES ($1, $2)")
+                .bind(task.id)
+                .bind(next.as_ref().map(|next| next.id))
+                .execute(&mut *tx)
+                .await?;
+            created.push(next);
+        }
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    async fn bulk(
+        &mut self,
+        actor: &Actor,
+        operations: &[Operation],
+        mode: BulkMode,
+    ) -> Result<Vec<Result<Applied, CustomError>>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let mut results = Vec::with_capacity(operations.len());
+        let mut rest = operations;
+        while !rest.is_empty() {
+            // Runs of creates go through multi-row INSERTs, as imports are mostly that.
+            let creates: Vec<&NewTask> = rest
+                .iter()
+                .map_while(|op| match op {
+                    Operation::Create { task } => Some(task),
+                    _ => None,
+                })
+                .collect();
+            if creates.len() > 1 {
+                let mut savepoint = tx.begin().await?;
+                if let Ok(created) = insert_many(&mut savepoint, actor, &creates).await {
+                    savepoint.commit().await?;
+                    results.extend(created.into_iter().map(|t| Ok(Applied::Created(t))));
+                    rest = &rest[creates.len()..];
+                    continue;
+                }
+                // Insert them one by one below, to find out which one fails.
+                savepoint.rollback().await?;
+            }
+
+            let count = creates.len().max(1);
+            for op in &rest[..count] {
+                let result = match mode {
+                    // A failure rolls the whole transaction back anyway.
+                    BulkMode::Atomic => apply(&mut tx, actor, op).await,
+                    BulkMode::BestEffort => {
+                        let mut savepoint = tx.begin().await?;
+                        let result = apply(&mut savepoint, actor, op).await;
+                        if result.is_ok() {
+                            savepoint.commit().await?;
+                        } else {
+                            savepoint.rollback().await?;
+                        }
+                        result
+                    }
+                };
+                let failed = result.is_err();
+                results.push(result);
+                if failed && mode == BulkMode::Atomic {
+                    return Ok(results);
+                }
+            }
+            rest = &rest[count..];
+        }
+        tx.commit().await?;
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for PgTaskRepository {
+    async fn list_projects(&mut self, owner: i32) -> Result<Vec<Project>, CustomError> {
+        let projects = sqlx::query_as("SELECT * FROM project WHERE owner_id=$1 ORDER BY name")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(projects)
+    }
+
+    async fn get_project(&mut self, owner: i32, id: i32) -> Result<Option<Project>, CustomError> {
+        let project = sqlx::query_as("SELECT * FROM project WHERE id=$1 AND owner_id=$2")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(project)
+    }
+
+    async fn create_project(
+        &mut self,
+        owner: i32,
+        project: &NewProject,
+    ) -> Result<Project, CustomError> {
+        let project =
+            sqlx::query_as("INSERT INTO project (owner_id, name) VALUES ($1, $2) RETURNING *")
+                .bind(owner)
+                .bind(&project.name)
+                .fetch_one(&mut *self.conn)
+                .await?;
+        Ok(project)
+    }
+
+    async fn update_project(
+        &mut self,
+        owner: i32,
+        id: i32,
+        project: &NewProject,
+    ) -> Result<Option<Project>, CustomError> {
+        let project =
+            sqlx::query_as("UPDATE project SET name=$1 WHERE id=$2 AND owner_id=$3 RETURNING *")
+                .bind(&project.name)
+                .bind(id)
+                .bind(owner)
+                .fetch_optional(&mut *self.conn)
+                .await?;
+        Ok(project)
+    }
+
+    async fn delete_project(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        // Locked so that no task is added to the project in the meantime.
+        let found: Option<i32> =
+            sqlx::query_scalar("SELECT id FROM project WHERE id=$1 AND owner_id=$2 FOR UPDATE")
+                .bind(id)
+                .bind(actor.user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if found.is_none() {
+            return Ok(false);
+        }
+        let ids = subtree(&mut tx, "project_id = $1", id, "TRUE").await?;
+        purge_all(&mut tx, Some(actor), &ids).await?;
+        sqlx::query("DELETE FROM project WHERE id=$1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl TagRepository for PgTaskRepository {
+    async fn list_tags(&mut self, owner: i32) -> Result<Vec<Tag>, CustomError> {
+        let tags = sqlx::query_as("SELECT * FROM tag WHERE owner_id=$1 ORDER BY name")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(tags)
+    }
+
+    async fn create_tag(&mut self, owner: i32, tag: &NewTag) -> Result<Tag, CustomError> {
+        let tag = sqlx::query_as("INSERT INTO tag (owner_id, name) VALUES ($1, $2) RETURNING *")
+            .bind(owner)
+            .bind(&tag.name)
+            .fetch_one(&mut *self.conn)
+            .await?;
+        Ok(tag)
+    }
+
+    async fn update_tag(
+        &mut self,
+        owner: i32,
+        id: i32,
+        tag: &NewTag,
+    ) -> Result<Option<Tag>, CustomError> {
+        let tag = sqlx::query_as("UPDATE tag SET name=$1 WHERE id=$2 AND owner_id=$3 RETURNING *")
+            .bind(&tag.name)
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(tag)
+    }
+
+    async fn delete_tag(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        // Taken off its tasks by the foreign key.
+        let result = sqlx::query("DELETE FROM tag WHERE id=$1 AND owner_id=$2")
+            .bind(id)
+            .bind(owner)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn task_tags(&mut self, owner: i32, task_id: i32) -> Result<Vec<Tag>, CustomError> {
+        let tags = sqlx::query_as(
+            "SELECT tag.* FROM tag JOIN task_tag ON task_tag.tag_id = tag.id \
+             JOIN task ON task.id = task_tag.task_id \
+             WHERE task.id=$1 AND task.owner_id=$2 AND task.deleted_at IS NULL ORDER BY tag.name",
+        )
+        .bind(task_id)
+        .bind(owner)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(tags)
+    }
+
+    async fn add_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let found: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM task WHERE id=$1 AND owner_id=$3 AND deleted_at IS NULL) \
+             AND EXISTS (SELECT 1 FROM tag WHERE id=$2 AND owner_id=$3)",
+        )
+        .bind(task_id)
+        .bind(tag_id)
+        .bind(owner)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        if !found {
+            return Ok(false);
+        }
+        sqlx::query(
+            "INSERT INTO task_tag (task_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(task_id)
+        .bind(tag_id)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(true)
+    }
+
+    async fn remove_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let result = sqlx::query(
+            "DELETE FROM task_tag USING task WHERE task_tag.task_id = task.id \
+             AND task.id=$1 AND task.owner_id=$2 AND task.deleted_at IS NULL AND task_tag.tag_id=$3",
+        )
+        .bind(task_id)
+        .bind(owner)
+        .bind(tag_id)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgTaskRepository {
+    async fn create_user(&mut self, email: &str, password_hash: &str) -> Result<User, CustomError> {
+        let user =
+            sqlx::query_as("INSERT INTO users (email, password_hash) values ($1, $2) RETURNING *")
+                .bind(email)
+                .bind(password_hash)
+                .fetch_one(&mut *self.conn)
+                .await?;
+        Ok(user)
+    }
+
+    async fn get_user(&mut self, id: i32) -> Result<Option<User>, CustomError> {
+        let user = sqlx::query_as("SELECT * FROM users where id=$1")
+            .bind(id)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(user)
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<User>, CustomError> {
+        let user = sqlx::query_as("SELECT * FROM users where email=$1")
+            .bind(email)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl EventRepository for PgTaskRepository {
+    async fn events_after(
+        &mut self,
+        owner: Option<i32>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        let events = sqlx::query_as(
+            "SELECT * FROM task_event WHERE id > $1 AND ($2::integer IS NULL OR owner_id = $2) \
+             ORDER BY id LIMIT $3",
+        )
+        .bind(after)
+        .bind(owner)
+        .bind(limit)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(events)
+    }
+
+    async fn follow_events(
+        &mut self,
+        cursor: &mut EventCursor,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        // The transactions in flight and the events must be read from the
+        // same snapshot, for none to commit in between.
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        let in_flight = in_flight(&mut tx).await?;
+        // Those in flight last time recorded their events below `after`, if
+        // any, and committed them all at once.
+        let events: Vec<TaskEvent> = sqlx::query_as(
+            "(SELECT * FROM task_event WHERE id <= $1 AND xid = ANY($2::text[]::xid8[])) \
+             UNION ALL (SELECT * FROM task_event WHERE id > $1 ORDER BY id LIMIT $3) \
+             ORDER BY id",
+        )
+        .bind(cursor.after)
+        .bind(
+            cursor
+                .in_flight
+                .iter()
+                .map(i64::to_string)
+                .collect::<Vec<_>>(),
+        )
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        if let Some(last) = events.last() {
+            cursor.after = cursor.after.max(last.id);
+        }
+        cursor.in_flight = in_flight;
+        Ok(events)
+    }
+
+    async fn event_cursor(&mut self) -> Result<EventCursor, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        let in_flight = in_flight(&mut tx).await?;
+        let after = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM task_event")
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(EventCursor { after, in_flight })
+    }
+
+    async fn last_event_id(&mut self, owner: i32) -> Result<i64, CustomError> {
+        let id =
+            sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM task_event WHERE owner_id = $1")
+                .bind(owner)
+                .fetch_one(&mut *self.conn)
+                .await?;
+        Ok(id)
+    }
+
+    async fn prune_events(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result = sqlx::query("DELETE FROM task_event WHERE created_at < $1")
+            .bind(before)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl AuditRepository for PgTaskRepository {
+    async fn audit_log(
+        &mut self,
+        owner: i32,
+        params: &AuditParams,
+    ) -> Result<AuditPage, CustomError> {
+        let mut select = QueryBuilder::<Postgres>::new("SELECT * FROM audit_log WHERE owner_id = ");
+        select.push_bind(owner);
+        if let Some(task_id) = params.task_id {
+            select.push(" AND task_id = ").push_bind(task_id);
+        }
+        if let Some(action) = params.action {
+            select.push(" AND action = ").push_bind(action);
+        }
+        if let Some(actor_id) = params.actor_id {
+            select.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(request_id) = &params.request_id {
+            select
+                .push(" AND request_id = ")
+                .push_bind(request_id.clone());
+        }
+        if let Some(since) = params.since {
+            select.push(" AND created_at >= ").push_bind(since);
+        }
+        if let Some(until) = params.until {
+            select.push(" AND created_at < ").push_bind(until);
+        }
+        if let Some(cursor) = params.cursor {
+            select.push(" AND id < ").push_bind(cursor);
+        }
+        select
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(params.limit() + 1);
+
+        let entries = select
+            .build_query_as::<AuditEntry>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        Ok(AuditPage::from_rows(entries, params.limit()))
+    }
+}
+
+/// The ids of the transactions in flight, as of the snapshot of `conn`.
+async fn in_flight(conn: &mut PgConnection) -> Result<Vec<i64>, CustomError> {
+    let ids = sqlx::query_scalar(
+        "SELECT xid::text::bigint FROM pg_snapshot_xip(pg_current_snapshot()) AS xid",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(ids)
+}
+
+async fn select(conn: &mut PgConnection, owner: i32, id: i32) -> Result<Option<Task>, CustomError> {
+    let task =
+        sqlx::query_as("SELECT * FROM task where id=$1 AND owner_id=$2 AND deleted_at IS NULL")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(conn)
+            .await?;
+    Ok(task)
+}
+
+/// The task `id` of `owner`, in the trash or not, locked until the end of the
+/// transaction so that it is recorded as it was right before the change.
+async fn lock(conn: &mut PgConnection, owner: i32, id: i32) -> Result<Option<Task>, CustomError> {
+    let task = sqlx::query_as("SELECT * FROM task WHERE id=$1 AND owner_id=$2 FOR UPDATE")
+        .bind(id)
+        .bind(owner)
+        .fetch_optional(conn)
+        .await?;
+    Ok(task)
+}
+
+/// The tasks `ids`, locked until the end of the transaction, in id order.
+async fn lock_all(conn: &mut PgConnection, ids: &[i32]) -> Result<Vec<Task>, CustomError> {
+    let tasks = sqlx::query_as("SELECT * FROM task WHERE id = ANY($1) ORDER BY id FOR UPDATE")
+        .bind(ids)
+        .fetch_all(conn)
+        .await?;
+    Ok(tasks)
+}
+
+/// The ids, in order, of the tasks matching `start`, which binds `id` as
+/// `$1`, and of their subtasks of every level that `follow` lets through
+/// along with their own subtasks. `follow` is a condition on a `task` and
+/// its parent in the `subtree`.
+async fn subtree(
+    conn: &mut PgConnection,
+    start: &str,
+    id: i32,
+    follow: &str,
+) -> Result<Vec<i32>, CustomError> {
+    let ids = sqlx::query_scalar(&format!(
+        "WITH RECURSIVE subtree AS (\
+           SELECT id, deleted_at FROM task WHERE {start} \
+           UNION SELECT task.id, task.deleted_at FROM task \
+           JOIN subtree ON task.parent_id = subtree.id AND {follow}\
+         ) SELECT id FROM subtree ORDER BY id"
+    ))
+    .bind(id)
+    .fetch_all(conn)
+    .await?;
+    Ok(ids)
+}
+
+/// Checks that the project and the parent of a task, if any, belong to
+/// `owner`. The parent is kept out of the trash until the end of the
+/// transaction.
+async fn check_references(
+    conn: &mut PgConnection,
+    owner: i32,
+    project_id: Option<i32>,
+    parent_id: Option<i32>,
+) -> Result<(), CustomError> {
+    if let Some(project_id) = project_id {
+        let found: Option<i32> =
+            sqlx::query_scalar("SELECT id FROM project WHERE id=$1 AND owner_id=$2 FOR SHARE")
+                .bind(project_id)
+                .bind(owner)
+                .fetch_optional(&mut *conn)
+                .await?;
+        if found.is_none() {
+            return Err(invalid_reference("project_id", "project"));
+        }
+    }
+    if let Some(parent_id) = parent_id {
+        let found: Option<i32> = sqlx::query_scalar(
+            "SELECT id FROM task WHERE id=$1 AND owner_id=$2 AND deleted_at IS NULL FOR SHARE",
+        )
+        .bind(parent_id)
+        .bind(owner)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if found.is_none() {
+            return Err(invalid_reference("parent_id", "task"));
+        }
+    }
+    Ok(())
+}
+
+/// Stores `task` as the new content of `current`, which must be locked.
+async fn update(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    current: &Task,
+    task: &UpdateTask,
+) -> Result<Task, CustomError> {
+    check_references(conn, actor.user_id, task.project_id, None).await?;
+    // `updated_at` and `version` are maintained by triggers.
+    let updated = sqlx::query_as(
+        "UPDATE task SET task=$1, status=$2, priority=$3, due_at=$4, project_id=$5, \
+         recurrence=$6 WHERE id=$7 RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(task.status)
+    .bind(task.priority)
+    .bind(task.due_at)
+    .bind(task.project_id)
+    .bind(&task.recurrence)
+    .bind(current.id)
+    .fetch_one(&mut *conn)
+    .await?;
+    record(
+        conn,
+        Some(actor),
+        AuditAction::Update,
+        Some(current),
+        Some(&updated),
+    )
+    .await?;
+    Ok(updated)
+}
+
+/// Moves a task to the trash, along with its subtasks.
+async fn delete(conn: &mut PgConnection, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+    let current = lock(conn, actor.user_id, id).await?;
+    if current.filter(|t| t.deleted_at.is_none()).is_none() {
+        return Ok(false);
+    }
+    let ids = subtree(conn, "id = $1", id, "task.deleted_at IS NULL").await?;
+    let before = lock_all(conn, &ids).await?;
+    // `now()` is the same for the whole transaction, telling which subtasks
+    // to restore along with the task.
+    let mut after: Vec<Task> =
+        sqlx::query_as("UPDATE task SET deleted_at = now() WHERE id = ANY($1) RETURNING *")
+            .bind(&ids)
+            .fetch_all(&mut *conn)
+            .await?;
+    after.sort_by_key(|task| task.id);
+    record_all(
+        conn,
+        Some(actor),
+        AuditAction::Delete,
+        &pairs(&before, &after),
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Deletes the tasks `ids` for good, recording it.
+async fn purge_all(
+    conn: &mut PgConnection,
+    actor: Option<&Actor>,
+    ids: &[i32],
+) -> Result<(), CustomError> {
+    let mut purged: Vec<Task> = sqlx::query_as("DELETE FROM task WHERE id = ANY($1) RETURNING *")
+        .bind(ids)
+        .fetch_all(&mut *conn)
+        .await?;
+    purged.sort_by_key(|task| task.id);
+    let changes: Vec<_> = purged.iter().map(|task| (Some(task), None)).collect();
+    record_all(conn, actor, AuditAction::Purge, &changes).await
+}
+
+/// Pairs up the same tasks before and after a change, both in id order.
+fn pairs<'a>(before: &'a [Task], after: &'a [Task]) -> Vec<(Option<&'a Task>, Option<&'a Task>)> {
+    before
+        .iter()
+        .zip(after)
+        .map(|(before, after)| (Some(before), Some(after)))
+        .collect()
+}
+
+/// Applies a single operation of a bulk request.
+async fn apply(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    op: &Operation,
+) -> Result<Applied, CustomError> {
+    match op {
+        Operation::Create { task } => Ok(Applied::Created(insert(conn, actor, task).await?)),
+        Operation::Update { id, version, task } => {
+            let current = lock(conn, actor.user_id, *id)
+                .await?
+                .filter(|t| t.deleted_at.is_none())
+                .ok_or_else(|| CustomError::not_found("task", id))?;
+            if version.is_some_and(|version| version != current.version) {
+                return Err(CustomError::PreconditionFailed);
+            }
+            let task = update(conn, actor, &current, task).await?;
+            Ok(Applied::Updated(task))
+        }
+        Operation::Delete { id } => {
+            if !delete(conn, actor, *id).await? {
+                return Err(CustomError::not_found("task", id));
+            }
+            Ok(Applied::Deleted)
+        }
+    }
+}
+
+/// Rows per multi-row INSERT, well below the limit of 65535 bind parameters.
+const INSERT_CHUNK_SIZE: usize = 1000;
+
+/// Inserts `tasks` with as few statements as possible, returning them in order.
+async fn insert_many(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    tasks: &[&NewTask],
+) -> Result<Vec<Task>, CustomError> {
+    for task in tasks {
+        check_references(conn, actor.user_id, task.project_id, task.parent_id).await?;
+    }
+    let mut created = Vec::with_capacity(tasks.len());
+    for chunk in tasks.chunks(INSERT_CHUNK_SIZE) {
+        let mut insert = QueryBuilder::<Postgres>::new(
+            "INSERT INTO task \
+             (task, status, priority, due_at, owner_id, project_id, parent_id, recurrence) ",
+        );
+        insert.push_values(chunk, |mut row, task| {
+            row.push_bind(&task.task)
+                .push_bind(task.status)
+                .push_bind(task.priority)
+                .push_bind(task.due_at)
+                .push_bind(actor.user_id)
+                .push_bind(task.project_id)
+                .push_bind(task.parent_id)
+                .push_bind(&task.recurrence);
+        });
+        insert.push(" RETURNING *");
+        let mut rows = insert
+            .build_query_as::<Task>()
+            .fetch_all(&mut *conn)
+            .await?;
+        // Ids are handed out in the order of the values, which RETURNING does not promise to keep.
+        rows.sort_by_key(|task| task.id);
+        let changes: Vec<_> = rows.iter().map(|task| (None, Some(task))).collect();
+        record_all(conn, Some(actor), AuditAction::Create, &changes).await?;
+        created.extend(rows);
+    }
+    Ok(created)
+}
+
+/// Records a change in the audit log. `before` and `after` cannot both be absent.
+async fn record(
+    conn: &mut PgConnection,
+    actor: Option<&Actor>,
+    action: AuditAction,
+    before: Option<&Task>,
+    after: Option<&Task>,
+) -> Result<(), CustomError> {
+    record_all(conn, actor, action, &[(before, after)]).await
+}
+
+/// Records changes of the same kind in the audit log, as `(before, after)`.
+async fn record_all(
+    conn: &mut PgConnection,
+    actor: Option<&Actor>,
+    action: AuditAction,
+    changes: &[(Option<&Task>, Option<&Task>)],
+) -> Result<(), CustomError> {
+    for chunk in changes.chunks(INSERT_CHUNK_SIZE) {
+        let mut insert = QueryBuilder::<Postgres>::new(
+            "INSERT INTO audit_log (action, task_id, owner_id, actor_id, request_id, before, after) ",
+        );
+        insert.push_values(chunk, |mut row, &(before, after)| {
+            let task = before
+                .or(after)
+                .expect("a change has a task before or after it");
+            row.push_bind(action)
+                .push_bind(task.id)
+                .push_bind(task.owner_id)
+                .push_bind(actor.map(|actor| actor.user_id))
+                .push_bind(actor.and_then(|actor| actor.request_id.clone()))
+                .push_bind(before.map(Json))
+                .push_bind(after.map(Json));
+        });
+        insert.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+async fn insert(
+    conn: &mut PgConnection,
+    actor: &Actor,
+    task: &NewTask,
+) -> Result<Task, CustomError> {
+    check_references(conn, actor.user_id, task.project_id, task.parent_id).await?;
+    let created = sqlx::query_as(
+        "INSERT INTO task \
+         (task, status, priority, due_at, owner_id, project_id, parent_id, recurrence) \
+         values ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(task.status)
+    .bind(task.priority)
+    .bind(task.due_at)
+    .bind(actor.user_id)
+    .bind(task.project_id)
+    .bind(task.parent_id)
+    .bind(&task.recurrence)
+    .fetch_one(&mut *conn)
+    .await?;
+    record(conn, Some(actor), AuditAction::Create, None, Some(&created)).await?;
+    Ok(created)
+}
+
+/// Creates the occurrence of the recurring `task` due at `due_at`, a copy of
+/// it with its tags that is still to do.
+async fn insert_occurrence(
+    conn: &mut PgConnection,
+    task: &Task,
+    due_at: DateTime<Utc>,
+) -> Result<Task, CustomError> {
+    let created: Task = sqlx::query_as(
+        "INSERT INTO task \
+         (task, status, priority, due_at, owner_id, project_id, parent_id, recurrence) \
+         values ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(Status::Todo)
+    .bind(task.priority)
+    .bind(due_at)
+    .bind(task.owner_id)
+    .bind(task.project_id)
+    .bind(task.parent_id)
+    .bind(&task.recurrence)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO task_tag (task_id, tag_id) SELECT $1, tag_id FROM task_tag WHERE task_id = $2",
+    )
+    .bind(created.id)
+    .bind(task.id)
+    .execute(&mut *conn)
+    .await?;
+    record(conn, None, AuditAction::Create, None, Some(&created)).await?;
+    Ok(created)
+}
+
+fn push_filter(
+    query: &mut QueryBuilder<'_, Postgres>,
+    owner: i32,
+    params: &ListParams,
+    scope: Scope,
+) {
+    query.push(" AND owner_id = ").push_bind(owner);
+    query.push(match scope {
+        Scope::Active => " AND deleted_at IS NULL",
+        Scope::Trash => " AND deleted_at IS NOT NULL",
+    });
+    if let Some(q) = params.q() {
+        query
+            .push(" AND strpos(lower(task), lower(")
+            .push_bind(q.to_owned())
+            .push(")) > 0");
+    }
+    if let Some(project) = params.project {
+        query.push(" AND project_id = ").push_bind(project);
+    }
+    if let Some(parent) = params.parent {
+        query.push(" AND parent_id = ").push_bind(parent);
+    }
+    if let Some(tag) = &params.tag {
+        query
+            .push(" AND id IN (SELECT task_id FROM task_tag JOIN tag ON tag.id = task_tag.tag_id")
+            .push(" WHERE tag.owner_id = ")
+            .push_bind(owner)
+            .push(" AND tag.name = ")
+            .push_bind(tag.clone())
+            .push(")");
+    }
+}
+
+#[async_trait]
+impl RateLimitRepository for PgTaskRepository {
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: Limit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        // Creates a full bucket for a new client, locking it either way.
+        let bucket: Bucket = sqlx::query_as(
+            "INSERT INTO rate_limit_bucket (key, tokens, updated_at) VALUES ($1, $2, $3) \
+             ON CONFLICT (key) DO UPDATE SET key = EXCLUDED.key RETURNING tokens, updated_at",
+        )
+        .bind(key)
+        .bind(f64::from(limit.requests))
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        let (bucket, decision) = Bucket::take(Some(bucket), limit, now);
+        sqlx::query("UPDATE rate_limit_bucket SET tokens = $1, updated_at = $2 WHERE key = $3")
+            .bind(bucket.tokens)
+            .bind(bucket.updated_at)
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(decision)
+    }
+
+    async fn prune_buckets(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result = sqlx::query("DELETE FROM rate_limit_bucket WHERE updated_at < $1")
+            .bind(before)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for PgTaskRepository {
+    async fn claim_idempotency_key(
+        &mut self,
+        owner: i32,
+        key: &str,
+        fingerprint: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, CustomError> {
+        // Takes over keys that expired, or that a request gave up on.
+        let claimed = sqlx::query(
+            "INSERT INTO idempotency_key (owner_id, key, fingerprint, locked_until, expires_at) \
+             VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (owner_id, key) DO UPDATE SET fingerprint = EXCLUDED.fingerprint, \
+               status = NULL, content_type = NULL, body = NULL, \
+               locked_until = EXCLUDED.locked_until, expires_at = EXCLUDED.expires_at \
+             WHERE idempotency_key.expires_at <= $6 \
+               OR (idempotency_key.status IS NULL AND idempotency_key.locked_until <= $6) \
+             RETURNING owner_id",
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(fingerprint)
+        .bind(locked_until)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+        let record = sqlx::query_as(
+            "SELECT fingerprint, status, content_type, body FROM idempotency_key \
+             WHERE owner_id = $1 AND key = $2",
+        )
+        .bind(owner)
+        .bind(key)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(Some(record))
+    }
+
+    async fn save_idempotent_response(
+        &mut self,
+        owner: i32,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<(), CustomError> {
+        sqlx::query(
+            "UPDATE idempotency_key SET status = $3, content_type = $4, body = $5 \
+             WHERE owner_id = $1 AND key = $2",
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(response.status as i16)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&mut self, owner: i32, key: &str) -> Result<(), CustomError> {
+        sqlx::query(
+            "DELETE FROM idempotency_key WHERE owner_id = $1 AND key = $2 AND status IS NULL",
+        )
+        .bind(owner)
+        .bind(key)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_idempotency_keys(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result = sqlx::query("DELETE FROM idempotency_key WHERE expires_at < $1")
+            .bind(before)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for PgTaskRepository {
+    async fn list_webhooks(&mut self, owner: i32) -> Result<Vec<Webhook>, CustomError> {
+        let webhooks = sqlx::query_as("SELECT * FROM webhook WHERE owner_id = $1 ORDER BY id")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(webhooks)
+    }
+
+    async fn create_webhook(
+        &mut self,
+        owner: i32,
+        webhook: &NewWebhook,
+        secret: &str,
+    ) -> Result<Webhook, CustomError> {
+        let webhook = sqlx::query_as(
+            "INSERT INTO webhook (owner_id, url, events, secret) VALUES ($1, $2, $3, $4) \
+             RETURNING *",
+        )
+        .bind(owner)
+        .bind(&webhook.url)
+        .bind(Json(webhook.events()))
+        .bind(secret)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(webhook)
+    }
+
+    async fn delete_webhook(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        // Its deliveries go with it through the foreign key.
+        let result = sqlx::query("DELETE FROM webhook WHERE id = $1 AND owner_id = $2")
+            .bind(id)
+            .bind(owner)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_deliveries(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        params: &DeliveryParams,
+    ) -> Result<Option<DeliveryPage>, CustomError> {
+        let found: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM webhook WHERE id = $1 AND owner_id = $2)",
+        )
+        .bind(webhook_id)
+        .bind(owner)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        if !found {
+            return Ok(None);
+        }
+
+        let mut select =
+            QueryBuilder::<Postgres>::new("SELECT * FROM webhook_delivery WHERE webhook_id = ");
+        select.push_bind(webhook_id);
+        if let Some(status) = params.status {
+            select.push(" AND status = ").push_bind(status);
+        }
+        if let Some(cursor) = params.cursor {
+            select.push(" AND id < ").push_bind(cursor);
+        }
+        select
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(params.limit() + 1);
+
+        let rows = select
+            .build_query_as::<Delivery>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(Some(DeliveryPage::from_rows(rows, params.limit())))
+    }
+
+    async fn redeliver(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        id: i64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Delivery>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let delivery = sqlx::query_as(
+            "UPDATE webhook_delivery SET status = 'pending', attempts = 0, next_attempt_at = $1 \
+             FROM webhook WHERE webhook.id = webhook_delivery.webhook_id \
+               AND webhook_delivery.id = $2 AND webhook_id = $3 AND webhook.owner_id = $4 \
+             RETURNING webhook_delivery.*",
+        )
+        .bind(now)
+        .bind(id)
+        .bind(webhook_id)
+        .bind(owner)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if delivery.is_some() {
+            sqlx::query("SELECT pg_notify($1, '')")
+                .bind(EVENTS_CHANNEL)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(delivery)
+    }
+
+    async fn claim_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, CustomError> {
+        // Skipping the rows other workers are claiming, rather than waiting for them.
+        let mut deliveries: Vec<DueDelivery> = sqlx::query_as(
+            "UPDATE webhook_delivery SET next_attempt_at = $1 FROM webhook \
+             WHERE webhook.id = webhook_delivery.webhook_id AND webhook_delivery.id IN ( \
+               SELECT id FROM webhook_delivery WHERE status = 'pending' AND next_attempt_at <= $2 \
+               ORDER BY next_attempt_at, id LIMIT $3 FOR UPDATE SKIP LOCKED) \
+             RETURNING webhook_delivery.id, webhook.url, webhook.secret, \
+               webhook_delivery.attempts, webhook_delivery.event_id, webhook_delivery.kind, \
+               webhook_delivery.task_id, webhook_delivery.task, webhook_delivery.created_at",
+        )
+        .bind(lease_until)
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        deliveries.sort_by_key(|delivery| delivery.id);
+        Ok(deliveries)
+    }
+
+    async fn record_attempt(&mut self, id: i64, attempt: &Attempt) -> Result<(), CustomError> {
+        sqlx::query(
+            "UPDATE webhook_delivery SET status = $1, attempts = attempts + 1, \
+               next_attempt_at = $2, response_status = $3, last_error = $4, \
+               delivered_at = CASE WHEN $1 = 'delivered' THEN $5 END \
+             WHERE id = $6",
+        )
+        .bind(attempt.status)
+        .bind(attempt.next_attempt_at)
+        .bind(attempt.response_status)
+        .bind(&attempt.error)
+        .bind(attempt.at)
+        .bind(id)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_deliveries(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result =
+            sqlx::query("DELETE FROM webhook_delivery WHERE status <> $1 AND created_at < $2")
+                .bind(DeliveryStatus::Pending)
+                .bind(before)
+                .execute(&mut *self.conn)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
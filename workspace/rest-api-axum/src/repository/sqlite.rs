@@ -0,0 +1,1370 @@
+use std::sync::Arc;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::pool::PoolConnection;
+use sqlx::types::Json;
+use sqlx::{Acquire, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use tokio::sync::watch;
+
+use super::{
+    invalid_reference, parent_in_trash, AuditRepository, EventRepository, IdempotencyRepository,
+    Listener, PoolStatus, ProjectRepository, RateLimitRepository, Repository, Store, TagRepository,
+    TaskRepository, UserRepository, WebhookRepository,
+};
+use crate::errors::CustomError;
+use crate::models::audit::{Actor, AuditAction, AuditEntry, AuditPage, AuditParams};
+use crate::models::bulk::{Applied, BulkMode, Operation};
+use crate::models::event::{EventCursor, TaskEvent};
+use crate::models::idempotency::{IdempotencyRecord, StoredResponse};
+use crate::models::page::Page;
+use crate::models::project::{NewProject, Project};
+use crate::models::rate_limit::{Bucket, Decision, Limit};
+use crate::models::search::{self, SearchHit, SearchParams};
+use crate::models::tag::{NewTag, Tag};
+use crate::models::task::{ListParams, NewTask, Scope, Sort, Status, Task, UpdateTask};
+use crate::models::user::User;
+use crate::models::webhook::{
+    Attempt, Delivery, DeliveryPage, DeliveryParams, DeliveryStatus, DueDelivery, NewWebhook,
+    Webhook,
+};
+
+pub struct SqliteStore {
+    pub pool: SqlitePool,
+    /// Poked after every write. Writes of other processes are only noticed
+    /// by polling.
+    changes: Arc<watch::Sender<()>>,
+}
+
+impl SqliteStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            changes: Arc::new(watch::channel(()).0),
+        }
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn acquire(&self) -> Result<Box<dyn Repository>, CustomError> {
+        let conn = self.pool.acquire().await?;
+        Ok(Box::new(SqliteTaskRepository {
+            conn,
+            changes: self.changes.clone(),
+        }))
+    }
+
+    async fn listen(&self) -> Result<Box<dyn Listener>, CustomError> {
+        Ok(Box::new(self.changes.subscribe()))
+    }
+
+    async fn ping(&self) -> Result<(), CustomError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    fn pool_status(&self) -> Option<PoolStatus> {
+        Some(PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+        })
+    }
+}
+
+pub struct SqliteTaskRepository {
+    conn: PoolConnection<Sqlite>,
+    changes: Arc<watch::Sender<()>>,
+}
+
+impl SqliteTaskRepository {
+    /// Wakes up the listeners once a write is committed.
+    fn changed(&self) {
+        self.changes.send_replace(());
+    }
+}
+
+#[async_trait]
+impl TaskRepository for SqliteTaskRepository {
+    async fn list(
+        &mut self,
+        owner: i32,
+        params: &ListParams,
+        scope: Scope,
+    ) -> Result<Page<Task>, CustomError> {
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM task WHERE TRUE");
+        push_filter(&mut count, owner, params, scope);
+
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *self.conn)
+            .await?;
+
+        let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM task WHERE TRUE");
+        push_filter(&mut select, owner, params, scope);
+
+        if let Some(cursor) = params.cursor {
+            match params.sort {
+                Sort::IdAsc => select.push(" AND id > ").push_bind(cursor),
+                Sort::IdDesc => select.push(" AND id < ").push_bind(cursor),
+                Sort::Task => {
+                    let task: String =
+                        sqlx::query_scalar("SELECT task FROM task WHERE id = ? AND owner_id = ?")
+                            .bind(cursor)
+                            .bind(owner)
+                            .fetch_optional(&mut *self.conn)
+                            .await?
+                            .ok_or_else(|| invalid_reference("cursor", "task"))?;
+                    select
+                        .push(" AND (task, id) > (")
+                        .push_bind(task)
+                        .push(", ")
+                        .push_bind(cursor)
+                        .push(")")
+                }
+            };
+        }
+
+        select.push(match params.sort {
+            Sort::IdAsc => " ORDER BY id ASC",
+            Sort::IdDesc => " ORDER BY id DESC",
+            Sort::Task => " ORDER BY task ASC, id ASC",
+        });
+
+        select.push(" LIMIT ").push_bind(params.limit() + 1);
+        if let Some(offset) = params.offset {
+            select.push(" OFFSET ").push_bind(offset);
+        }
+
+        let tasks = select
+            .build_query_as::<Task>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        Ok(Page::from_rows(tasks, params.limit(), total, |t| t.id))
+    }
+
+    /// Without full-text search, matches the tasks containing every word.
+    async fn search(
+        &mut self,
+        owner: i32,
+        params: &SearchParams,
+    ) -> Result<Vec<SearchHit>, CustomError> {
+        let terms = params.terms();
+        let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM task WHERE owner_id = ");
+        select.push_bind(owner).push(" AND deleted_at IS NULL");
+        for term in &terms {
+            select
+                .push(" AND task LIKE ")
+                .push_bind(like_pattern(term))
+                .push(" ESCAPE '\\'");
+        }
+        select
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(params.limit());
+
+        let tasks = select
+            .build_query_as::<Task>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| SearchHit {
+                snippet: search::snippet_html(&search::mark_terms(&task.task, &terms)),
+                rank: None,
+                task,
+            })
+            .collect())
+    }
+
+    async fn export(
+        &mut self,
+        owner: i32,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<Task>, CustomError> {
+        let tasks = sqlx::query_as::<_, Task>(
+            "SELECT * FROM task WHERE owner_id = ? AND deleted_at IS NULL AND id > ? \
+             ORDER BY id LIMIT ?",
+        )
+        .bind(owner)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(tasks)
+    }
+
+    async fn get(&mut self, owner: i32, id: i32) -> Result<Option<Task>, CustomError> {
+        select(&mut self.conn, owner, id).await
+    }
+
+    async fn create(&mut self, actor: &Actor, task: &NewTask) -> Result<Task, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let task = insert(&mut tx, actor, task, Utc::now()).await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(task)
+    }
+
+    async fn create_many(
+        &mut self,
+        actor: &Actor,
+        tasks: &[NewTask],
+    ) -> Result<Vec<Task>, CustomError> {
+        let now = Utc::now();
+        let mut tx = self.conn.begin().await?;
+        let mut created = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            created.push(insert(&mut tx, actor, task, now).await?);
+        }
+        tx.commit().await?;
+        self.changed();
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        actor: &Actor,
+        id: i32,
+        version: i32,
+        task: &UpdateTask,
+    ) -> Result<Option<Task>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let Some(current) = select_any(&mut tx, actor.user_id, id)
+            .await?
+            .filter(|t| t.deleted_at.is_none() && t.version == version)
+        else {
+            return Ok(None);
+        };
+        let task = update(&mut tx, actor, &current, task).await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(Some(task))
+    }
+
+    async fn delete(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let deleted = delete(&mut tx, actor, id).await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(deleted)
+    }
+
+    async fn restore(&mut self, actor: &Actor, id: i32) -> Result<Option<Task>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let Some(current) = select_any(&mut tx, actor.user_id, id)
+            .await?
+            .filter(|t| t.deleted_at.is_some())
+        else {
+            return Ok(None);
+        };
+        if let Some(parent) = current.parent_id {
+            let trashed: bool =
+                sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM task WHERE id = ?")
+                    .bind(parent)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            if trashed {
+                return Err(parent_in_trash());
+            }
+        }
+        // The subtasks that went to the trash along with the task.
+        let ids = subtree(
+            &mut tx,
+            "id = ?",
+            id,
+            "task.deleted_at = subtree.deleted_at",
+        )
+        .await?;
+        let before = select_all(&mut tx, &ids).await?;
+        let after: Vec<Task> = sqlx::query_as(
+            "UPDATE task SET deleted_at = NULL, updated_at = ?, version = version + 1 \
+             WHERE id IN (SELECT value FROM json_each(?)) RETURNING *",
+        )
+        .bind(Utc::now())
+        .bind(Json(&ids))
+        .fetch_all(&mut *tx)
+        .await?;
+        let after = in_order(after);
+        record_all(&mut tx, Some(actor), AuditAction::Restore, &before, &after).await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(after.into_iter().find(|task| task.id == id))
+    }
+
+    async fn purge(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        if select_any(&mut tx, actor.user_id, id).await?.is_none() {
+            return Ok(false);
+        }
+        let ids = subtree(&mut tx, "id = ?", id, "TRUE").await?;
+        purge_all(&mut tx, Some(actor), &ids).await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(true)
+    }
+
+    async fn purge_trash(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let ids: Vec<i32> = sqlx::query_scalar(
+            "SELECT id FROM task WHERE julianday(deleted_at) < julianday(?) ORDER BY id",
+        )
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        purge_all(&mut tx, None, &ids).await?;
+        tx.commit().await?;
+        Ok(ids.len() as u64)
+    }
+
+    /// Other processes sharing the file wait for the write lock, and would
+    /// then fail on the primary key of `task_occurrence` rather than create
+    /// an occurrence twice.
+    async fn create_next_occurrences(
+        &mut self,
+        limit: i64,
+    ) -> Result<Vec<Option<Task>>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let done: Vec<Task> = sqlx::query_as(
+            "SELECT * FROM task \
+             WHERE recurrence IS NOT NULL AND status = 'done' AND deleted_at IS NULL \
+             AND NOT EXISTS (SELECT 1 FROM task_occurrence WHERE task_id = task.id) \
+             ORDER BY id LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        let now = Utc::now();
+        let mut created = Vec::with_capacity(done.len());
+        for task in &done {
+            let next = match task.next_due_at() {
+                Some(due_at) => Some(insert_occurrence(&mut tx, task, due_at, now).await?),
+                None => None,
+            };
+            sqlx::query(
+                "INSERT INTO task_occurrence (task_id, next_id, created_at) VALUES (?, ?, ?)",
+            )
+            .bind(task.id)
+            .bind(next.as_ref().map(|next| next.id))
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            created.push(next);
+        }
+        tx.commit().await?;
+        if !done.is_empty() {
+            self.changed();
+        }
+        Ok(created)
+    }
+
+    async fn bulk(
+        &mut self,
+        actor: &Actor,
+        operations: &[Operation],
+        mode: BulkMode,
+    ) -> Result<Vec<Result<Applied, CustomError>>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let mut results = Vec::with_capacity(operations.len());
+        for op in operations {
+            let result = match mode {
+                BulkMode::Atomic => apply(&mut tx, actor, op).await,
+                BulkMode::BestEffort => {
+                    let mut savepoint = tx.begin().await?;
+                    let result = apply(&mut savepoint, actor, op).await;
+                    if result.is_ok() {
+                        savepoint.commit().await?;
+                    } else {
+                        savepoint.rollback().await?;
+                    }
+                    result
+                }
+            };
+            let failed = result.is_err();
+            results.push(result);
+            if failed && mode == BulkMode::Atomic {
+                return Ok(results);
+            }
+        }
+        tx.commit().await?;
+        self.changed();
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for SqliteTaskRepository {
+    async fn list_projects(&mut self, owner: i32) -> Result<Vec<Project>, CustomError> {
+        let projects = sqlx::query_as("SELECT * FROM project WHERE owner_id = ? ORDER BY name")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(projects)
+    }
+
+    async fn get_project(&mut self, owner: i32, id: i32) -> Result<Option<Project>, CustomError> {
+        let project = sqlx::query_as("SELECT * FROM project WHERE id = ? AND owner_id = ?")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(project)
+    }
+
+    async fn create_project(
+        &mut self,
+        owner: i32,
+        project: &NewProject,
+    ) -> Result<Project, CustomError> {
+        let project = sqlx::query_as(
+            "INSERT INTO project (owner_id, name, created_at) VALUES (?, ?, ?) RETURNING *",
+        )
+        .bind(owner)
+        .bind(&project.name)
+        .bind(Utc::now())
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(project)
+    }
+
+    async fn update_project(
+        &mut self,
+        owner: i32,
+        id: i32,
+        project: &NewProject,
+    ) -> Result<Option<Project>, CustomError> {
+        let project =
+            sqlx::query_as("UPDATE project SET name = ? WHERE id = ? AND owner_id = ? RETURNING *")
+                .bind(&project.name)
+                .bind(id)
+                .bind(owner)
+                .fetch_optional(&mut *self.conn)
+                .await?;
+        Ok(project)
+    }
+
+    async fn delete_project(&mut self, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        let found: Option<i32> =
+            sqlx::query_scalar("SELECT id FROM project WHERE id = ? AND owner_id = ?")
+                .bind(id)
+                .bind(actor.user_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if found.is_none() {
+            return Ok(false);
+        }
+        let ids = subtree(&mut tx, "project_id = ?", id, "TRUE").await?;
+        purge_all(&mut tx, Some(actor), &ids).await?;
+        sqlx::query("DELETE FROM project WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.changed();
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl TagRepository for SqliteTaskRepository {
+    async fn list_tags(&mut self, owner: i32) -> Result<Vec<Tag>, CustomError> {
+        let tags = sqlx::query_as("SELECT * FROM tag WHERE owner_id = ? ORDER BY name")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(tags)
+    }
+
+    async fn create_tag(&mut self, owner: i32, tag: &NewTag) -> Result<Tag, CustomError> {
+        let tag = sqlx::query_as(
+            "INSERT INTO tag (owner_id, name, created_at) VALUES (?, ?, ?) RETURNING *",
+        )
+        .bind(owner)
+        .bind(&tag.name)
+        .bind(Utc::now())
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(tag)
+    }
+
+    async fn update_tag(
+        &mut self,
+        owner: i32,
+        id: i32,
+        tag: &NewTag,
+    ) -> Result<Option<Tag>, CustomError> {
+        let tag =
+            sqlx::query_as("UPDATE tag SET name = ? WHERE id = ? AND owner_id = ? RETURNING *")
+                .bind(&tag.name)
+                .bind(id)
+                .bind(owner)
+                .fetch_optional(&mut *self.conn)
+                .await?;
+        Ok(tag)
+    }
+
+    async fn delete_tag(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        let result = sqlx::query("DELETE FROM tag WHERE id = ? AND owner_id = ?")
+            .bind(id)
+            .bind(owner)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn task_tags(&mut self, owner: i32, task_id: i32) -> Result<Vec<Tag>, CustomError> {
+        let tags = sqlx::query_as(
+            "SELECT tag.* FROM tag JOIN task_tag ON task_tag.tag_id = tag.id \
+             JOIN task ON task.id = task_tag.task_id \
+             WHERE task.id = ? AND task.owner_id = ? AND task.deleted_at IS NULL \
+             ORDER BY tag.name",
+        )
+        .bind(task_id)
+        .bind(owner)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(tags)
+    }
+
+    async fn add_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let found: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM task WHERE id = ?1 AND owner_id = ?3 AND deleted_at IS NULL) \
+             AND EXISTS (SELECT 1 FROM tag WHERE id = ?2 AND owner_id = ?3)",
+        )
+        .bind(task_id)
+        .bind(tag_id)
+        .bind(owner)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        if !found {
+            return Ok(false);
+        }
+        sqlx::query("INSERT OR IGNORE INTO task_tag (task_id, tag_id) VALUES (?, ?)")
+            .bind(task_id)
+            .bind(tag_id)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(true)
+    }
+
+    async fn remove_task_tag(
+        &mut self,
+        owner: i32,
+        task_id: i32,
+        tag_id: i32,
+    ) -> Result<bool, CustomError> {
+        let result = sqlx::query(
+            "DELETE FROM task_tag WHERE tag_id = ? AND task_id = (\
+               SELECT id FROM task WHERE id = ? AND owner_id = ? AND deleted_at IS NULL)",
+        )
+        .bind(tag_id)
+        .bind(task_id)
+        .bind(owner)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteTaskRepository {
+    async fn create_user(&mut self, email: &str, password_hash: &str) -> Result<User, CustomError> {
+        let user = sqlx::query_as(
+            "INSERT INTO users (email, password_hash, created_at) VALUES (?, ?, ?) RETURNING *",
+        )
+        .bind(email)
+        .bind(password_hash)
+        .bind(Utc::now())
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(user)
+    }
+
+    async fn get_user(&mut self, id: i32) -> Result<Option<User>, CustomError> {
+        let user = sqlx::query_as("SELECT * FROM users WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(user)
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<User>, CustomError> {
+        let user = sqlx::query_as("SELECT * FROM users WHERE email = ?")
+            .bind(email)
+            .fetch_optional(&mut *self.conn)
+            .await?;
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl EventRepository for SqliteTaskRepository {
+    async fn events_after(
+        &mut self,
+        owner: Option<i32>,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        let events = sqlx::query_as(
+            "SELECT * FROM task_event WHERE id > ? AND (? IS NULL OR owner_id = ?) \
+             ORDER BY id LIMIT ?",
+        )
+        .bind(after)
+        .bind(owner)
+        .bind(owner)
+        .bind(limit)
+        .fetch_all(&mut *self.conn)
+        .await?;
+        Ok(events)
+    }
+
+    async fn follow_events(
+        &mut self,
+        cursor: &mut EventCursor,
+        limit: i64,
+    ) -> Result<Vec<TaskEvent>, CustomError> {
+        // Writers take turns, so events are recorded in id order.
+        let events = self.events_after(None, cursor.after, limit).await?;
+        if let Some(last) = events.last() {
+            cursor.after = last.id;
+        }
+        Ok(events)
+    }
+
+    async fn event_cursor(&mut self) -> Result<EventCursor, CustomError> {
+        let after = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM task_event")
+            .fetch_one(&mut *self.conn)
+            .await?;
+        Ok(EventCursor {
+            after,
+            in_flight: Vec::new(),
+        })
+    }
+
+    async fn last_event_id(&mut self, owner: i32) -> Result<i64, CustomError> {
+        let id =
+            sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM task_event WHERE owner_id = ?")
+                .bind(owner)
+                .fetch_one(&mut *self.conn)
+                .await?;
+        Ok(id)
+    }
+
+    async fn prune_events(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        // The trigger and sqlx spell timestamps differently, so compare them as dates.
+        let result =
+            sqlx::query("DELETE FROM task_event WHERE julianday(created_at) < julianday(?)")
+                .bind(before)
+                .execute(&mut *self.conn)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl AuditRepository for SqliteTaskRepository {
+    async fn audit_log(
+        &mut self,
+        owner: i32,
+        params: &AuditParams,
+    ) -> Result<AuditPage, CustomError> {
+        let mut select = QueryBuilder::<Sqlite>::new("SELECT * FROM audit_log WHERE owner_id = ");
+        select.push_bind(owner);
+        if let Some(task_id) = params.task_id {
+            select.push(" AND task_id = ").push_bind(task_id);
+        }
+        if let Some(action) = params.action {
+            select.push(" AND action = ").push_bind(action);
+        }
+        if let Some(actor_id) = params.actor_id {
+            select.push(" AND actor_id = ").push_bind(actor_id);
+        }
+        if let Some(request_id) = &params.request_id {
+            select
+                .push(" AND request_id = ")
+                .push_bind(request_id.clone());
+        }
+        if let Some(since) = params.since {
+            select
+                .push(" AND julianday(created_at) >= julianday(")
+                .push_bind(since)
+                .push(")");
+        }
+        if let Some(until) = params.until {
+            select
+                .push(" AND julianday(created_at) < julianday(")
+                .push_bind(until)
+                .push(")");
+        }
+        if let Some(cursor) = params.cursor {
+            select.push(" AND id < ").push_bind(cursor);
+        }
+        select
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(params.limit() + 1);
+
+        let entries = select
+            .build_query_as::<AuditEntry>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+        Ok(AuditPage::from_rows(entries, params.limit()))
+    }
+}
+
+async fn select(
+    conn: &mut SqliteConnection,
+    owner: i32,
+    id: i32,
+) -> Result<Option<Task>, CustomError> {
+    let task =
+        sqlx::query_as("SELECT * FROM task WHERE id = ? AND owner_id = ? AND deleted_at IS NULL")
+            .bind(id)
+            .bind(owner)
+            .fetch_optional(conn)
+            .await?;
+    Ok(task)
+}
+
+/// Like [`select`], but for tasks in the trash too.
+async fn select_any(
+    conn: &mut SqliteConnection,
+    owner: i32,
+    id: i32,
+) -> Result<Option<Task>, CustomError> {
+    let task = sqlx::query_as("SELECT * FROM task WHERE id = ? AND owner_id = ?")
+        .bind(id)
+        .bind(owner)
+        .fetch_optional(conn)
+        .await?;
+    Ok(task)
+}
+
+/// The tasks `ids`, in id order.
+async fn select_all(conn: &mut SqliteConnection, ids: &[i32]) -> Result<Vec<Task>, CustomError> {
+    let tasks = sqlx::query_as(
+        "SELECT * FROM task WHERE id IN (SELECT value FROM json_each(?)) ORDER BY id",
+    )
+    .bind(Json(ids))
+    .fetch_all(conn)
+    .await?;
+    Ok(tasks)
+}
+
+/// The ids, in order, of the tasks matching `start`, which binds `id`, and
+/// of their subtasks of every level that `follow` lets through along with
+/// their own subtasks. `follow` is a condition on a `task` and its parent in
+/// the `subtree`.
+async fn subtree(
+    conn: &mut SqliteConnection,
+    start: &str,
+    id: i32,
+    follow: &str,
+) -> Result<Vec<i32>, CustomError> {
+    let ids = sqlx::query_scalar(&format!(
+        "WITH RECURSIVE subtree AS (\
+           SELECT id, deleted_at FROM task WHERE {start} \
+           UNION SELECT task.id, task.deleted_at FROM task \
+           JOIN subtree ON task.parent_id = subtree.id AND {follow}\
+         ) SELECT id FROM subtree ORDER BY id"
+    ))
+    .bind(id)
+    .fetch_all(conn)
+    .await?;
+    Ok(ids)
+}
+
+/// Checks that the project and the parent of a task, if any, belong to `owner`.
+async fn check_references(
+    conn: &mut SqliteConnection,
+    owner: i32,
+    project_id: Option<i32>,
+    parent_id: Option<i32>,
+) -> Result<(), CustomError> {
+    if let Some(project_id) = project_id {
+        let found: Option<i32> =
+            sqlx::query_scalar("SELECT id FROM project WHERE id = ? AND owner_id = ?")
+                .bind(project_id)
+                .bind(owner)
+                .fetch_optional(&mut *conn)
+                .await?;
+        if found.is_none() {
+            return Err(invalid_reference("project_id", "project"));
+        }
+    }
+    if let Some(parent_id) = parent_id {
+        if select(conn, owner, parent_id).await?.is_none() {
+            return Err(invalid_reference("parent_id", "task"));
+        }
+    }
+    Ok(())
+}
+
+/// Stores `task` as the new content of `current`.
+async fn update(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    current: &Task,
+    task: &UpdateTask,
+) -> Result<Task, CustomError> {
+    check_references(conn, actor.user_id, task.project_id, None).await?;
+    let updated = sqlx::query_as(
+        "UPDATE task SET task = ?, status = ?, priority = ?, due_at = ?, project_id = ?, \
+         recurrence = ?, updated_at = ?, version = version + 1 WHERE id = ? RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(task.status)
+    .bind(task.priority)
+    .bind(task.due_at)
+    .bind(task.project_id)
+    .bind(&task.recurrence)
+    .bind(Utc::now())
+    .bind(current.id)
+    .fetch_one(&mut *conn)
+    .await?;
+    record(
+        conn,
+        Some(actor),
+        AuditAction::Update,
+        Some(current),
+        Some(&updated),
+    )
+    .await?;
+    Ok(updated)
+}
+
+/// Moves a task to the trash, along with its subtasks.
+async fn delete(conn: &mut SqliteConnection, actor: &Actor, id: i32) -> Result<bool, CustomError> {
+    if select(conn, actor.user_id, id).await?.is_none() {
+        return Ok(false);
+    }
+    let ids = subtree(conn, "id = ?", id, "task.deleted_at IS NULL").await?;
+    let before = select_all(conn, &ids).await?;
+    // The same `deleted_at` tells which subtasks to restore along with the task.
+    let now = Utc::now();
+    let after: Vec<Task> = sqlx::query_as(
+        "UPDATE task SET deleted_at = ?, updated_at = ?, version = version + 1 \
+         WHERE id IN (SELECT value FROM json_each(?)) RETURNING *",
+    )
+    .bind(now)
+    .bind(now)
+    .bind(Json(&ids))
+    .fetch_all(&mut *conn)
+    .await?;
+    let after = in_order(after);
+    record_all(conn, Some(actor), AuditAction::Delete, &before, &after).await?;
+    Ok(true)
+}
+
+/// Deletes the tasks `ids` for good, recording it.
+async fn purge_all(
+    conn: &mut SqliteConnection,
+    actor: Option<&Actor>,
+    ids: &[i32],
+) -> Result<(), CustomError> {
+    // Not `RETURNING`, which misses the subtasks the foreign key deletes first.
+    let purged = select_all(conn, ids).await?;
+    sqlx::query("DELETE FROM task WHERE id IN (SELECT value FROM json_each(?))")
+        .bind(Json(ids))
+        .execute(&mut *conn)
+        .await?;
+    for task in &purged {
+        record(conn, actor, AuditAction::Purge, Some(task), None).await?;
+    }
+    Ok(())
+}
+
+/// Sorts tasks by id, as `RETURNING` does not promise any order.
+fn in_order(mut tasks: Vec<Task>) -> Vec<Task> {
+    tasks.sort_by_key(|task| task.id);
+    tasks
+}
+
+/// Records the changes of the same tasks, in id order before and after.
+async fn record_all(
+    conn: &mut SqliteConnection,
+    actor: Option<&Actor>,
+    action: AuditAction,
+    before: &[Task],
+    after: &[Task],
+) -> Result<(), CustomError> {
+    for (before, after) in before.iter().zip(after) {
+        record(conn, actor, action, Some(before), Some(after)).await?;
+    }
+    Ok(())
+}
+
+/// Applies a single operation of a bulk request.
+async fn apply(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    op: &Operation,
+) -> Result<Applied, CustomError> {
+    match op {
+        Operation::Create { task } => {
+            let task = insert(conn, actor, task, Utc::now()).await?;
+            Ok(Applied::Created(task))
+        }
+        Operation::Update { id, version, task } => {
+            let current = select(conn, actor.user_id, *id)
+                .await?
+                .ok_or_else(|| CustomError::not_found("task", id))?;
+            if version.is_some_and(|version| version != current.version) {
+                return Err(CustomError::PreconditionFailed);
+            }
+            let task = update(conn, actor, &current, task).await?;
+            Ok(Applied::Updated(task))
+        }
+        Operation::Delete { id } => {
+            if !delete(conn, actor, *id).await? {
+                return Err(CustomError::not_found("task", id));
+            }
+            Ok(Applied::Deleted)
+        }
+    }
+}
+
+/// Records a change in the audit log. `before` and `after` cannot both be absent.
+async fn record(
+    conn: &mut SqliteConnection,
+    actor: Option<&Actor>,
+    action: AuditAction,
+    before: Option<&Task>,
+    after: Option<&Task>,
+) -> Result<(), CustomError> {
+    let task = before
+        .or(after)
+        .expect("a change has a task before or after it");
+    sqlx::query(
+        "INSERT INTO audit_log \
+         (action, task_id, owner_id, actor_id, request_id, before, after, created_at) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(action)
+    .bind(task.id)
+    .bind(task.owner_id)
+    .bind(actor.map(|actor| actor.user_id))
+    .bind(actor.and_then(|actor| actor.request_id.as_deref()))
+    .bind(before.map(Json))
+    .bind(after.map(Json))
+    .bind(Utc::now())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn insert(
+    conn: &mut SqliteConnection,
+    actor: &Actor,
+    task: &NewTask,
+    now: DateTime<Utc>,
+) -> Result<Task, CustomError> {
+    check_references(conn, actor.user_id, task.project_id, task.parent_id).await?;
+    let created = sqlx::query_as(
+        "INSERT INTO task (task, status, priority, due_at, created_at, updated_at, owner_id, \
+         project_id, parent_id, recurrence) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(task.status)
+    .bind(task.priority)
+    .bind(task.due_at)
+    .bind(now)
+    .bind(now)
+    .bind(actor.user_id)
+    .bind(task.project_id)
+    .bind(task.parent_id)
+    .bind(&task.recurrence)
+    .fetch_one(&mut *conn)
+    .await?;
+    record(conn, Some(actor), AuditAction::Create, None, Some(&created)).await?;
+    Ok(created)
+}
+
+/// Creates the occurrence of the recurring `task` due at `due_at`, a copy of
+/// it with its tags that is still to do.
+async fn insert_occurrence(
+    conn: &mut SqliteConnection,
+    task: &Task,
+    due_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Task, CustomError> {
+    let created: Task = sqlx::query_as(
+        "INSERT INTO task (task, status, priority, due_at, created_at, updated_at, owner_id, \
+         project_id, parent_id, recurrence) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(&task.task)
+    .bind(Status::Todo)
+    .bind(task.priority)
+    .bind(due_at)
+    .bind(now)
+    .bind(now)
+    .bind(task.owner_id)
+    .bind(task.project_id)
+    .bind(task.parent_id)
+    .bind(&task.recurrence)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO task_tag (task_id, tag_id) SELECT ?, tag_id FROM task_tag WHERE task_id = ?",
+    )
+    .bind(created.id)
+    .bind(task.id)
+    .execute(&mut *conn)
+    .await?;
+    record(conn, None, AuditAction::Create, None, Some(&created)).await?;
+    Ok(created)
+}
+
+/// A `LIKE` pattern matching `term` anywhere, escaping its wildcards with `\`.
+fn like_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+fn push_filter(
+    query: &mut QueryBuilder<'_, Sqlite>,
+    owner: i32,
+    params: &ListParams,
+    scope: Scope,
+) {
+    query.push(" AND owner_id = ").push_bind(owner);
+    query.push(match scope {
+        Scope::Active => " AND deleted_at IS NULL",
+        Scope::Trash => " AND deleted_at IS NOT NULL",
+    });
+    if let Some(q) = params.q() {
+        query
+            .push(" AND instr(lower(task), lower(")
+            .push_bind(q.to_owned())
+            .push(")) > 0");
+    }
+    if let Some(project) = params.project {
+        query.push(" AND project_id = ").push_bind(project);
+    }
+    if let Some(parent) = params.parent {
+        query.push(" AND parent_id = ").push_bind(parent);
+    }
+    if let Some(tag) = &params.tag {
+        query
+            .push(" AND id IN (SELECT task_id FROM task_tag JOIN tag ON tag.id = task_tag.tag_id")
+            .push(" WHERE tag.owner_id = ")
+            .push_bind(owner)
+            .push(" AND tag.name = ")
+            .push_bind(tag.clone())
+            .push(")");
+    }
+}
+
+#[async_trait]
+impl RateLimitRepository for SqliteTaskRepository {
+    async fn take_token(
+        &mut self,
+        key: &str,
+        limit: Limit,
+        now: DateTime<Utc>,
+    ) -> Result<Decision, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        // Creates a full bucket for a new client, locking it either way.
+        let bucket: Bucket = sqlx::query_as(
+            "INSERT INTO rate_limit_bucket (key, tokens, updated_at) VALUES (?, ?, ?) \
+             ON CONFLICT (key) DO UPDATE SET key = excluded.key RETURNING tokens, updated_at",
+        )
+        .bind(key)
+        .bind(f64::from(limit.requests))
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        let (bucket, decision) = Bucket::take(Some(bucket), limit, now);
+        sqlx::query("UPDATE rate_limit_bucket SET tokens = ?, updated_at = ? WHERE key = ?")
+            .bind(bucket.tokens)
+            .bind(bucket.updated_at)
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(decision)
+    }
+
+    async fn prune_buckets(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result =
+            sqlx::query("DELETE FROM rate_limit_bucket WHERE julianday(updated_at) < julianday(?)")
+                .bind(before)
+                .execute(&mut *self.conn)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl IdempotencyRepository for SqliteTaskRepository {
+    async fn claim_idempotency_key(
+        &mut self,
+        owner: i32,
+        key: &str,
+        fingerprint: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<IdempotencyRecord>, CustomError> {
+        // Takes over keys that expired, or that a request gave up on.
+        let claimed = sqlx::query(
+            "INSERT INTO idempotency_key (owner_id, key, fingerprint, locked_until, expires_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5) \
+             ON CONFLICT (owner_id, key) DO UPDATE SET fingerprint = excluded.fingerprint, \
+               status = NULL, content_type = NULL, body = NULL, \
+               locked_until = excluded.locked_until, expires_at = excluded.expires_at \
+             WHERE julianday(idempotency_key.expires_at) <= julianday(?6) \
+               OR (idempotency_key.status IS NULL \
+                 AND julianday(idempotency_key.locked_until) <= julianday(?6)) \
+             RETURNING owner_id",
+        )
+        .bind(owner)
+        .bind(key)
+        .bind(fingerprint)
+        .bind(locked_until)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+        let record = sqlx::query_as(
+            "SELECT fingerprint, status, content_type, body FROM idempotency_key \
+             WHERE owner_id = ? AND key = ?",
+        )
+        .bind(owner)
+        .bind(key)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(Some(record))
+    }
+
+    async fn save_idempotent_response(
+        &mut self,
+        owner: i32,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<(), CustomError> {
+        sqlx::query(
+            "UPDATE idempotency_key SET status = ?, content_type = ?, body = ? \
+             WHERE owner_id = ? AND key = ?",
+        )
+        .bind(response.status as i16)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .bind(owner)
+        .bind(key)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&mut self, owner: i32, key: &str) -> Result<(), CustomError> {
+        sqlx::query(
+            "DELETE FROM idempotency_key WHERE owner_id = ? AND key = ? AND status IS NULL",
+        )
+        .bind(owner)
+        .bind(key)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_idempotency_keys(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result =
+            sqlx::query("DELETE FROM idempotency_key WHERE julianday(expires_at) < julianday(?)")
+                .bind(before)
+                .execute(&mut *self.conn)
+                .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for SqliteTaskRepository {
+    async fn list_webhooks(&mut self, owner: i32) -> Result<Vec<Webhook>, CustomError> {
+        let webhooks = sqlx::query_as("SELECT * FROM webhook WHERE owner_id = ? ORDER BY id")
+            .bind(owner)
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(webhooks)
+    }
+
+    async fn create_webhook(
+        &mut self,
+        owner: i32,
+        webhook: &NewWebhook,
+        secret: &str,
+    ) -> Result<Webhook, CustomError> {
+        let webhook = sqlx::query_as(
+            "INSERT INTO webhook (owner_id, url, events, secret) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(owner)
+        .bind(&webhook.url)
+        .bind(Json(webhook.events()))
+        .bind(secret)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        Ok(webhook)
+    }
+
+    async fn delete_webhook(&mut self, owner: i32, id: i32) -> Result<bool, CustomError> {
+        let result = sqlx::query("DELETE FROM webhook WHERE id = ? AND owner_id = ?")
+            .bind(id)
+            .bind(owner)
+            .execute(&mut *self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_deliveries(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        params: &DeliveryParams,
+    ) -> Result<Option<DeliveryPage>, CustomError> {
+        let found: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM webhook WHERE id = ? AND owner_id = ?)",
+        )
+        .bind(webhook_id)
+        .bind(owner)
+        .fetch_one(&mut *self.conn)
+        .await?;
+        if !found {
+            return Ok(None);
+        }
+
+        let mut select =
+            QueryBuilder::<Sqlite>::new("SELECT * FROM webhook_delivery WHERE webhook_id = ");
+        select.push_bind(webhook_id);
+        if let Some(status) = params.status {
+            select.push(" AND status = ").push_bind(status);
+        }
+        if let Some(cursor) = params.cursor {
+            select.push(" AND id < ").push_bind(cursor);
+        }
+        select
+            .push(" ORDER BY id DESC LIMIT ")
+            .push_bind(params.limit() + 1);
+
+        let rows = select
+            .build_query_as::<Delivery>()
+            .fetch_all(&mut *self.conn)
+            .await?;
+        Ok(Some(DeliveryPage::from_rows(rows, params.limit())))
+    }
+
+    async fn redeliver(
+        &mut self,
+        owner: i32,
+        webhook_id: i32,
+        id: i64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Delivery>, CustomError> {
+        let delivery = sqlx::query_as(
+            "UPDATE webhook_delivery SET status = 'pending', attempts = 0, next_attempt_at = ? \
+             WHERE id = ? AND webhook_id = ? \
+               AND webhook_id IN (SELECT id FROM webhook WHERE owner_id = ?) \
+             RETURNING *",
+        )
+        .bind(now)
+        .bind(id)
+        .bind(webhook_id)
+        .bind(owner)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+        if delivery.is_some() {
+            self.changed();
+        }
+        Ok(delivery)
+    }
+
+    async fn claim_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<DueDelivery>, CustomError> {
+        let mut tx = self.conn.begin().await?;
+        // Claimed in a single statement, as RETURNING cannot see the webhook.
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "UPDATE webhook_delivery SET next_attempt_at = ?1 WHERE id IN ( \
+               SELECT id FROM webhook_delivery \
+               WHERE status = 'pending' AND julianday(next_attempt_at) <= julianday(?2) \
+               ORDER BY julianday(next_attempt_at), id LIMIT ?3) \
+             RETURNING id",
+        )
+        .bind(lease_until)
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut select = QueryBuilder::<Sqlite>::new(
+            "SELECT webhook_delivery.id, url, secret, attempts, event_id, kind, task_id, task, \
+             webhook_delivery.created_at FROM webhook_delivery \
+             JOIN webhook ON webhook.id = webhook_delivery.webhook_id \
+             WHERE webhook_delivery.id IN (",
+        );
+        let mut separated = select.separated(", ");
+        for id in ids {
+            separated.push_bind(id);
+        }
+        select.push(") ORDER BY webhook_delivery.id");
+        let deliveries = select
+            .build_query_as::<DueDelivery>()
+            .fetch_all(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(deliveries)
+    }
+
+    async fn record_attempt(&mut self, id: i64, attempt: &Attempt) -> Result<(), CustomError> {
+        sqlx::query(
+            "UPDATE webhook_delivery SET status = ?1, attempts = attempts + 1, \
+               next_attempt_at = ?2, response_status = ?3, last_error = ?4, \
+               delivered_at = CASE WHEN ?1 = 'delivered' THEN ?5 END \
+             WHERE id = ?6",
+        )
+        .bind(attempt.status)
+        .bind(attempt.next_attempt_at)
+        .bind(attempt.response_status)
+        .bind(&attempt.error)
+        .bind(attempt.at)
+        .bind(id)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn prune_deliveries(&mut self, before: DateTime<Utc>) -> Result<u64, CustomError> {
+        let result = sqlx::query(
+            "DELETE FROM webhook_delivery WHERE status <> ? AND julianday(created_at) < julianday(?)",
+        )
+        .bind(DeliveryStatus::Pending)
+        .bind(before)
+        .execute(&mut *self.conn)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,32 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Query};
+use crate::models::audit;
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    operation_id = "get_audit",
+    tag = "audit",
+    params(audit::AuditParams),
+    responses(
+        (status = 200, description = "A page of the changes to the caller's tasks, newest first", body = AuditPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Query(params): Query<audit::AuditParams>,
+) -> Result<(StatusCode, Json<audit::AuditPage>), CustomError> {
+    params.validate()?;
+
+    let page = conn.audit_log(user.id, &params).await?;
+
+    Ok((StatusCode::OK, Json(page)))
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+
+use crate::auth::{verify_password, Keys};
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::user;
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    operation_id = "login",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "A new pair of tokens", body = TokenResponse),
+        (status = 401, description = "Wrong email or password", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn handler(
+    State(keys): State<Arc<Keys>>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(credentials): Json<user::Credentials>,
+) -> Result<(StatusCode, Json<user::TokenResponse>), CustomError> {
+    let user = conn.find_user_by_email(&credentials.email()).await?;
+
+    let hash = user.as_ref().map(|user| user.password_hash.clone());
+    let valid = verify_password(credentials.password, hash).await?;
+
+    // Same answer for an unknown email and a wrong password.
+    let user = user
+        .filter(|_| valid)
+        .ok_or_else(|| CustomError::Unauthorized("invalid email or password".to_owned()))?;
+
+    Ok((StatusCode::OK, Json(keys.issue(user.id)?)))
+}
@@ -0,0 +1,3 @@
+pub mod login;
+pub mod refresh;
+pub mod register;
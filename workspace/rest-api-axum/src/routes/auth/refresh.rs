@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+
+use crate::auth::{Keys, TokenType};
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::user;
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    operation_id = "refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "A new pair of tokens", body = TokenResponse),
+        (status = 401, description = "Invalid or expired refresh token", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn handler(
+    State(keys): State<Arc<Keys>>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(request): Json<user::RefreshRequest>,
+) -> Result<(StatusCode, Json<user::TokenResponse>), CustomError> {
+    let id = keys.verify(&request.refresh_token, TokenType::Refresh)?;
+
+    // Tokens outlive deleted accounts, so check this one still exists.
+    let user = conn
+        .get_user(id)
+        .await?
+        .ok_or_else(|| CustomError::Unauthorized("invalid token".to_owned()))?;
+
+    Ok((StatusCode::OK, Json(keys.issue(user.id)?)))
+}
@@ -0,0 +1,34 @@
+use axum::http::StatusCode;
+
+use crate::auth::hash_password;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::user;
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    operation_id = "register",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "The new account", body = User),
+        (status = 400, description = "Invalid email or too short password", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The email is already taken", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn handler(
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(credentials): Json<user::Credentials>,
+) -> Result<(StatusCode, Json<user::User>), CustomError> {
+    credentials.validate()?;
+
+    let password_hash = hash_password(credentials.password.clone()).await?;
+
+    let user = conn
+        .create_user(&credentials.email(), &password_hash)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
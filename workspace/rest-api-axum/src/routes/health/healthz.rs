@@ -0,0 +1,10 @@
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    operation_id = "healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is up", body = String, content_type = "text/plain")),
+)]
+pub async fn handler() -> &'static str {
+    "ok"
+}
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::header::{HeaderValue, CONTENT_TYPE};
+use axum::response::IntoResponse;
+
+use crate::metrics::Metrics;
+use crate::repository::Store;
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    operation_id = "metrics",
+    tag = "health",
+    responses((status = 200, description = "Every metric, in the text format of Prometheus", body = String, content_type = "text/plain; version=0.0.4")),
+)]
+pub async fn handler(
+    State(metrics): State<Arc<Metrics>>,
+    State(store): State<Arc<dyn Store>>,
+) -> impl IntoResponse {
+    let content_type = HeaderValue::from_static(prometheus::TEXT_FORMAT);
+
+    (
+        [(CONTENT_TYPE, content_type)],
+        metrics.render(store.pool_status()),
+    )
+}
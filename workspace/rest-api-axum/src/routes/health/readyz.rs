@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+
+use crate::errors::CustomError;
+use crate::repository::Store;
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    operation_id = "readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "The database answers", body = String, content_type = "text/plain"),
+        (status = 503, description = "The database is unavailable", body = Problem, content_type = "application/problem+json"),
+        (status = 504, description = "No connection of the pool was available in time", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub async fn handler(State(store): State<Arc<dyn Store>>) -> Result<&'static str, CustomError> {
+    store.ping().await?;
+
+    Ok("ok")
+}
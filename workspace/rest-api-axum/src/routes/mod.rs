@@ -1 +1,7 @@
+pub mod audit;
+pub mod auth;
+pub mod health;
+pub mod projects;
+pub mod tags;
 pub mod tasks;
+pub mod webhooks;
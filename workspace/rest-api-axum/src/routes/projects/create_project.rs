@@ -0,0 +1,33 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::project;
+
+#[utoipa::path(
+    post,
+    path = "/projects",
+    operation_id = "create_project",
+    tag = "projects",
+    request_body = NewProject,
+    responses(
+        (status = 201, description = "The created project", body = Project),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The caller already has a project of that name", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(project): Json<project::NewProject>,
+) -> Result<(StatusCode, Json<project::Project>), CustomError> {
+    project.validate()?;
+
+    let project = conn.create_project(user.id, &project).await?;
+
+    Ok((StatusCode::CREATED, Json(project)))
+}
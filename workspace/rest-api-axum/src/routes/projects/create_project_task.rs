@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::audit::Actor;
+use crate::models::task;
+
+#[utoipa::path(
+    post,
+    path = "/projects/{id}/tasks",
+    operation_id = "create_project_task",
+    tag = "projects",
+    params(("id" = i32, Path, description = "Id of the project")),
+    request_body(content = NewTask, description = "The task, whose `project_id` is ignored"),
+    responses(
+        (status = 201, description = "The created task, in the project", body = Task),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such project", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Json(mut task): Json<task::NewTask>,
+) -> Result<(StatusCode, Json<task::Task>), CustomError> {
+    task.validate()?;
+    if conn.get_project(actor.user_id, id).await?.is_none() {
+        return Err(CustomError::not_found("project", id));
+    }
+
+    task.project_id = Some(id);
+    let task = conn.create(&actor, &task).await?;
+
+    Ok((StatusCode::CREATED, Json(task)))
+}
@@ -0,0 +1,32 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::audit::Actor;
+
+#[utoipa::path(
+    delete,
+    path = "/projects/{id}",
+    operation_id = "delete_project",
+    tag = "projects",
+    params(("id" = i32, Path, description = "Id of the project")),
+    responses(
+        (status = 200, description = "The project was deleted, and its tasks and their subtasks with it, for good", body = Object, example = json!({"msg": "Project Deleted"})),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such project", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<Value>), CustomError> {
+    if !conn.delete_project(&actor, id).await? {
+        return Err(CustomError::not_found("project", id));
+    }
+
+    Ok((StatusCode::OK, Json(json!({"msg": "Project Deleted"}))))
+}
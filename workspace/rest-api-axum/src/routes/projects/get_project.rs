@@ -0,0 +1,33 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::project;
+
+#[utoipa::path(
+    get,
+    path = "/projects/{id}",
+    operation_id = "get_project",
+    tag = "projects",
+    params(("id" = i32, Path, description = "Id of the project")),
+    responses(
+        (status = 200, description = "The project", body = Project),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such project", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<project::Project>), CustomError> {
+    let project = conn
+        .get_project(user.id, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("project", id))?;
+
+    Ok((StatusCode::OK, Json(project)))
+}
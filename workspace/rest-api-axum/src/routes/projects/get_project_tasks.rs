@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path, Query};
+use crate::models::page::Page;
+use crate::models::task;
+
+#[utoipa::path(
+    get,
+    path = "/projects/{id}/tasks",
+    operation_id = "get_project_tasks",
+    tag = "projects",
+    params(("id" = i32, Path, description = "Id of the project"), task::ListParams),
+    responses(
+        (status = 200, description = "A page of the tasks of the project, as `GET /tasks?project={id}`", body = TaskPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such project", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Query(mut params): Query<task::ListParams>,
+) -> Result<(StatusCode, Json<Page<task::Task>>), CustomError> {
+    params.validate()?;
+    if conn.get_project(user.id, id).await?.is_none() {
+        return Err(CustomError::not_found("project", id));
+    }
+
+    params.project = Some(id);
+    let page = conn.list(user.id, &params, task::Scope::Active).await?;
+
+    Ok((StatusCode::OK, Json(page)))
+}
@@ -0,0 +1,27 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::project;
+
+#[utoipa::path(
+    get,
+    path = "/projects",
+    operation_id = "get_projects",
+    tag = "projects",
+    responses(
+        (status = 200, description = "The caller's projects, by name", body = [Project]),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<(StatusCode, Json<Vec<project::Project>>), CustomError> {
+    let projects = conn.list_projects(user.id).await?;
+
+    Ok((StatusCode::OK, Json(projects)))
+}
@@ -0,0 +1,7 @@
+pub mod create_project;
+pub mod create_project_task;
+pub mod delete_project;
+pub mod get_project;
+pub mod get_project_tasks;
+pub mod get_projects;
+pub mod update_project;
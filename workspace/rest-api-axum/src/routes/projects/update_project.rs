@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::project;
+
+#[utoipa::path(
+    put,
+    path = "/projects/{id}",
+    operation_id = "update_project",
+    tag = "projects",
+    params(("id" = i32, Path, description = "Id of the project")),
+    request_body = NewProject,
+    responses(
+        (status = 200, description = "The renamed project", body = Project),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such project", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The caller already has a project of that name", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Json(project): Json<project::NewProject>,
+) -> Result<(StatusCode, Json<project::Project>), CustomError> {
+    project.validate()?;
+
+    let project = conn
+        .update_project(user.id, id, &project)
+        .await?
+        .ok_or_else(|| CustomError::not_found("project", id))?;
+
+    Ok((StatusCode::OK, Json(project)))
+}
@@ -0,0 +1,33 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::tag;
+
+#[utoipa::path(
+    post,
+    path = "/tags",
+    operation_id = "create_tag",
+    tag = "tags",
+    request_body = NewTag,
+    responses(
+        (status = 201, description = "The created tag", body = Tag),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The caller already has a tag of that name", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(tag): Json<tag::NewTag>,
+) -> Result<(StatusCode, Json<tag::Tag>), CustomError> {
+    tag.validate()?;
+
+    let tag = conn.create_tag(user.id, &tag).await?;
+
+    Ok((StatusCode::CREATED, Json(tag)))
+}
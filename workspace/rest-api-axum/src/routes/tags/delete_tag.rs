@@ -0,0 +1,32 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+
+#[utoipa::path(
+    delete,
+    path = "/tags/{id}",
+    operation_id = "delete_tag",
+    tag = "tags",
+    params(("id" = i32, Path, description = "Id of the tag")),
+    responses(
+        (status = 200, description = "The tag was deleted and taken off its tasks", body = Object, example = json!({"msg": "Tag Deleted"})),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such tag", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<Value>), CustomError> {
+    if !conn.delete_tag(user.id, id).await? {
+        return Err(CustomError::not_found("tag", id));
+    }
+
+    Ok((StatusCode::OK, Json(json!({"msg": "Tag Deleted"}))))
+}
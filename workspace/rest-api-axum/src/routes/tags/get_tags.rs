@@ -0,0 +1,27 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::tag;
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    operation_id = "get_tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "The caller's tags, by name", body = [Tag]),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<(StatusCode, Json<Vec<tag::Tag>>), CustomError> {
+    let tags = conn.list_tags(user.id).await?;
+
+    Ok((StatusCode::OK, Json(tags)))
+}
@@ -0,0 +1,4 @@
+pub mod create_tag;
+pub mod delete_tag;
+pub mod get_tags;
+pub mod update_tag;
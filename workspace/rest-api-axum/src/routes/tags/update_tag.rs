@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::tag;
+
+#[utoipa::path(
+    put,
+    path = "/tags/{id}",
+    operation_id = "update_tag",
+    tag = "tags",
+    params(("id" = i32, Path, description = "Id of the tag")),
+    request_body = NewTag,
+    responses(
+        (status = 200, description = "The renamed tag", body = Tag),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such tag", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The caller already has a tag of that name", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Json(tag): Json<tag::NewTag>,
+) -> Result<(StatusCode, Json<tag::Tag>), CustomError> {
+    tag.validate()?;
+
+    let tag = conn
+        .update_tag(user.id, id, &tag)
+        .await?
+        .ok_or_else(|| CustomError::not_found("tag", id))?;
+
+    Ok((StatusCode::OK, Json(tag)))
+}
@@ -0,0 +1,107 @@
+use axum::http::StatusCode;
+
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::audit::Actor;
+use crate::models::bulk;
+
+#[utoipa::path(
+    post,
+    path = "/tasks/bulk",
+    operation_id = "bulk_tasks",
+    tag = "tasks",
+    request_body = BulkRequest,
+    responses(
+        (status = 200, description = "Every operation was applied", body = BulkResponse),
+        (status = 207, description = "Some operations failed, see `results`", body = BulkResponse),
+        (status = 400, description = "Too many operations", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(request): Json<bulk::BulkRequest>,
+) -> Result<(StatusCode, Json<bulk::BulkResponse>), CustomError> {
+    request.validate()?;
+
+    // Invalid operations never reach the database.
+    let errors: Vec<Option<CustomError>> = request
+        .operations
+        .iter()
+        .map(|op| op.validate().err())
+        .collect();
+
+    let (committed, results): (bool, Vec<bulk::BulkResult>) = match request.mode {
+        bulk::BulkMode::Atomic if errors.iter().any(Option::is_some) => {
+            (false, not_applied(errors))
+        }
+        bulk::BulkMode::Atomic => {
+            let count = request.operations.len();
+            let mut outcomes = conn.bulk(&actor, &request.operations, request.mode).await?;
+            match outcomes.pop() {
+                Some(Err(error)) => {
+                    let mut errors: Vec<Option<CustomError>> = (0..count).map(|_| None).collect();
+                    errors[outcomes.len()] = Some(error);
+                    (false, not_applied(errors))
+                }
+                last => {
+                    outcomes.extend(last);
+                    (
+                        true,
+                        outcomes.into_iter().map(bulk::BulkResult::from).collect(),
+                    )
+                }
+            }
+        }
+        bulk::BulkMode::BestEffort => {
+            let valid: Vec<bulk::Operation> = request
+                .operations
+                .into_iter()
+                .zip(&errors)
+                .filter(|(_, error)| error.is_none())
+                .map(|(op, _)| op)
+                .collect();
+            let mut outcomes = conn.bulk(&actor, &valid, request.mode).await?.into_iter();
+            let results = errors
+                .into_iter()
+                .map(|error| match error {
+                    Some(error) => bulk::BulkResult::from(Err(error)),
+                    None => bulk::BulkResult::from(
+                        outcomes
+                            .next()
+                            .expect("one outcome per valid operation in best-effort mode"),
+                    ),
+                })
+                .collect();
+            (true, results)
+        }
+    };
+
+    let status = if committed && results.iter().all(|result| result.error.is_none()) {
+        StatusCode::OK
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+
+    Ok((status, Json(bulk::BulkResponse { committed, results })))
+}
+
+/// The results of an atomic batch that failed: the failed operations report
+/// their error, and all the others that they were not applied either.
+fn not_applied(errors: Vec<Option<CustomError>>) -> Vec<bulk::BulkResult> {
+    let failed = errors.iter().position(Option::is_some).unwrap_or_default();
+    errors
+        .into_iter()
+        .map(|error| {
+            let error = error.unwrap_or_else(|| {
+                CustomError::FailedDependency(format!(
+                    "not applied because operations[{failed}] failed"
+                ))
+            });
+            bulk::BulkResult::from(Err(error))
+        })
+        .collect()
+}
@@ -0,0 +1,41 @@
+use axum::http::StatusCode;
+
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::audit::Actor;
+use crate::models::task;
+
+#[utoipa::path(
+    post,
+    path = "/task/{id}/subtasks",
+    operation_id = "create_subtask",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the parent task")),
+    request_body(content = NewTask, description = "The subtask, whose `parent_id` is ignored. Without a `project_id`, it goes to the project of its parent."),
+    responses(
+        (status = 201, description = "The created subtask", body = Task),
+        (status = 400, description = "Invalid fields", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Json(mut task): Json<task::NewTask>,
+) -> Result<(StatusCode, Json<task::Task>), CustomError> {
+    task.validate()?;
+    let parent = conn
+        .get(actor.user_id, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("task", id))?;
+
+    task.parent_id = Some(id);
+    task.project_id = task.project_id.or(parent.project_id);
+    let task = conn.create(&actor, &task).await?;
+
+    Ok((StatusCode::CREATED, Json(task)))
+}
@@ -1,25 +1,38 @@
 use axum::http::StatusCode;
-use axum::Json;
 
 use crate::db::DatabaseConnection;
 use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::audit::Actor;
 use crate::models::task;
 
+#[utoipa::path(
+    post,
+    path = "/task",
+    operation_id = "create_task",
+    tag = "tasks",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Makes retries safe: a retry with the same key gets the response to the first request instead of creating the task again"),
+    ),
+    request_body = NewTask,
+    responses(
+        (status = 201, description = "The created task", body = Task, headers(("Idempotent-Replayed" = String, description = "`true` when this is the stored response to an earlier request with the same `Idempotency-Key`"))),
+        (status = 400, description = "Invalid fields, no such project or parent task, or an invalid `Idempotency-Key`", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A request with the same `Idempotency-Key` is still in progress", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "A body of more than 2 MB", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "The `Idempotency-Key` was already used for another request", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
 pub async fn handler(
+    actor: Actor,
     DatabaseConnection(mut conn): DatabaseConnection,
     Json(task): Json<task::NewTask>,
-) -> Result<(StatusCode, Json<task::NewTask>), CustomError> {
-    if task.task.is_empty() {
-        return Err(CustomError::BadRequest);
-    }
+) -> Result<(StatusCode, Json<task::Task>), CustomError> {
+    task.validate()?;
 
-    let sql = "INSERT INTO task (task) values ($1)";
-
-    let _ = sqlx::query(&sql)
-        .bind(&task.task)
-        .execute(&mut *conn)
-        .await
-        .map_err(|_| CustomError::InternalServerError)?;
+    let task = conn.create(&actor, &task).await?;
 
     Ok((StatusCode::CREATED, Json(task)))
 }
@@ -1,29 +1,40 @@
 use crate::errors::CustomError;
-use axum::extract::Path;
 use axum::http::StatusCode;
 use serde_json::json;
 use serde_json::Value;
 
 use crate::db::DatabaseConnection;
-use axum::Json;
-
+use crate::extract::{Json, Path, Query};
+use crate::models::audit::Actor;
 use crate::models::task;
 
+#[utoipa::path(
+    delete,
+    path = "/task/{id}",
+    operation_id = "delete_task",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the task"), task::DeleteParams),
+    responses(
+        (status = 200, description = "The task was moved to the trash or, with `purge`, deleted for good, along with its subtasks", body = Object, example = json!({"msg": "Task Deleted"})),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task, or only in the trash without `purge`", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
 pub async fn handler(
+    actor: Actor,
     DatabaseConnection(mut conn): DatabaseConnection,
     Path(id): Path<i32>,
+    Query(params): Query<task::DeleteParams>,
 ) -> Result<(StatusCode, Json<Value>), CustomError> {
-    let _find: task::Task = sqlx::query_as("SELECT * FROM task where id=$1")
-        .bind(id)
-        .fetch_one(&mut *conn)
-        .await
-        .map_err(|_| CustomError::TaskNotFound)?;
-
-    sqlx::query("DELETE FROM task WHERE id=$1")
-        .bind(id)
-        .execute(&mut *conn)
-        .await
-        .map_err(|_| CustomError::TaskNotFound)?;
+    let deleted = if params.purge {
+        conn.purge(&actor, id).await?
+    } else {
+        conn.delete(&actor, id).await?
+    };
+    if !deleted {
+        return Err(CustomError::not_found("task", id));
+    }
 
     Ok((StatusCode::OK, Json(json!({"msg": "Task Deleted"}))))
 }
@@ -0,0 +1,44 @@
+use axum::http::header::{HeaderMap, HeaderValue, IF_MATCH};
+
+use crate::errors::CustomError;
+use crate::models::task;
+use crate::repository::Repository;
+
+/// The strong `ETag` of a task, derived from its row version.
+pub fn etag(task: &task::Task) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", task.version))
+        .expect("a quoted number is a valid header")
+}
+
+/// Checks the `If-Match` request header, if any, against the current state of `task`.
+pub fn check_if_match(headers: &HeaderMap, task: &task::Task) -> Result<(), CustomError> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(());
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| CustomError::BadRequest("invalid If-Match header".to_owned()))?;
+    let current = etag(task);
+
+    // `If-Match` uses the strong comparison, so weak validators never match.
+    let matches = value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.as_bytes() == current.as_bytes());
+
+    if matches {
+        Ok(())
+    } else {
+        Err(CustomError::PreconditionFailed)
+    }
+}
+
+/// Why an update of task `id` at the version read beforehand matched no row:
+/// the task was deleted or moved to the trash in between, or it changed.
+pub async fn lost_update(conn: &mut dyn Repository, owner: i32, id: i32) -> CustomError {
+    match conn.get(owner, id).await {
+        Ok(Some(_)) => CustomError::PreconditionFailed,
+        Ok(None) => CustomError::not_found("task", id),
+        Err(err) => err,
+    }
+}
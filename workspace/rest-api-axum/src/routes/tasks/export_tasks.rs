@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::body::StreamBody;
+use axum::extract::State;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::IntoResponse;
+use futures_util::stream::{self, StreamExt};
+
+use crate::auth::CurrentUser;
+use crate::db;
+use crate::errors::CustomError;
+use crate::extract::Query;
+use crate::metrics::Metrics;
+use crate::models::task::MAX_PAGE_SIZE;
+use crate::models::transfer;
+use crate::repository::Store;
+
+#[utoipa::path(
+    get,
+    path = "/tasks/export",
+    operation_id = "export_tasks",
+    tag = "tasks",
+    params(transfer::ExportParams),
+    responses(
+        (status = 200, description = "Every task of the caller but those in the trash, by id, as an attachment. CSV exports start with a header row", body = [Task], content_type = ["application/json", "application/x-ndjson", "text/csv"]),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    State(store): State<Arc<dyn Store>>,
+    State(metrics): State<Arc<Metrics>>,
+    Query(params): Query<transfer::ExportParams>,
+) -> impl IntoResponse {
+    let format = params.format;
+
+    // A batch at a time, so that the whole table is never held in memory, and
+    // on a connection of its own, so that a slow client does not keep one
+    // from the pool for the whole download. Errors can only cut the response
+    // short.
+    let batches = stream::unfold((Some(0), true), move |(after, first)| {
+        let store = store.clone();
+        let metrics = metrics.clone();
+        async move {
+            let after = after?;
+            let batch = async {
+                let mut conn =
+                    db::acquire(&*store, metrics, "GET /tasks/export".to_owned()).await?;
+                conn.export(user.id, after, MAX_PAGE_SIZE).await
+            };
+            match batch.await {
+                Ok(tasks) if tasks.is_empty() => None,
+                Ok(tasks) => {
+                    let next =
+                        (tasks.len() as i64 == MAX_PAGE_SIZE).then(|| tasks[tasks.len() - 1].id);
+                    let rows = format.rows(&tasks, first);
+                    Some((Ok(rows), (next, false)))
+                }
+                Err(err) => {
+                    tracing::error!("task export interrupted: {err}");
+                    Some((Err(err), (None, false)))
+                }
+            }
+        }
+    });
+    let body = stream::once(async move { Ok::<_, CustomError>(format.header()) })
+        .chain(batches)
+        .chain(stream::once(async move { Ok(format.footer()) }));
+
+    let disposition = format!("attachment; filename=\"tasks.{}\"", format.extension());
+    (
+        [
+            (CONTENT_TYPE, format.content_type().to_owned()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        StreamBody::new(body),
+    )
+}
@@ -1,22 +1,38 @@
-use axum::extract::Path;
-use axum::http::StatusCode;
-use axum::Json;
+use axum::http::header::ETAG;
+use axum::http::{HeaderMap, StatusCode};
 
+use super::etag::etag;
+use crate::auth::CurrentUser;
 use crate::db::DatabaseConnection;
 use crate::errors::CustomError;
+use crate::extract::{Json, Path};
 use crate::models::task;
 
+#[utoipa::path(
+    get,
+    path = "/task/{id}",
+    operation_id = "get_task",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the task")),
+    responses(
+        (status = 200, description = "The task", body = Task, headers(("ETag" = String, description = "The version of the task, for `If-Match`"))),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
 pub async fn handler(
+    user: CurrentUser,
     DatabaseConnection(mut conn): DatabaseConnection,
     Path(id): Path<i32>,
-) -> Result<(StatusCode, Json<task::Task>), CustomError> {
-    let sql = "SELECT * FROM task where id=$1".to_string();
+) -> Result<(StatusCode, HeaderMap, Json<task::Task>), CustomError> {
+    let task = conn
+        .get(user.id, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("task", id))?;
 
-    let task: task::Task = sqlx::query_as(&sql)
-        .bind(id)
-        .fetch_one(&mut *conn)
-        .await
-        .map_err(|_| CustomError::TaskNotFound)?;
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag(&task));
 
-    Ok((StatusCode::OK, Json(task)))
+    Ok((StatusCode::OK, headers, Json(task)))
 }
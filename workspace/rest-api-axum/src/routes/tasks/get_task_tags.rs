@@ -0,0 +1,34 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::tag;
+
+#[utoipa::path(
+    get,
+    path = "/task/{id}/tags",
+    operation_id = "get_task_tags",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the task")),
+    responses(
+        (status = 200, description = "The tags of the task, by name", body = [Tag]),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<Vec<tag::Tag>>), CustomError> {
+    if conn.get(user.id, id).await?.is_none() {
+        return Err(CustomError::not_found("task", id));
+    }
+
+    let tags = conn.task_tags(user.id, id).await?;
+
+    Ok((StatusCode::OK, Json(tags)))
+}
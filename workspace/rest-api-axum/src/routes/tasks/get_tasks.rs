@@ -1,19 +1,33 @@
 use axum::http::StatusCode;
-use axum::Json;
 
+use crate::auth::CurrentUser;
 use crate::db::DatabaseConnection;
 use crate::errors::CustomError;
+use crate::extract::{Json, Query};
+use crate::models::page::Page;
 use crate::models::task;
 
+#[utoipa::path(
+    get,
+    path = "/tasks",
+    operation_id = "get_tasks",
+    tag = "tasks",
+    params(task::ListParams),
+    responses(
+        (status = 200, description = "A page of the caller's tasks", body = TaskPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
 pub async fn handler(
+    user: CurrentUser,
     DatabaseConnection(mut conn): DatabaseConnection,
-) -> Result<(StatusCode, Json<Vec<task::Task>>), CustomError> {
-    let sql = "SELECT * FROM task ".to_string();
+    Query(params): Query<task::ListParams>,
+) -> Result<(StatusCode, Json<Page<task::Task>>), CustomError> {
+    params.validate()?;
 
-    let tasks = sqlx::query_as::<_, task::Task>(&sql)
-        .fetch_all(&mut *conn)
-        .await
-        .map_err(|_| CustomError::InternalServerError)?;
+    let page = conn.list(user.id, &params, task::Scope::Active).await?;
 
-    Ok((StatusCode::OK, Json(tasks)))
+    Ok((StatusCode::OK, Json(page)))
 }
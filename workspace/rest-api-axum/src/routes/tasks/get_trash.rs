@@ -0,0 +1,33 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Query};
+use crate::models::page::Page;
+use crate::models::task;
+
+#[utoipa::path(
+    get,
+    path = "/tasks/trash",
+    operation_id = "get_trash",
+    tag = "tasks",
+    params(task::ListParams),
+    responses(
+        (status = 200, description = "A page of the caller's tasks in the trash, which are purged once the retention period is over", body = TaskPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Query(params): Query<task::ListParams>,
+) -> Result<(StatusCode, Json<Page<task::Task>>), CustomError> {
+    params.validate()?;
+
+    let page = conn.list(user.id, &params, task::Scope::Trash).await?;
+
+    Ok((StatusCode::OK, Json(page)))
+}
@@ -0,0 +1,90 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::audit::Actor;
+use crate::models::bulk::{BulkMode, Operation, MAX_BULK_OPERATIONS};
+use crate::models::transfer;
+
+#[utoipa::path(
+    post,
+    path = "/tasks/import",
+    operation_id = "import_tasks",
+    tag = "tasks",
+    request_body(
+        description = "Tasks in any format of `GET /tasks/export`, told by the `Content-Type`: `application/json`, `application/x-ndjson` or `text/csv`. Only the fields of a new task are read: the others are set anew, and unknown ones are ignored",
+        content = [NewTask],
+    ),
+    responses(
+        (status = 200, description = "Every row was imported", body = ImportReport),
+        (status = 207, description = "Some rows were rejected, see `rejected`; the others were imported", body = ImportReport),
+        (status = 400, description = "The upload could not be split into rows", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "Not CSV, NDJSON or JSON", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<transfer::ImportReport>), CustomError> {
+    let format = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(transfer::Format::from_content_type)
+        .ok_or_else(|| {
+            CustomError::UnsupportedMediaType(
+                "text/csv, application/x-ndjson or application/json".to_owned(),
+            )
+        })?;
+
+    // Rows that are invalid never reach the database, like in `POST /task`.
+    let mut rejected = Vec::new();
+    let mut lines = Vec::new();
+    let mut operations = Vec::new();
+    for row in format.parse(&body)? {
+        match row.task {
+            Ok(task) => {
+                lines.push(row.line);
+                operations.push(Operation::Create { task });
+            }
+            Err(err) => rejected.push((row.line, err)),
+        }
+    }
+
+    // Each row on its own, like the operations of a best-effort bulk request,
+    // which also records them in the audit log and notifies the event feeds.
+    let mut imported = 0;
+    for (lines, operations) in lines
+        .chunks(MAX_BULK_OPERATIONS)
+        .zip(operations.chunks(MAX_BULK_OPERATIONS))
+    {
+        let outcomes = conn.bulk(&actor, operations, BulkMode::BestEffort).await?;
+        for (line, outcome) in lines.iter().zip(outcomes) {
+            match outcome {
+                Ok(_) => imported += 1,
+                Err(err) => rejected.push((*line, err)),
+            }
+        }
+    }
+
+    rejected.sort_by_key(|(line, _)| *line);
+    let status = if rejected.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+    let rejected = rejected
+        .into_iter()
+        .map(|(line, err)| transfer::RejectedRow {
+            line,
+            error: err.into_problem(),
+        })
+        .collect();
+
+    Ok((status, Json(transfer::ImportReport { imported, rejected })))
+}
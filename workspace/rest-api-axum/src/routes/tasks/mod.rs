@@ -1,5 +1,21 @@
+pub mod bulk_tasks;
+pub mod create_subtask;
 pub mod create_task;
 pub mod delete_task;
+mod etag;
+pub mod export_tasks;
+pub mod get_subtasks;
 pub mod get_task;
+pub mod get_task_tags;
 pub mod get_tasks;
+pub mod get_trash;
+pub mod import_tasks;
+pub mod patch_task;
+pub mod restore_task;
+pub mod search_tasks;
+pub mod tag_task;
+pub mod task_events;
+pub mod task_history;
+pub mod task_socket;
+pub mod untag_task;
 pub mod update_task;
@@ -0,0 +1,73 @@
+use axum::body::Bytes;
+use axum::http::header::{CONTENT_TYPE, ETAG};
+use axum::http::{HeaderMap, StatusCode};
+
+use super::etag::{check_if_match, etag, lost_update};
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::audit::Actor;
+use crate::models::task;
+
+/// Media types accepted for the patch document.
+const PATCH_CONTENT_TYPES: [&str; 2] = ["application/merge-patch+json", "application/json"];
+
+#[utoipa::path(
+    patch,
+    path = "/task/{id}",
+    operation_id = "patch_task",
+    tag = "tasks",
+    params(
+        ("id" = i32, Path, description = "Id of the task"),
+        ("If-Match" = Option<String>, Header, description = "Only update the task if its `ETag` is one of these"),
+    ),
+    request_body(content = TaskPatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "The updated task", body = Task, headers(("ETag" = String, description = "The version of the task, for `If-Match`"))),
+        (status = 400, description = "Malformed patch, invalid fields or no such project", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "The task changed since `If-Match`, or concurrently", body = Problem, content_type = "application/problem+json"),
+        (status = 415, description = "The body is not a merge patch", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, HeaderMap, Json<task::Task>), CustomError> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    if !content_type.is_some_and(|ct| PATCH_CONTENT_TYPES.contains(&ct)) {
+        return Err(CustomError::UnsupportedMediaType(
+            PATCH_CONTENT_TYPES[0].to_owned(),
+        ));
+    }
+
+    let patch: task::TaskPatch =
+        serde_json::from_slice(&body).map_err(|err| CustomError::BadRequest(err.to_string()))?;
+
+    let find = conn
+        .get(actor.user_id, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("task", id))?;
+
+    check_if_match(&headers, &find)?;
+
+    let update = patch.apply(&find)?;
+
+    let Some(task) = conn.update(&actor, id, find.version, &update).await? else {
+        return Err(lost_update(&mut *conn, actor.user_id, id).await);
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag(&task));
+
+    Ok((StatusCode::OK, headers, Json(task)))
+}
@@ -0,0 +1,39 @@
+use axum::http::header::ETAG;
+use axum::http::{HeaderMap, StatusCode};
+
+use super::etag::etag;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::audit::Actor;
+use crate::models::task;
+
+#[utoipa::path(
+    post,
+    path = "/task/{id}/restore",
+    operation_id = "restore_task",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the task")),
+    responses(
+        (status = 200, description = "The task, out of the trash along with the subtasks trashed with it", body = Task, headers(("ETag" = String, description = "The version of the task, for `If-Match`"))),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task in the trash", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The parent of the task is in the trash", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    actor: Actor,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, HeaderMap, Json<task::Task>), CustomError> {
+    let task = conn
+        .restore(&actor, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("task", id))?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag(&task));
+
+    Ok((StatusCode::OK, headers, Json(task)))
+}
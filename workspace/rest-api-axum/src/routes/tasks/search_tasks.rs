@@ -0,0 +1,32 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Query};
+use crate::models::search::{SearchParams, SearchResults};
+
+#[utoipa::path(
+    get,
+    path = "/tasks/search",
+    operation_id = "search_tasks",
+    tag = "tasks",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "The caller's tasks containing the words of `q`, the most relevant first", body = SearchResults),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Query(params): Query<SearchParams>,
+) -> Result<(StatusCode, Json<SearchResults>), CustomError> {
+    params.validate()?;
+
+    let results = conn.search(user.id, &params).await?;
+
+    Ok((StatusCode::OK, Json(SearchResults { results })))
+}
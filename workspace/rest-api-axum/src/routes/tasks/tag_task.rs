@@ -0,0 +1,40 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::tag;
+
+#[utoipa::path(
+    put,
+    path = "/task/{id}/tags/{tag_id}",
+    operation_id = "tag_task",
+    tag = "tasks",
+    params(
+        ("id" = i32, Path, description = "Id of the task"),
+        ("tag_id" = i32, Path, description = "Id of the tag"),
+    ),
+    responses(
+        (status = 200, description = "The tags of the task, now with the tag", body = [Tag]),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task or tag", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path((id, tag_id)): Path<(i32, i32)>,
+) -> Result<(StatusCode, Json<Vec<tag::Tag>>), CustomError> {
+    if !conn.add_task_tag(user.id, id, tag_id).await? {
+        return Err(match conn.get(user.id, id).await? {
+            Some(_) => CustomError::not_found("tag", tag_id),
+            None => CustomError::not_found("task", id),
+        });
+    }
+
+    let tags = conn.task_tags(user.id, id).await?;
+
+    Ok((StatusCode::OK, Json(tags)))
+}
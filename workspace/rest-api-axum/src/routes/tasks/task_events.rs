@@ -0,0 +1,71 @@
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream};
+
+use crate::auth::FeedUser;
+use crate::errors::CustomError;
+use crate::events::Hub;
+use crate::extract::Query;
+use crate::models::event;
+
+#[utoipa::path(
+    get,
+    path = "/tasks/events",
+    operation_id = "task_events",
+    tag = "tasks",
+    params(
+        event::FeedParams,
+        ("Last-Event-ID" = Option<i64>, Header, description = "The id of the last event received, sent by `EventSource` when it reconnects"),
+    ),
+    responses(
+        (status = 200, description = "Server-sent events for every change to the caller's tasks, named after their `kind`, with the id of the event and the event itself as JSON data", body = TaskEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid `Last-Event-ID`", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: FeedUser,
+    State(hub): State<Hub>,
+    headers: HeaderMap,
+    Query(params): Query<event::FeedParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, CustomError> {
+    let after = resume_after(&headers, &params)?;
+    let subscription = hub.subscribe(user.id, after).await?;
+
+    // Ends on errors, after which `EventSource` reconnects with the last id it got.
+    let events = stream::unfold(subscription, |mut subscription| async move {
+        let event = subscription
+            .next()
+            .await
+            .map_err(|err| tracing::warn!("task event feed interrupted: {err}"))
+            .ok()?;
+        let sse = Event::default()
+            .id(event.id.to_string())
+            .event(event.kind.as_str())
+            .json_data(&event)
+            .ok()?;
+        Some((Ok(sse), subscription))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// The event to resume after, from the `Last-Event-ID` header or else the query.
+pub(super) fn resume_after(
+    headers: &HeaderMap,
+    params: &event::FeedParams,
+) -> Result<Option<i64>, CustomError> {
+    let Some(value) = headers.get("last-event-id") else {
+        return Ok(params.last_event_id);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .map(Some)
+        .ok_or_else(|| CustomError::BadRequest("invalid Last-Event-ID header".to_owned()))
+}
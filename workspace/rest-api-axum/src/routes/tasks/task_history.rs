@@ -0,0 +1,34 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path, Query};
+use crate::models::audit;
+
+#[utoipa::path(
+    get,
+    path = "/task/{id}/history",
+    operation_id = "task_history",
+    tag = "tasks",
+    params(("id" = i32, Path, description = "Id of the task"), audit::HistoryParams),
+    responses(
+        (status = 200, description = "A page of the changes to the task, newest first, kept after the task is purged. Empty for tasks of other users.", body = AuditPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Query(params): Query<audit::HistoryParams>,
+) -> Result<(StatusCode, Json<audit::AuditPage>), CustomError> {
+    let params = params.of_task(id);
+    params.validate()?;
+
+    let page = conn.audit_log(user.id, &params).await?;
+
+    Ok((StatusCode::OK, Json(page)))
+}
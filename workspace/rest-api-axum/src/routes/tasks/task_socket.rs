@@ -0,0 +1,74 @@
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::Response;
+
+use super::task_events::resume_after;
+use crate::auth::FeedUser;
+use crate::errors::CustomError;
+use crate::events::{Hub, Subscription};
+use crate::extract::Query;
+use crate::models::event;
+
+#[utoipa::path(
+    get,
+    path = "/tasks/ws",
+    operation_id = "task_socket",
+    tag = "tasks",
+    params(
+        event::FeedParams,
+        ("Last-Event-ID" = Option<i64>, Header, description = "The id of the last event received"),
+    ),
+    responses(
+        (status = 101, description = "A WebSocket sending every change to the caller's tasks as a JSON `TaskEvent` text message. Messages from the client are ignored.", body = TaskEvent),
+        (status = 400, description = "Invalid `Last-Event-ID`", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: FeedUser,
+    State(hub): State<Hub>,
+    headers: HeaderMap,
+    Query(params): Query<event::FeedParams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, CustomError> {
+    let after = resume_after(&headers, &params)?;
+    let subscription = hub.subscribe(user.id, after).await?;
+
+    Ok(upgrade.on_upgrade(move |socket| forward(socket, subscription)))
+}
+
+/// Sends the events of `subscription` until either side goes away.
+async fn forward(mut socket: WebSocket, mut subscription: Subscription) {
+    loop {
+        tokio::select! {
+            event = subscription.next() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => {
+                        tracing::warn!("task event feed interrupted: {err}");
+                        // Clients should reconnect with the id of the last event they got.
+                        let close = CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "event feed interrupted".into(),
+                        };
+                        let _ = socket.send(Message::Close(Some(close))).await;
+                        return;
+                    }
+                };
+                let Ok(text) = serde_json::to_string(&event) else {
+                    return;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            // Also answers pings, as a side effect of reading.
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -1,31 +1,71 @@
-use axum::extract::Path;
+use std::sync::Arc;
+
 use axum::extract::State;
-use axum::http::StatusCode;
-use axum::Json;
-use sqlx::PgPool;
+use axum::http::header::ETAG;
+use axum::http::{HeaderMap, StatusCode};
+use tracing::Instrument;
 
+use super::etag::{check_if_match, etag, lost_update};
 use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::metrics::Metrics;
+use crate::models::audit::Actor;
 use crate::models::task;
+use crate::repository::instrumented::Instrumented;
+use crate::repository::{Store, TaskRepository};
 
+#[utoipa::path(
+    put,
+    path = "/task/{id}",
+    operation_id = "update_task",
+    tag = "tasks",
+    params(
+        ("id" = i32, Path, description = "Id of the task"),
+        ("If-Match" = Option<String>, Header, description = "Only update the task if its `ETag` is one of these"),
+    ),
+    request_body = UpdateTask,
+    responses(
+        (status = 200, description = "The updated task", body = Task, headers(("ETag" = String, description = "The version of the task, for `If-Match`"))),
+        (status = 400, description = "Invalid fields, or no such project", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such task", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "The task changed since `If-Match`, or concurrently", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
 pub async fn handler(
+    actor: Actor,
     // Example using State instead of our custom pool manager like in the other routes
-    State(pool): State<PgPool>,
+    State(store): State<Arc<dyn Store>>,
+    State(metrics): State<Arc<Metrics>>,
     Path(id): Path<i32>,
+    headers: HeaderMap,
     Json(task): Json<task::UpdateTask>,
-) -> Result<(StatusCode, Json<task::UpdateTask>), CustomError> {
-    let sql = "SELECT * FROM task where id=$1".to_string();
-
-    let _find: task::Task = sqlx::query_as(&sql)
-        .bind(id)
-        .fetch_one(&pool)
-        .await
-        .map_err(|_| CustomError::TaskNotFound)?;
-
-    let _ = sqlx::query("UPDATE task SET task=$1 WHERE id=$2")
-        .bind(&task.task)
-        .bind(id)
-        .execute(&pool)
-        .await;
-
-    Ok((StatusCode::OK, Json(task)))
+) -> Result<(StatusCode, HeaderMap, Json<task::Task>), CustomError> {
+    task.validate()?;
+
+    // Which is also why the connection and queries need tracing and timing by hand.
+    let conn = store
+        .acquire()
+        .instrument(tracing::debug_span!("acquire_connection"))
+        .await?;
+    let mut conn = Instrumented::new(conn, "PUT /task/:id".to_owned(), metrics);
+
+    let find = conn
+        .get(actor.user_id, id)
+        .await?
+        .ok_or_else(|| CustomError::not_found("task", id))?;
+
+    check_if_match(&headers, &find)?;
+
+    // Matching on the version read above turns a concurrent write into a 412
+    // instead of silently overwriting it, and a concurrent delete into a 404.
+    let Some(task) = conn.update(&actor, id, find.version, &task).await? else {
+        return Err(lost_update(&mut conn, actor.user_id, id).await);
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, etag(&task));
+
+    Ok((StatusCode::OK, headers, Json(task)))
 }
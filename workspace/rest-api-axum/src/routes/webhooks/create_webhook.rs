@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::config::Config;
+use crate::db::DatabaseConnection;
+use crate::errors::{CustomError, FieldError};
+use crate::extract::Json;
+use crate::models::webhook;
+use crate::webhooks::{check_url, generate_secret};
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    operation_id = "create_webhook",
+    tag = "webhooks",
+    request_body = NewWebhook,
+    responses(
+        (status = 201, description = "The created webhook, which is sent the events of the caller's tasks from now on, with the secret signing them", body = CreatedWebhook),
+        (status = 400, description = "Invalid fields, or a URL on a loopback, private or link-local address", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    State(config): State<Arc<Config>>,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Json(webhook): Json<webhook::NewWebhook>,
+) -> Result<(StatusCode, Json<webhook::CreatedWebhook>), CustomError> {
+    webhook.validate()?;
+    check_url(&webhook.url, &config.webhook_allowed_hosts)
+        .await
+        .map_err(|err| CustomError::Validation(vec![FieldError::new("url", err)]))?;
+
+    let secret = generate_secret();
+    let webhook = conn.create_webhook(user.id, &webhook, &secret).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(webhook::CreatedWebhook { webhook, secret }),
+    ))
+}
@@ -0,0 +1,32 @@
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    operation_id = "delete_webhook",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Id of the webhook")),
+    responses(
+        (status = 200, description = "The webhook was deleted, along with its deliveries", body = Object, example = json!({"msg": "Webhook Deleted"})),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+) -> Result<(StatusCode, Json<Value>), CustomError> {
+    if !conn.delete_webhook(user.id, id).await? {
+        return Err(CustomError::not_found("webhook", id));
+    }
+
+    Ok((StatusCode::OK, Json(json!({"msg": "Webhook Deleted"}))))
+}
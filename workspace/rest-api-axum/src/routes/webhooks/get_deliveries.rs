@@ -0,0 +1,37 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path, Query};
+use crate::models::webhook;
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    operation_id = "get_deliveries",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Id of the webhook"), webhook::DeliveryParams),
+    responses(
+        (status = 200, description = "A page of the deliveries of the webhook, newest first", body = DeliveryPage),
+        (status = 400, description = "Invalid query parameters", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path(id): Path<i32>,
+    Query(params): Query<webhook::DeliveryParams>,
+) -> Result<(StatusCode, Json<webhook::DeliveryPage>), CustomError> {
+    params.validate()?;
+
+    let page = conn
+        .list_deliveries(user.id, id, &params)
+        .await?
+        .ok_or_else(|| CustomError::not_found("webhook", id))?;
+
+    Ok((StatusCode::OK, Json(page)))
+}
@@ -0,0 +1,27 @@
+use axum::http::StatusCode;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::Json;
+use crate::models::webhook;
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    operation_id = "get_webhooks",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "The caller's webhooks, by id, without their secret", body = [Webhook]),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+) -> Result<(StatusCode, Json<Vec<webhook::Webhook>>), CustomError> {
+    let webhooks = conn.list_webhooks(user.id).await?;
+
+    Ok((StatusCode::OK, Json(webhooks)))
+}
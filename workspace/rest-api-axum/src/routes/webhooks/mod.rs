@@ -0,0 +1,5 @@
+pub mod create_webhook;
+pub mod delete_webhook;
+pub mod get_deliveries;
+pub mod get_webhooks;
+pub mod redeliver;
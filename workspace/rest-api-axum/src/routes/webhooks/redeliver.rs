@@ -0,0 +1,37 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+
+use crate::auth::CurrentUser;
+use crate::db::DatabaseConnection;
+use crate::errors::CustomError;
+use crate::extract::{Json, Path};
+use crate::models::webhook;
+
+#[utoipa::path(
+    post,
+    path = "/webhooks/{id}/deliveries/{delivery_id}/redeliver",
+    operation_id = "redeliver",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Id of the webhook"),
+        ("delivery_id" = i64, Path, description = "Id of the delivery"),
+    ),
+    responses(
+        (status = 202, description = "The delivery, pending again with every attempt left, whether it was dead or delivered", body = Delivery),
+        (status = 401, description = "Missing, invalid or expired access token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such delivery of the webhook", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn handler(
+    user: CurrentUser,
+    DatabaseConnection(mut conn): DatabaseConnection,
+    Path((id, delivery_id)): Path<(i32, i64)>,
+) -> Result<(StatusCode, Json<webhook::Delivery>), CustomError> {
+    let delivery = conn
+        .redeliver(user.id, id, delivery_id, Utc::now())
+        .await?
+        .ok_or_else(|| CustomError::not_found("delivery", delivery_id))?;
+
+    Ok((StatusCode::ACCEPTED, Json(delivery)))
+}
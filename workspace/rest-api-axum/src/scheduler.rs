@@ -0,0 +1,77 @@
+// Creation of the next occurrence of recurring tasks, once they are done.
+//
+// Every instance runs the scheduler, which wakes up whenever tasks change and
+// at least every `RECURRENCE_INTERVAL_SECS`, as changes made by other
+// processes are not always noticed. Backends make sure that each task recurs
+// once however many instances do: on Postgres, a single one at a time goes
+// through the tasks under an advisory lock, and every task that recurred is
+// recorded in the same transaction as its occurrence.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::errors::CustomError;
+use crate::repository::Store;
+use crate::shutdown::Stop;
+
+/// Tasks made to recur per transaction.
+const BATCH_SIZE: i64 = 100;
+
+/// How long to wait before starting again after an error.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Creates in the background, and right away, the next occurrence of the
+/// recurring tasks that are done, looking again at least every `interval`
+/// until asked to `stop`.
+pub fn start(store: Arc<dyn Store>, interval: Duration, mut stop: Stop) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let Err(err) = run(&*store, interval, &mut stop).await else {
+                return;
+            };
+            tracing::error!("could not create occurrences of recurring tasks: {err}");
+            tokio::select! {
+                () = tokio::time::sleep(RETRY_DELAY) => {}
+                () = stop.requested() => return,
+            }
+        }
+    })
+}
+
+/// Runs until asked to `stop`, which only ends the wait between two rounds.
+async fn run(store: &dyn Store, interval: Duration, stop: &mut Stop) -> Result<(), CustomError> {
+    // Listen first, not to miss a task done in the meantime.
+    let mut listener = store.listen().await?;
+    loop {
+        let count = create_next_occurrences(store).await?;
+        if count > 0 {
+            tracing::debug!("created {count} occurrences of recurring tasks");
+        }
+        tokio::select! {
+            changed = tokio::time::timeout(interval, listener.changed()) => {
+                if let Ok(result) = changed {
+                    result?;
+                }
+            }
+            () = stop.requested() => return Ok(()),
+        }
+    }
+}
+
+/// Creates the next occurrence of every recurring task that is done, until
+/// none is left, returning how many were created.
+pub async fn create_next_occurrences(store: &dyn Store) -> Result<usize, CustomError> {
+    let mut conn = store.acquire().await?;
+    let mut count = 0;
+    loop {
+        // Tasks whose rule has no next occurrence create none, so only a
+        // short batch tells that none is left.
+        let recurred = conn.create_next_occurrences(BATCH_SIZE).await?;
+        count += recurred.iter().flatten().count();
+        if recurred.len() < BATCH_SIZE as usize {
+            return Ok(count);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Graceful shutdown, so that deploys do not cut requests off.
+//
+// On SIGINT or SIGTERM the server stops accepting connections and lets the
+// requests in flight finish, up to `SHUTDOWN_TIMEOUT_SECS`. Their transactions
+// are committed or rolled back as usual before the binary closes the pool.
+
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+
+use axum::Router;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Completes on SIGINT (Ctrl+C) or, on Unix, SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("could not listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("could not listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Serves `app` on `listener` until `signal` completes, then waits for the
+/// requests in flight to finish, for at most `timeout` if there is one.
+///
+/// Requests still running past `timeout` are left behind, to be dropped along
+/// with the runtime.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    signal: impl Future<Output = ()> + Send + 'static,
+    timeout: Option<Duration>,
+) -> anyhow::Result<()> {
+    let (signalled, on_signal) = oneshot::channel();
+    let server = axum::Server::from_tcp(listener)?
+        // Rate limits tell anonymous clients apart by their address.
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            signal.await;
+            tracing::info!("shutting down, waiting for the requests in flight");
+            let _ = signalled.send(());
+        });
+
+    let deadline = async {
+        match (on_signal.await, timeout) {
+            (Ok(()), Some(timeout)) => tokio::time::sleep(timeout).await,
+            // Without a signal the server only stops on errors.
+            _ => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        result = server => result?,
+        () = deadline => {
+            tracing::warn!("requests still in flight after {timeout:?}, giving up on them");
+        }
+    }
+    Ok(())
+}
+
+/// Background tasks working with the database, to stop before the pool is
+/// closed under them.
+pub struct Workers {
+    stop: watch::Sender<bool>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Workers {
+    pub fn new() -> Self {
+        Self {
+            stop: watch::channel(false).0,
+            handles: Vec::new(),
+        }
+    }
+
+    /// What a worker waits on, between two pieces of work, to know when to stop.
+    pub fn stop_signal(&self) -> Stop {
+        Stop(self.stop.subscribe())
+    }
+
+    pub fn push(&mut self, handle: JoinHandle<()>) {
+        self.handles.push(handle);
+    }
+
+    /// Asks every worker to stop once done with what it is doing, and waits
+    /// for them to.
+    pub async fn stop(self) {
+        self.stop.send_replace(true);
+        for handle in self.handles {
+            if let Err(err) = handle.await {
+                tracing::error!("a background task failed: {err}");
+            }
+        }
+    }
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tells a background task when to stop.
+#[derive(Clone)]
+pub struct Stop(watch::Receiver<bool>);
+
+impl Stop {
+    /// Completes once the task is asked to stop, or its [`Workers`] are
+    /// dropped, right away if that is already the case.
+    pub async fn requested(&mut self) {
+        let _ = self.0.wait_for(|stop| *stop).await;
+    }
+}
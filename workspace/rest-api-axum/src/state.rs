@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+
+use crate::auth::Keys;
+use crate::config::Config;
+use crate::events::Hub;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+use crate::repository::Store;
+
+/// Everything the handlers can get from `State` or through extractors.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub store: Arc<dyn Store>,
+    pub keys: Arc<Keys>,
+    pub events: Hub,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub metrics: Arc<Metrics>,
+}
+
+impl AppState {
+    /// Also starts following the task events of `store` and pruning rate
+    /// limits, so it must be called within a Tokio runtime.
+    pub fn new(config: Config, store: Arc<dyn Store>) -> Self {
+        Self {
+            keys: Arc::new(Keys::new(&config)),
+            events: Hub::start(store.clone(), config.events_retention),
+            rate_limiter: RateLimiter::start(&config, store.clone()),
+            metrics: Arc::new(Metrics::new()),
+            config: Arc::new(config),
+            store,
+        }
+    }
+}
+
+impl FromRef<AppState> for Arc<Config> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Store> {
+    fn from_ref(state: &AppState) -> Self {
+        state.store.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Keys> {
+    fn from_ref(state: &AppState) -> Self {
+        state.keys.clone()
+    }
+}
+
+impl FromRef<AppState> for Hub {
+    fn from_ref(state: &AppState) -> Self {
+        state.events.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RateLimiter> {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Metrics> {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
@@ -0,0 +1,66 @@
+// Export of traces over OTLP, to a collector at `OTLP_ENDPOINT`. Only built
+// with the `otel` feature.
+//
+// The spans of the logs become those of the traces: the `request` span of
+// `TraceLayer`, and within it the spans of acquiring a connection and of each
+// query. Like the logs, they are filtered by `LOG_FILTER`. Requests carrying a
+// W3C `traceparent` header continue the trace of the client.
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TraceError;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The `service.name` of the exported spans.
+const SERVICE_NAME: &str = "rest-api-axum";
+
+/// Exports spans to the collector at `endpoint`, in batches, in the background.
+/// Must be called within a Tokio runtime.
+pub fn layer<S>(endpoint: &str) -> Result<impl Layer<S>, TraceError>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+        )
+        .install_batch(runtime::Tokio)?;
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Makes `span` a child of the span of the client, if `headers` tell one.
+pub fn set_parent(span: &Span, headers: &HeaderMap) {
+    let context = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    span.set_parent(context);
+}
+
+/// Exports the spans not sent yet.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
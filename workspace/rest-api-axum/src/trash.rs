@@ -0,0 +1,44 @@
+// Purging of the trash, where `DELETE /task/{id}` moves tasks until they are
+// restored with `POST /task/{id}/restore`.
+//
+// Every instance purges on its own schedule. Purging is idempotent, so
+// instances sharing a database only repeat each other's work.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::task::JoinHandle;
+
+use crate::errors::CustomError;
+use crate::repository::Store;
+use crate::shutdown::Stop;
+
+/// Upper bound on how late a task is purged after its retention period.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Purges in the background, and right away, the tasks that have been in the
+/// trash for longer than `retention`, until asked to `stop`.
+pub fn start_purging(store: Arc<dyn Store>, retention: Duration, mut stop: Stop) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(retention.min(PURGE_INTERVAL));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = stop.requested() => return,
+            }
+            match purge_expired(&*store, retention).await {
+                Ok(count) => tracing::debug!("purged {count} tasks from the trash"),
+                Err(err) => tracing::error!("could not purge the trash: {err}"),
+            }
+        }
+    })
+}
+
+/// Deletes for good the tasks that have been in the trash for longer than
+/// `retention`, returning how many.
+pub async fn purge_expired(store: &dyn Store, retention: Duration) -> Result<u64, CustomError> {
+    let before = Utc::now() - chrono::Duration::from_std(retention).unwrap_or_default();
+    store.acquire().await?.purge_trash(before).await
+}
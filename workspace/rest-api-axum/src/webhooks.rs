@@ -0,0 +1,405 @@
+// Delivery of task events to the webhooks of their owner.
+//
+// Backends keep an outbox of deliveries, written along with the events
+// themselves (see `WebhookRepository`). Every instance runs a worker, which
+// wakes up whenever tasks change and at least every `WEBHOOK_INTERVAL_SECS`
+// for the retries falling due. It claims the due deliveries for as long as
+// sending them may take, so that the workers of other instances leave them
+// alone, and POSTs their event as JSON, as the feed at `/tasks/events` has
+// it, signed with the secret of the webhook. A 2xx response delivers it.
+// Otherwise it is retried after a delay doubling with every attempt, until
+// the last one leaves it dead, until redelivered through the API.
+//
+// A worker stopping mid-way lets its claims expire, so events are delivered
+// at least once: receivers can tell duplicates by their `X-Webhook-Delivery`.
+//
+// Webhooks are only ever sent to public addresses, unless their host is one of
+// `WEBHOOK_ALLOWED_HOSTS`, for users not to have the server reach into the
+// network it runs in. Their host is checked on creation and resolved again by
+// every attempt, which refuses to connect to any other address.
+
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::http::header::{CONTENT_TYPE, USER_AGENT};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use hyper::client::connect::dns::Name;
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::{Body, Request, Uri};
+use hyper_tls::HttpsConnector;
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::config::Config;
+use crate::errors::CustomError;
+use crate::models::webhook::{Attempt, DeliveryStatus, DueDelivery};
+use crate::repository::Store;
+use crate::shutdown::Stop;
+
+/// The id of the delivery, the same for every attempt.
+pub const X_WEBHOOK_DELIVERY: &str = "x-webhook-delivery";
+/// The kind of event, e.g. `updated`.
+pub const X_WEBHOOK_EVENT: &str = "x-webhook-event";
+/// When the delivery was sent, in seconds since the Unix epoch.
+pub const X_WEBHOOK_TIMESTAMP: &str = "x-webhook-timestamp";
+/// `sha256=` and the hex HMAC-SHA256 of the timestamp, a dot and the body,
+/// keyed with the secret of the webhook. See [`sign`].
+pub const X_WEBHOOK_SIGNATURE: &str = "x-webhook-signature";
+
+/// Deliveries claimed, and sent concurrently, at once.
+const BATCH_SIZE: i64 = 20;
+/// Added to the timeout for the claims, to record the outcome.
+const LEASE_MARGIN: Duration = Duration::from_secs(30);
+/// The longest delay between attempts, however many there were.
+const MAX_BACKOFF: Duration = Duration::from_secs(24 * 60 * 60);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How the worker sends the deliveries, see the `WEBHOOK_*` keys of [`Config`].
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub max_attempts: u32,
+    pub backoff: Duration,
+    /// How long deliveries that are no longer pending are kept, like task
+    /// events. `None` keeps them forever.
+    pub retention: Option<Duration>,
+    /// Hosts sent deliveries whatever their address.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Settings {
+    /// `None` when the worker is disabled.
+    pub fn new(config: &Config) -> Option<Self> {
+        Some(Self {
+            interval: config.webhook_interval?,
+            timeout: config.webhook_timeout,
+            max_attempts: config.webhook_max_attempts,
+            backoff: config.webhook_backoff,
+            retention: config.events_retention,
+            allowed_hosts: config.webhook_allowed_hosts.clone(),
+        })
+    }
+
+    /// The delay before the attempt following the `attempts`th one.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
+
+/// Sends the deliveries of a store.
+pub struct Worker {
+    store: Arc<dyn Store>,
+    settings: Settings,
+    client: hyper::Client<HttpsConnector<HttpConnector<Resolver>>>,
+}
+
+impl Worker {
+    pub fn new(store: Arc<dyn Store>, settings: Settings) -> Self {
+        let mut http = HttpConnector::new_with_resolver(Resolver {
+            allowed_hosts: settings.allowed_hosts.clone().into(),
+        });
+        http.enforce_http(false);
+        Self {
+            store,
+            settings,
+            client: hyper::Client::builder().build(HttpsConnector::new_with_connector(http)),
+        }
+    }
+
+    /// Sends the deliveries in the background, right away and then as they
+    /// fall due, until asked to `stop`.
+    pub fn start(self, mut stop: Stop) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut pruned_at = None;
+            loop {
+                let Err(err) = self.run(&mut pruned_at, &mut stop).await else {
+                    return;
+                };
+                tracing::error!("could not deliver webhooks: {err}");
+                tokio::select! {
+                    () = tokio::time::sleep(RETRY_DELAY) => {}
+                    () = stop.requested() => return,
+                }
+            }
+        })
+    }
+
+    /// Runs until asked to `stop`, which only ends the wait between two
+    /// rounds, so that the attempts at a batch are always recorded.
+    async fn run(
+        &self,
+        pruned_at: &mut Option<Instant>,
+        stop: &mut Stop,
+    ) -> Result<(), CustomError> {
+        // Listen first, not to miss an event recorded in the meantime.
+        let mut listener = self.store.listen().await?;
+        loop {
+            let count = self.deliver_due().await?;
+            if count > 0 {
+                tracing::debug!("sent {count} webhook deliveries");
+            }
+
+            if let Some(retention) = self.settings.retention {
+                if pruned_at.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                    let before =
+                        Utc::now() - chrono::Duration::from_std(retention).unwrap_or_default();
+                    let count = self.store.acquire().await?.prune_deliveries(before).await?;
+                    tracing::debug!("pruned {count} webhook deliveries");
+                    *pruned_at = Some(Instant::now());
+                }
+            }
+
+            tokio::select! {
+                changed = tokio::time::timeout(self.settings.interval, listener.changed()) => {
+                    if let Ok(result) = changed {
+                        result?;
+                    }
+                }
+                () = stop.requested() => return Ok(()),
+            }
+        }
+    }
+
+    /// Sends every delivery that is due, a batch at a time until none is,
+    /// returning how many were sent, successfully or not.
+    pub async fn deliver_due(&self) -> Result<usize, CustomError> {
+        let lease =
+            chrono::Duration::from_std(self.settings.timeout + LEASE_MARGIN).unwrap_or_default();
+        let mut count = 0;
+        loop {
+            let now = Utc::now();
+            let deliveries = self
+                .store
+                .acquire()
+                .await?
+                .claim_deliveries(now, now + lease, BATCH_SIZE)
+                .await?;
+            if deliveries.is_empty() {
+                return Ok(count);
+            }
+            count += deliveries.len();
+
+            let outcomes =
+                futures_util::future::join_all(deliveries.iter().map(|d| self.send(d))).await;
+            let mut conn = self.store.acquire().await?;
+            for (delivery, outcome) in deliveries.iter().zip(outcomes) {
+                conn.record_attempt(delivery.id, &self.attempt(delivery, outcome))
+                    .await?;
+            }
+        }
+    }
+
+    /// POSTs the event of `delivery`, returning the status of the response
+    /// or, on failure, that status if any and the reason.
+    async fn send(&self, delivery: &DueDelivery) -> Result<u16, (Option<u16>, String)> {
+        check_literal(&delivery.url, &self.settings.allowed_hosts).map_err(|err| (None, err))?;
+        let body = serde_json::to_vec(&delivery.event()).map_err(|err| (None, err.to_string()))?;
+        let timestamp = Utc::now().timestamp();
+        let request = Request::post(&delivery.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(
+                USER_AGENT,
+                concat!("rest-api-axum/", env!("CARGO_PKG_VERSION")),
+            )
+            .header(X_WEBHOOK_DELIVERY, delivery.id)
+            .header(X_WEBHOOK_EVENT, delivery.kind.as_str())
+            .header(X_WEBHOOK_TIMESTAMP, timestamp)
+            .header(
+                X_WEBHOOK_SIGNATURE,
+                sign(&delivery.secret, timestamp, &body),
+            )
+            .body(Body::from(body))
+            .map_err(|err| (None, err.to_string()))?;
+
+        let response = tokio::time::timeout(self.settings.timeout, self.client.request(request))
+            .await
+            .map_err(|_| {
+                (
+                    None,
+                    format!("no response within {:?}", self.settings.timeout),
+                )
+            })?
+            .map_err(|err| (None, err.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.as_u16())
+        } else {
+            Err((Some(status.as_u16()), format!("responded with {status}")))
+        }
+    }
+
+    /// What becomes of `delivery` after the `outcome` of sending it.
+    fn attempt(
+        &self,
+        delivery: &DueDelivery,
+        outcome: Result<u16, (Option<u16>, String)>,
+    ) -> Attempt {
+        let now = Utc::now();
+        let attempts = u32::try_from(delivery.attempts).unwrap_or_default() + 1;
+        match outcome {
+            Ok(status) => Attempt {
+                status: DeliveryStatus::Delivered,
+                next_attempt_at: now,
+                response_status: Some(status.into()),
+                error: None,
+                at: now,
+            },
+            Err((status, error)) => {
+                tracing::debug!(
+                    delivery = delivery.id,
+                    attempts,
+                    "could not deliver to {}: {error}",
+                    delivery.url
+                );
+                let (status_after, delay) = if attempts >= self.settings.max_attempts {
+                    (DeliveryStatus::Dead, Duration::ZERO)
+                } else {
+                    (DeliveryStatus::Pending, self.settings.delay(attempts))
+                };
+                Attempt {
+                    status: status_after,
+                    next_attempt_at: now + chrono::Duration::from_std(delay).unwrap_or_default(),
+                    response_status: status.map(Into::into),
+                    error: Some(error),
+                    at: now,
+                }
+            }
+        }
+    }
+}
+
+/// The `X-Webhook-Signature` of a delivery of `body` sent at `timestamp`.
+/// Receivers compute it again to check that the delivery comes from here,
+/// and may reject old timestamps to thwart replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Resolves the host of `url`, refusing it unless all its addresses are
+/// public or it is one of `allowed_hosts`.
+pub async fn check_url(url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    let uri: Uri = url.parse().map_err(|err| format!("{err}"))?;
+    let host = unbracket(uri.host().unwrap_or_default());
+    resolve(host, allowed_hosts).await.map(|_| ())
+}
+
+/// Refuses `url` when its host is an address that is not public, as the
+/// connector of the worker only checks the host names it resolves.
+fn check_literal(url: &str, allowed_hosts: &[String]) -> Result<(), String> {
+    let uri: Uri = url.parse().map_err(|err| format!("{err}"))?;
+    let host = unbracket(uri.host().unwrap_or_default());
+    match host.parse() {
+        Ok(ip) => check_addresses(host, &[ip], allowed_hosts),
+        Err(_) => Ok(()),
+    }
+}
+
+/// The addresses of `host`, refused unless they are all public or it is one
+/// of `allowed_hosts`.
+async fn resolve(host: &str, allowed_hosts: &[String]) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<_> = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|err| format!("{host} could not be resolved: {err}"))?
+        .collect();
+    let ips: Vec<_> = addrs.iter().map(SocketAddr::ip).collect();
+    check_addresses(host, &ips, allowed_hosts)?;
+    Ok(addrs)
+}
+
+fn check_addresses(host: &str, ips: &[IpAddr], allowed_hosts: &[String]) -> Result<(), String> {
+    if allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return Ok(());
+    }
+    match ips.iter().find(|ip| !is_public(**ip)) {
+        Some(ip) => Err(format!("{host} is on {ip}, which is not a public address")),
+        None if ips.is_empty() => Err(format!("{host} has no address")),
+        None => Ok(()),
+    }
+}
+
+/// Whether `ip` is reachable from anywhere, rather than only from the host or
+/// network the server is on.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                // Shared address space, as private as the above behind a carrier-grade NAT.
+                || (ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    // Unique local addresses, private like 10.0.0.0/8.
+                    || first & 0xfe00 == 0xfc00
+                    // Link-local unicast.
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// `host` of a URL, without the brackets around an IPv6 address.
+fn unbracket(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Resolves the hosts of webhooks for the connector of the worker, refusing
+/// those it may not send to.
+#[derive(Clone)]
+struct Resolver {
+    allowed_hosts: Arc<[String]>,
+}
+
+impl Service<Name> for Resolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let allowed_hosts = self.allowed_hosts.clone();
+        Box::pin(async move {
+            resolve(name.as_str(), &allowed_hosts)
+                .await
+                .map(Vec::into_iter)
+                .map_err(|err| io::Error::new(io::ErrorKind::PermissionDenied, err))
+        })
+    }
+}
+
+/// A new random secret for a webhook.
+pub fn generate_secret() -> String {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
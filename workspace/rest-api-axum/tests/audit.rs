@@ -0,0 +1,263 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::trash;
+use serde_json::{json, Value};
+
+use common::{
+    app, app_on, create_task, delete, get, post, put, request, send, sqlite_app, sqlite_pool,
+    TestApp,
+};
+
+/// The entries of the audit log listed at `uri`.
+async fn entries(app: &TestApp, uri: &str) -> Vec<Value> {
+    let response = get(app, uri).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["items"].as_array().unwrap().clone()
+}
+
+fn actions(entries: &[Value]) -> Vec<&str> {
+    entries
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect()
+}
+
+async fn assert_every_change_is_recorded(app: TestApp) {
+    let id = create_task(&app, "Buy milk").await;
+    put(&app, &format!("/task/{id}"), json!({"task": "Buy bread"})).await;
+    let patch = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("/task/{id}"))
+        .header(header::CONTENT_TYPE, "application/merge-patch+json")
+        .body(Body::from(json!({"status": "done"}).to_string()))
+        .unwrap();
+    assert_eq!(send(&app, patch).await.status, StatusCode::OK);
+    delete(&app, &format!("/task/{id}")).await;
+    send(
+        &app,
+        request(Method::POST, &format!("/task/{id}/restore"), None),
+    )
+    .await;
+    delete(&app, &format!("/task/{id}?purge=true")).await;
+
+    let history = entries(&app, &format!("/task/{id}/history")).await;
+
+    assert_eq!(
+        actions(&history),
+        ["purge", "restore", "delete", "update", "update", "create"]
+    );
+    let [purge, restore, delete, patch, update, create] = &history[..] else {
+        unreachable!()
+    };
+    assert!(create["before"].is_null());
+    assert_eq!(create["after"]["task"], "Buy milk");
+    assert_eq!(update["before"]["task"], "Buy milk");
+    assert_eq!(update["after"]["task"], "Buy bread");
+    assert_eq!(patch["before"]["status"], update["after"]["status"]);
+    assert_eq!(patch["after"]["status"], "done");
+    assert!(delete["before"]["deleted_at"].is_null());
+    assert!(delete["after"]["deleted_at"].is_string());
+    assert!(restore["after"]["deleted_at"].is_null());
+    assert_eq!(purge["before"]["version"], 5);
+    assert!(purge["after"].is_null());
+    for entry in &history {
+        assert_eq!(entry["task_id"], id);
+        assert!(entry["actor_id"].is_i64());
+        assert!(entry["created_at"].is_string());
+        assert!(entry.get("owner_id").is_none());
+    }
+}
+
+#[tokio::test]
+async fn every_change_is_recorded() {
+    assert_every_change_is_recorded(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_every_change_is_recorded() {
+    assert_every_change_is_recorded(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn entries_have_the_request_id() {
+    let app = app().await;
+    let mut create = request(Method::POST, "/task", Some(json!({"task": "Buy milk"})));
+    create
+        .headers_mut()
+        .insert("x-request-id", "my-request".parse().unwrap());
+    let response = send(&app, create).await;
+    let id = response.body["id"].as_i64().unwrap();
+    put(&app, &format!("/task/{id}"), json!({"task": "Buy bread"})).await;
+
+    let history = entries(&app, &format!("/task/{id}/history")).await;
+
+    assert_eq!(history[1]["request_id"], "my-request");
+    let generated = history[0]["request_id"].as_str().unwrap();
+    assert!(!generated.is_empty() && generated != "my-request");
+    let found = entries(&app, "/audit?request_id=my-request").await;
+    assert_eq!(actions(&found), ["create"]);
+}
+
+async fn assert_audit_log_is_filtered(app: TestApp) {
+    let milk = create_task(&app, "Buy milk").await;
+    let bread = create_task(&app, "Buy bread").await;
+    // SQLite compares times to the millisecond.
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    delete(&app, &format!("/task/{milk}")).await;
+    let user_id = entries(&app, "/audit").await[0]["actor_id"].clone();
+
+    assert_eq!(
+        actions(&entries(&app, "/audit").await),
+        ["delete", "create", "create"]
+    );
+    let creates = entries(&app, "/audit?action=create").await;
+    assert_eq!(creates[0]["task_id"], bread);
+    assert_eq!(creates[1]["task_id"], milk);
+    let of_milk = entries(&app, &format!("/audit?task_id={milk}")).await;
+    assert_eq!(actions(&of_milk), ["delete", "create"]);
+    let by_user = entries(&app, &format!("/audit?actor_id={user_id}")).await;
+    assert_eq!(by_user.len(), 3);
+    assert!(entries(&app, "/audit?actor_id=0").await.is_empty());
+
+    let since = of_milk[0]["created_at"].as_str().unwrap();
+    let since = since.replace('+', "%2B");
+    let recent = entries(&app, &format!("/audit?since={since}")).await;
+    assert_eq!(actions(&recent), ["delete"]);
+    let older = entries(&app, &format!("/audit?until={since}")).await;
+    assert_eq!(actions(&older), ["create", "create"]);
+}
+
+#[tokio::test]
+async fn audit_log_is_filtered() {
+    assert_audit_log_is_filtered(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_audit_log_is_filtered() {
+    assert_audit_log_is_filtered(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn audit_log_is_paginated() {
+    let app = app().await;
+    for i in 0..5 {
+        create_task(&app, &format!("Task {i}")).await;
+    }
+
+    let mut seen = Vec::new();
+    let mut uri = "/audit?limit=2".to_owned();
+    loop {
+        let response = get(&app, &uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+        for entry in response.body["items"].as_array().unwrap() {
+            seen.push(entry["task_id"].as_i64().unwrap());
+        }
+        match response.body["next_cursor"].as_i64() {
+            Some(cursor) => uri = format!("/audit?limit=2&cursor={cursor}"),
+            None => break,
+        }
+    }
+
+    assert_eq!(seen, [5, 4, 3, 2, 1]);
+}
+
+#[tokio::test]
+async fn audit_log_only_has_the_callers_tasks() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let id = create_task(&bob, "Not for alice").await;
+
+    assert!(entries(&alice, "/audit").await.is_empty());
+    assert!(entries(&alice, &format!("/task/{id}/history"))
+        .await
+        .is_empty());
+    assert_eq!(entries(&bob, "/audit").await.len(), 1);
+}
+
+#[tokio::test]
+async fn bulk_changes_are_recorded() {
+    let app = app().await;
+    let id = create_task(&app, "Delete me").await;
+
+    let body = json!({"operations": [
+        {"op": "create", "task": {"task": "New"}},
+        {"op": "delete", "id": id},
+    ]});
+    let response = post(&app, "/tasks/bulk", body).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    assert_eq!(
+        actions(&entries(&app, "/audit").await),
+        ["delete", "create", "create"]
+    );
+}
+
+#[tokio::test]
+async fn failed_atomic_bulk_records_nothing() {
+    let app = app().await;
+
+    let body = json!({"operations": [
+        {"op": "create", "task": {"task": "New"}},
+        {"op": "delete", "id": 42},
+    ]});
+    let response = post(&app, "/tasks/bulk", body).await;
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+
+    assert!(entries(&app, "/audit").await.is_empty());
+}
+
+async fn assert_purging_the_trash_is_recorded(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    let id = create_task(&app, "Delete me").await;
+    delete(&app, &format!("/task/{id}")).await;
+    // SQLite compares times to the millisecond.
+    tokio::time::sleep(Duration::from_millis(5)).await;
+
+    trash::purge_expired(&*store, Duration::ZERO).await.unwrap();
+
+    let history = entries(&app, &format!("/task/{id}/history")).await;
+    assert_eq!(actions(&history), ["purge", "delete", "create"]);
+    assert!(history[0]["actor_id"].is_null());
+    assert!(history[0]["request_id"].is_null());
+}
+
+#[tokio::test]
+async fn purging_the_trash_is_recorded() {
+    assert_purging_the_trash_is_recorded(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_purging_the_trash_is_recorded() {
+    let store = SqliteStore::new(sqlite_pool(true).await);
+    assert_purging_the_trash_is_recorded(Arc::new(store)).await;
+}
+
+#[tokio::test]
+async fn invalid_audit_query_is_rejected() {
+    let app = app().await;
+
+    for (uri, field) in [
+        ("/audit?limit=0", "limit"),
+        ("/audit?limit=501", "limit"),
+        (
+            "/audit?since=2024-01-02T00:00:00Z&until=2024-01-01T00:00:00Z",
+            "until",
+        ),
+        ("/task/1/history?limit=0", "limit"),
+    ] {
+        let response = get(&app, uri).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(response.body["errors"][0]["field"], field, "{uri}");
+    }
+    let response = get(&app, "/audit?action=archive").await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
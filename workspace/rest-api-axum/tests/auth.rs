@@ -0,0 +1,161 @@
+mod common;
+
+use axum::http::{header, StatusCode};
+use serde_json::json;
+
+use common::{app, create_task, delete, get, post, put, sqlite_app, TestApp, PASSWORD};
+
+#[tokio::test]
+async fn tasks_require_a_token() {
+    let app = app().await.anonymous();
+
+    let response = get(&app, "/tasks").await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers[header::WWW_AUTHENTICATE], "Bearer");
+    assert_eq!(response.body["type"], "/problems/unauthorized");
+}
+
+#[tokio::test]
+async fn invalid_tokens_are_rejected() {
+    let app = app().await;
+    let forged = TestApp {
+        token: Some(format!("{}x", app.token.as_deref().unwrap())),
+        ..app.clone()
+    };
+
+    let response = get(&forged, "/tasks").await;
+
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.body["detail"], "invalid token");
+}
+
+#[tokio::test]
+async fn register_rejects_invalid_and_duplicate_accounts() {
+    let app = app().await.anonymous();
+
+    let response = post(
+        &app,
+        "/auth/register",
+        json!({"email": "nobody", "password": "short"}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["errors"].as_array().unwrap().len(), 2);
+
+    // Emails are case-insensitive.
+    let response = post(
+        &app,
+        "/auth/register",
+        json!({"email": "User@Example.com", "password": PASSWORD}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn login_with_wrong_credentials() {
+    let app = app().await.anonymous();
+
+    let wrong_password = post(
+        &app,
+        "/auth/login",
+        json!({"email": "user@example.com", "password": "not the password"}),
+    )
+    .await;
+    let unknown_email = post(
+        &app,
+        "/auth/login",
+        json!({"email": "someone@example.com", "password": PASSWORD}),
+    )
+    .await;
+
+    assert_eq!(wrong_password.status, StatusCode::UNAUTHORIZED);
+    // Nothing tells whether the account exists.
+    assert_eq!(wrong_password.body, unknown_email.body);
+}
+
+#[tokio::test]
+async fn refresh_tokens() {
+    let app = app().await.anonymous();
+    let login = post(
+        &app,
+        "/auth/login",
+        json!({"email": "user@example.com", "password": PASSWORD}),
+    )
+    .await;
+    assert_eq!(login.body["token_type"], "Bearer");
+    let refresh_token = login.body["refresh_token"].as_str().unwrap();
+
+    let refreshed = post(
+        &app,
+        "/auth/refresh",
+        json!({"refresh_token": refresh_token}),
+    )
+    .await;
+    assert_eq!(refreshed.status, StatusCode::OK);
+    let refreshed = TestApp {
+        token: Some(refreshed.body["access_token"].as_str().unwrap().to_owned()),
+        ..app.clone()
+    };
+    assert_eq!(get(&refreshed, "/tasks").await.status, StatusCode::OK);
+
+    // Each kind of token only works for its own purpose.
+    let as_access = TestApp {
+        token: Some(refresh_token.to_owned()),
+        ..app.clone()
+    };
+    assert_eq!(
+        get(&as_access, "/tasks").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    let as_refresh = post(
+        &app,
+        "/auth/refresh",
+        json!({"refresh_token": login.body["access_token"]}),
+    )
+    .await;
+    assert_eq!(as_refresh.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn tasks_of_other_users_are_not_found() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let id = create_task(&alice, "alice's").await;
+    create_task(&bob, "bob's").await;
+
+    let list = get(&bob, "/tasks").await;
+    assert_eq!(list.body["total"], 1);
+    assert_eq!(list.body["items"][0]["task"], "bob's");
+
+    let uri = format!("/task/{id}");
+    assert_eq!(get(&bob, &uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        put(&bob, &uri, json!({"task": "mine"})).await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(delete(&bob, &uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(get(&alice, &uri).await.body["task"], "alice's");
+}
+
+#[tokio::test]
+async fn sqlite_backend_scopes_tasks_to_their_owner() {
+    let alice = sqlite_app(true).await;
+    let bob = alice.register("bob@example.com").await;
+    let id = create_task(&alice, "alice's").await;
+
+    assert_eq!(get(&bob, "/tasks").await.body["total"], 0);
+    assert_eq!(
+        get(&bob, &format!("/task/{id}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+
+    let duplicate = post(
+        &alice.anonymous(),
+        "/auth/register",
+        json!({"email": "bob@example.com", "password": PASSWORD}),
+    )
+    .await;
+    assert_eq!(duplicate.status, StatusCode::CONFLICT);
+}
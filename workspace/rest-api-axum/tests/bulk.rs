@@ -0,0 +1,190 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{app, create_task, get, post, sqlite_app, Response, TestApp};
+
+fn statuses(response: &Response) -> Vec<u64> {
+    response.body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["status"].as_u64().unwrap())
+        .collect()
+}
+
+async fn tasks(app: &TestApp) -> Vec<Value> {
+    get(app, "/tasks").await.body["items"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test]
+async fn bulk_applies_every_operation() {
+    let app = app().await;
+    let updated = create_task(&app, "draft").await;
+    let deleted = create_task(&app, "obsolete").await;
+
+    let response = post(
+        &app,
+        "/tasks/bulk",
+        json!({"operations": [
+            {"op": "create", "task": {"task": "first"}},
+            {"op": "create", "task": {"task": "second", "priority": 2}},
+            {"op": "update", "id": updated, "version": 1, "task": {"task": "final"}},
+            {"op": "delete", "id": deleted},
+        ]}),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["committed"], true);
+    assert_eq!(statuses(&response), [201, 201, 200, 200]);
+    assert_eq!(response.body["results"][1]["task"]["priority"], 2);
+    assert_eq!(response.body["results"][2]["task"]["version"], 2);
+    let names: Vec<_> = tasks(&app)
+        .await
+        .iter()
+        .map(|t| t["task"].clone())
+        .collect();
+    assert_eq!(names, ["final", "first", "second"]);
+}
+
+#[tokio::test]
+async fn atomic_bulk_rolls_back_on_failure() {
+    let app = app().await;
+    let id = create_task(&app, "draft").await;
+
+    let response = post(
+        &app,
+        "/tasks/bulk",
+        json!({"mode": "atomic", "operations": [
+            {"op": "create", "task": {"task": "new"}},
+            {"op": "update", "id": id, "version": 7, "task": {"task": "final"}},
+            {"op": "delete", "id": id},
+        ]}),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    assert_eq!(response.body["committed"], false);
+    assert_eq!(statuses(&response), [424, 412, 424]);
+    assert_eq!(
+        response.body["results"][0]["error"]["detail"],
+        "not applied because operations[1] failed"
+    );
+    let tasks = tasks(&app).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["task"], "draft");
+}
+
+#[tokio::test]
+async fn atomic_bulk_reports_every_invalid_operation() {
+    let app = app().await;
+
+    let response = post(
+        &app,
+        "/tasks/bulk",
+        json!({"operations": [
+            {"op": "create", "task": {"task": "fine"}},
+            {"op": "create", "task": {"task": ""}},
+            {"op": "create", "task": {"task": "too important", "priority": 9}},
+        ]}),
+    )
+    .await;
+
+    assert_eq!(statuses(&response), [424, 400, 400]);
+    assert_eq!(
+        response.body["results"][2]["error"]["errors"][0]["field"],
+        "priority"
+    );
+    assert!(tasks(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn best_effort_bulk_skips_failures() {
+    let app = app().await;
+
+    let response = post(
+        &app,
+        "/tasks/bulk",
+        json!({"mode": "best_effort", "operations": [
+            {"op": "create", "task": {"task": "first"}},
+            {"op": "delete", "id": 42},
+            {"op": "create", "task": {"task": ""}},
+            {"op": "create", "task": {"task": "second"}},
+        ]}),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    assert_eq!(response.body["committed"], true);
+    assert_eq!(statuses(&response), [201, 404, 400, 201]);
+    assert_eq!(tasks(&app).await.len(), 2);
+}
+
+#[tokio::test]
+async fn bulk_only_touches_the_callers_tasks() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let id = create_task(&alice, "alice's").await;
+
+    let response = post(
+        &bob,
+        "/tasks/bulk",
+        json!({"mode": "best_effort", "operations": [{"op": "delete", "id": id}]}),
+    )
+    .await;
+
+    assert_eq!(statuses(&response), [404]);
+    assert_eq!(tasks(&alice).await.len(), 1);
+}
+
+#[tokio::test]
+async fn bulk_rejects_too_many_operations() {
+    let app = app().await;
+    let operations = vec![json!({"op": "delete", "id": 1}); 1001];
+
+    let response = post(&app, "/tasks/bulk", json!({ "operations": operations })).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["errors"][0]["field"], "operations");
+}
+
+#[tokio::test]
+async fn sqlite_bulk_uses_savepoints() {
+    let app = sqlite_app(true).await;
+    let id = create_task(&app, "draft").await;
+
+    let best_effort = post(
+        &app,
+        "/tasks/bulk",
+        json!({"mode": "best_effort", "operations": [
+            {"op": "update", "id": id, "task": {"task": "final"}},
+            {"op": "update", "id": 42, "task": {"task": "missing"}},
+            {"op": "create", "task": {"task": "new"}},
+        ]}),
+    )
+    .await;
+    assert_eq!(statuses(&best_effort), [200, 404, 201]);
+
+    let atomic = post(
+        &app,
+        "/tasks/bulk",
+        json!({"operations": [
+            {"op": "delete", "id": id},
+            {"op": "delete", "id": 42},
+        ]}),
+    )
+    .await;
+    assert_eq!(statuses(&atomic), [424, 404]);
+
+    let names: Vec<_> = tasks(&app)
+        .await
+        .iter()
+        .map(|t| t["task"].clone())
+        .collect();
+    assert_eq!(names, ["final", "new"]);
+}
@@ -0,0 +1,179 @@
+// Helpers shared by the integration tests, which drive the router in process.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::Router;
+use rest_api_axum::auth::Keys;
+use rest_api_axum::config::Config;
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::state::AppState;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use tower::ServiceExt;
+
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+/// The API, and the access token sent with every request, if any.
+#[derive(Clone)]
+pub struct TestApp {
+    pub router: Router,
+    pub token: Option<String>,
+}
+
+impl TestApp {
+    /// The same API, without credentials.
+    pub fn anonymous(&self) -> Self {
+        Self {
+            router: self.router.clone(),
+            token: None,
+        }
+    }
+
+    /// The same API, as a newly registered user.
+    pub async fn register(&self, email: &str) -> Self {
+        let anonymous = self.anonymous();
+        let credentials = json!({"email": email, "password": PASSWORD});
+        let response = post(&anonymous, "/auth/register", credentials.clone()).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        let response = post(&anonymous, "/auth/login", credentials).await;
+        assert_eq!(response.status, StatusCode::OK);
+        Self {
+            router: self.router.clone(),
+            token: Some(response.body["access_token"].as_str().unwrap().to_owned()),
+        }
+    }
+}
+
+pub const PASSWORD: &str = "correct horse battery staple";
+
+/// The API on top of an empty in-memory store, as a registered user.
+pub async fn app() -> TestApp {
+    app_on(Arc::new(MemoryStore::default())).await
+}
+
+/// The API on top of `store`, which must have its schema, as a registered user.
+pub async fn app_on(store: Arc<dyn Store>) -> TestApp {
+    app_with(Config::default(), store).await
+}
+
+/// Like [`app_on`], with `config`.
+pub async fn app_with(config: Config, store: Arc<dyn Store>) -> TestApp {
+    let app = TestApp {
+        router: rest_api_axum::app(AppState::new(config, store)),
+        token: None,
+    };
+    app.register("user@example.com").await
+}
+
+/// The API on top of an in-memory SQLite database, with or without its schema.
+///
+/// Without it no account can be registered, so requests carry a token for a
+/// user that does not exist.
+pub async fn sqlite_app(migrate: bool) -> TestApp {
+    let pool = sqlite_pool(migrate).await;
+    let config = Config::default();
+    let token = Keys::new(&config).issue(1).unwrap().access_token;
+    let app = TestApp {
+        router: rest_api_axum::app(AppState::new(config, Arc::new(SqliteStore::new(pool)))),
+        token: None,
+    };
+    if migrate {
+        app.register("user@example.com").await
+    } else {
+        TestApp {
+            token: Some(token),
+            ..app
+        }
+    }
+}
+
+/// An in-memory SQLite database, with or without its schema.
+pub async fn sqlite_pool(migrate: bool) -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    if migrate {
+        rest_api_axum::cli::migrate::SQLITE_MIGRATOR
+            .run(&pool)
+            .await
+            .unwrap();
+    }
+    pool
+}
+
+/// Sends `request`, with the token of `app` unless it already has credentials.
+pub async fn send(app: &TestApp, request: Request<Body>) -> Response {
+    let response = open(app, request).await;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+    };
+    Response {
+        status,
+        headers,
+        body,
+    }
+}
+
+/// Like [`send`], but leaves the body to be read, e.g. as a stream.
+pub async fn open(app: &TestApp, mut request: Request<Body>) -> axum::response::Response {
+    if let Some(token) = &app.token {
+        if !request.headers().contains_key(header::AUTHORIZATION) {
+            let value = HeaderValue::from_str(&format!("Bearer {token}")).unwrap();
+            request.headers_mut().insert(header::AUTHORIZATION, value);
+        }
+    }
+    app.router.clone().oneshot(request).await.unwrap()
+}
+
+pub async fn get(app: &TestApp, uri: &str) -> Response {
+    send(app, request(Method::GET, uri, None)).await
+}
+
+pub async fn delete(app: &TestApp, uri: &str) -> Response {
+    send(app, request(Method::DELETE, uri, None)).await
+}
+
+pub async fn post(app: &TestApp, uri: &str, body: Value) -> Response {
+    send(app, request(Method::POST, uri, Some(body))).await
+}
+
+pub async fn put(app: &TestApp, uri: &str, body: Value) -> Response {
+    send(app, request(Method::PUT, uri, Some(body))).await
+}
+
+/// A request with a JSON body, if any.
+pub fn request(method: Method, uri: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder().method(method).uri(uri);
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    }
+}
+
+/// Creates a task and returns its id.
+pub async fn create_task(app: &TestApp, task: &str) -> i64 {
+    let response = post(app, "/task", json!({ "task": task })).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    response.body["id"].as_i64().unwrap()
+}
@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use rest_api_axum::config::{Config, ConfigErrors, Purpose, Source, Sources};
+
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+/// Loads the configuration for `purpose` from a TOML file and a `.env` file
+/// with the given contents, and the environment `env`.
+fn load_for(
+    purpose: Purpose,
+    toml: &str,
+    dotenv: &str,
+    env: &[(&str, &str)],
+) -> Result<Config, ConfigErrors> {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("config.toml");
+    std::fs::write(&file, toml).unwrap();
+    let sources = Sources {
+        dotenv: dir.path().join(".env"),
+        env: env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .chain([("CONFIG_FILE".to_owned(), file.into())])
+            .collect(),
+    };
+    std::fs::write(&sources.dotenv, dotenv).unwrap();
+    Config::load_from(purpose, &sources)
+}
+
+fn load(toml: &str, dotenv: &str, env: &[(&str, &str)]) -> Result<Config, ConfigErrors> {
+    load_for(Purpose::Serve, toml, dotenv, env)
+}
+
+/// The keys of `errors`, in order, along with where their values came from,
+/// files being named without their directory.
+fn keys(errors: &ConfigErrors) -> Vec<(&str, Option<String>)> {
+    errors
+        .0
+        .iter()
+        .map(|error| {
+            let source = error.source.as_ref().map(|source| match source {
+                Source::File(path) => path.file_name().unwrap().to_string_lossy().into_owned(),
+                source => source.to_string(),
+            });
+            (error.key.as_str(), source)
+        })
+        .collect()
+}
+
+#[test]
+fn later_sources_win() {
+    let config = load(
+        "request_timeout_secs = 11\n\
+         shutdown_timeout_secs = 12\n\
+         access_token_ttl_secs = 13\n",
+        "SHUTDOWN_TIMEOUT_SECS=22\n\
+         ACCESS_TOKEN_TTL_SECS=23\n",
+        &[
+            ("DATABASE_URL", "memory:"),
+            ("JWT_SECRET", SECRET),
+            ("ACCESS_TOKEN_TTL_SECS", "33"),
+        ],
+    )
+    .unwrap();
+
+    // Set nowhere.
+    assert_eq!(
+        config.database_max_connections,
+        Config::default().database_max_connections
+    );
+    assert_eq!(config.request_timeout, Duration::from_secs(11));
+    assert_eq!(config.shutdown_timeout, Some(Duration::from_secs(22)));
+    assert_eq!(config.access_token_ttl, Duration::from_secs(33));
+}
+
+#[test]
+fn toml_values_may_be_of_any_sensible_type() {
+    let config = load(
+        "database_url = \"memory:\"\n\
+         jwt_secret = \"0123456789abcdef0123456789abcdef\"\n\
+         database_max_connections = 7\n\
+         database_auto_migrate = true\n\
+         cors_origins = [\"https://a.example\", \"https://b.example\"]\n",
+        "",
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(config.database_url, "memory:");
+    assert_eq!(config.database_max_connections, 7);
+    assert!(config.database_auto_migrate);
+    assert_eq!(
+        config.cors_origins,
+        ["https://a.example", "https://b.example"]
+    );
+}
+
+#[test]
+fn unexpected_toml_values_are_rejected() {
+    let errors = load(
+        "request_timeout_secs = 1.5\n\
+         cors_origins = [1, 2]\n\
+         no_such_key = 1\n",
+        "",
+        &[("DATABASE_URL", "memory:"), ("JWT_SECRET", SECRET)],
+    )
+    .unwrap_err();
+
+    // In the order of the keys.
+    let messages: Vec<_> = errors.0.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "expected a list of strings",
+            "unknown key",
+            "unexpected float"
+        ]
+    );
+}
+
+#[test]
+fn every_invalid_key_is_reported_at_once() {
+    let errors = load(
+        "log_format = \"yaml\"\n",
+        "BIND_ADDRESS=nowhere\n",
+        &[
+            ("JWT_SECRET", "short"),
+            ("DATABASE_MAX_CONNECTIONS", "lots"),
+        ],
+    )
+    .unwrap_err();
+
+    let from = |source: &str| Some(source.to_owned());
+    assert_eq!(
+        keys(&errors),
+        [
+            ("DATABASE_URL", None),
+            ("DATABASE_MAX_CONNECTIONS", from("environment")),
+            ("BIND_ADDRESS", from(".env")),
+            ("LOG_FORMAT", from("config.toml")),
+            ("JWT_SECRET", from("environment")),
+        ]
+    );
+    let report = errors.to_string();
+    assert!(
+        report.starts_with("invalid configuration (5 errors):"),
+        "{report}"
+    );
+}
+
+#[test]
+fn only_serving_needs_a_jwt_secret() {
+    let env = [("DATABASE_URL", "memory:")];
+
+    let errors = load_for(Purpose::Serve, "", "", &env).unwrap_err();
+    assert_eq!(keys(&errors), [("JWT_SECRET", None)]);
+
+    assert!(load_for(Purpose::Database, "", "", &env).is_ok());
+    let short = [("DATABASE_URL", "memory:"), ("JWT_SECRET", "short")];
+    assert!(load_for(Purpose::Database, "", "", &short).is_ok());
+}
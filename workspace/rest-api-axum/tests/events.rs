@@ -0,0 +1,222 @@
+mod common;
+
+use std::net::TcpListener;
+use std::time::Duration;
+
+use axum::body::{Body, BoxBody};
+use axum::http::{header, Method, Request, StatusCode};
+use futures_util::StreamExt;
+use hyper::body::HttpBody;
+use serde_json::{json, Value};
+
+use common::{app, create_task, delete, open, put, request, send, sqlite_app, TestApp};
+
+/// A server-sent event, as `(id, event, data)`.
+type Sse = (i64, String, Value);
+
+/// The stream of events of `app`, resuming after `last_event_id` if any.
+async fn subscribe(app: &TestApp, last_event_id: Option<i64>) -> BoxBody {
+    let mut request = request(Method::GET, "/tasks/events", None);
+    if let Some(id) = last_event_id {
+        request
+            .headers_mut()
+            .insert("last-event-id", id.to_string().parse().unwrap());
+    }
+    let response = open(app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    response.into_body()
+}
+
+/// Reads the next `count` events of `body`, skipping keep-alive comments.
+async fn next_events(body: &mut BoxBody, count: usize) -> Vec<Sse> {
+    let mut text = String::new();
+    let mut events = Vec::new();
+    while events.len() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.data())
+            .await
+            .expect("no event within 5s")
+            .expect("the stream ended")
+            .unwrap();
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+
+        while let Some((event, rest)) = text.split_once("\n\n") {
+            let (mut id, mut name, mut data) = (None, None, None);
+            for line in event.lines() {
+                match line.split_once(':') {
+                    Some(("id", value)) => id = Some(value.trim().parse().unwrap()),
+                    Some(("event", value)) => name = Some(value.trim().to_owned()),
+                    Some(("data", value)) => data = Some(serde_json::from_str(value).unwrap()),
+                    _ => {}
+                }
+            }
+            if let (Some(id), Some(name), Some(data)) = (id, name, data) {
+                events.push((id, name, data));
+            }
+            text = rest.to_owned();
+        }
+    }
+    events
+}
+
+async fn assert_feed_follows_changes(app: TestApp) {
+    let mut events = subscribe(&app, None).await;
+
+    let id = create_task(&app, "Write the docs").await;
+    let response = put(
+        &app,
+        &format!("/task/{id}"),
+        json!({"task": "Read the docs"}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        delete(&app, &format!("/task/{id}")).await.status,
+        StatusCode::OK
+    );
+
+    let events = next_events(&mut events, 3).await;
+    let names: Vec<&str> = events.iter().map(|(_, name, _)| name.as_str()).collect();
+    assert_eq!(names, ["created", "updated", "deleted"]);
+    assert!(events.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (event_id, name, data) in &events {
+        assert_eq!(data["id"], *event_id);
+        assert_eq!(data["kind"], *name);
+        assert_eq!(data["task_id"], id);
+        assert!(data.get("owner_id").is_none());
+    }
+    assert_eq!(events[0].2["task"]["task"], "Write the docs");
+    assert_eq!(events[1].2["task"]["task"], "Read the docs");
+    assert_eq!(events[1].2["task"]["version"], 2);
+    assert!(events[2].2["task"].is_null());
+}
+
+#[tokio::test]
+async fn feed_follows_changes() {
+    assert_feed_follows_changes(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_feed_follows_changes() {
+    assert_feed_follows_changes(sqlite_app(true).await).await;
+}
+
+async fn assert_trash_looks_like_delete_and_create(app: TestApp) {
+    let id = create_task(&app, "Trash me").await;
+    let mut events = subscribe(&app, None).await;
+
+    delete(&app, &format!("/task/{id}")).await;
+    let restore = request(Method::POST, &format!("/task/{id}/restore"), None);
+    assert_eq!(send(&app, restore).await.status, StatusCode::OK);
+    delete(&app, &format!("/task/{id}")).await;
+    delete(&app, &format!("/task/{id}?purge=true")).await;
+    // Purging a task from the trash is not news, so this comes right after.
+    let other = create_task(&app, "Not trashed").await;
+
+    let events = next_events(&mut events, 4).await;
+    let names: Vec<&str> = events.iter().map(|(_, name, _)| name.as_str()).collect();
+    assert_eq!(names, ["deleted", "created", "deleted", "created"]);
+    assert_eq!(events[1].2["task"]["task"], "Trash me");
+    assert!(events[1].2["task"]["deleted_at"].is_null());
+    assert_eq!(events[3].2["task_id"], other);
+}
+
+#[tokio::test]
+async fn trash_looks_like_delete_and_create() {
+    assert_trash_looks_like_delete_and_create(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_trash_looks_like_delete_and_create() {
+    assert_trash_looks_like_delete_and_create(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn feed_only_has_the_callers_tasks() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let mut events = subscribe(&alice, None).await;
+
+    create_task(&bob, "Not for alice").await;
+    let id = create_task(&alice, "For alice").await;
+
+    let events = next_events(&mut events, 1).await;
+    assert_eq!(events[0].2["task_id"], id);
+}
+
+#[tokio::test]
+async fn feed_resumes_after_last_event_id() {
+    let app = app().await;
+    let mut ids = Vec::new();
+    for task in ["one", "two", "three"] {
+        ids.push(create_task(&app, task).await);
+    }
+    let first = next_events(&mut subscribe(&app, Some(0)).await, 1).await[0].0;
+
+    let mut events = subscribe(&app, Some(first)).await;
+    let id = create_task(&app, "four").await;
+
+    let events = next_events(&mut events, 3).await;
+    let task_ids: Vec<&Value> = events.iter().map(|(_, _, data)| &data["task_id"]).collect();
+    assert_eq!(task_ids, [ids[1], ids[2], id]);
+}
+
+#[tokio::test]
+async fn feed_accepts_the_token_in_the_query() {
+    let app = app().await;
+    let token = app.token.clone().unwrap();
+    let anonymous = app.anonymous();
+
+    let response = open(&anonymous, request(Method::GET, "/tasks/events", None)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let uri = format!("/tasks/events?access_token={token}");
+    let response = open(&anonymous, request(Method::GET, &uri, None)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn invalid_last_event_id_is_rejected() {
+    let app = app().await;
+    let request = Request::get("/tasks/events")
+        .header("last-event-id", "latest")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = open(&app, request).await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn websocket_sends_changes() {
+    let app = app().await;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = axum::Server::from_tcp(listener)
+        .unwrap()
+        .serve(app.router.clone().into_make_service());
+    tokio::spawn(server);
+    let first = create_task(&app, "Before connecting").await;
+
+    let token = app.token.as_ref().unwrap();
+    let url = format!("ws://{addr}/tasks/ws?access_token={token}&last_event_id=0");
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    let second = create_task(&app, "After connecting").await;
+
+    let mut task_ids = Vec::new();
+    while task_ids.len() < 2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("no message within 5s")
+            .expect("the socket closed")
+            .unwrap();
+        let event: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(event["kind"], "created");
+        task_ids.push(event["task_id"].as_i64().unwrap());
+    }
+    assert_eq!(task_ids, [first, second]);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::{header, StatusCode};
+use rest_api_axum::config::Config;
+use rest_api_axum::models::rate_limit::Limit;
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use serde_json::json;
+
+use common::{app, app_on, app_with, create_task, get, post, sqlite_app, sqlite_pool, TestApp};
+
+/// The samples of the metric `name` scraped from `/metrics`, with their labels.
+async fn samples(app: &TestApp, name: &str) -> Vec<(String, f64)> {
+    let response = get(&app.anonymous(), "/metrics").await;
+    assert_eq!(response.status, StatusCode::OK);
+    response
+        .body
+        .as_str()
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix(name))
+        .filter_map(|line| {
+            let (labels, value) = line.rsplit_once(' ')?;
+            Some((labels.to_owned(), value.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The value of the sample of `name` with exactly `labels`.
+async fn sample(app: &TestApp, name: &str, labels: &str) -> Option<f64> {
+    samples(app, name)
+        .await
+        .into_iter()
+        .find(|(l, _)| l == labels)
+        .map(|(_, value)| value)
+}
+
+#[tokio::test]
+async fn process_is_healthy() {
+    let app = app().await.anonymous();
+
+    let response = get(&app, "/healthz").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, "ok");
+}
+
+#[tokio::test]
+async fn ready_when_the_database_answers() {
+    for app in [app().await, sqlite_app(true).await] {
+        let response = get(&app.anonymous(), "/readyz").await;
+
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body, "ok");
+    }
+}
+
+#[tokio::test]
+async fn not_ready_when_the_pool_is_closed() {
+    let pool = sqlite_pool(true).await;
+    let app = app_on(Arc::new(SqliteStore::new(pool.clone()))).await;
+    pool.close().await;
+
+    let response = get(&app.anonymous(), "/readyz").await;
+
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.body["type"], "/problems/db-unavailable");
+    assert_eq!(get(&app, "/healthz").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_and_status() {
+    let app = app().await;
+    let id = create_task(&app, "Buy milk").await;
+    get(&app, &format!("/task/{id}")).await;
+    get(&app, "/task/42").await;
+    get(&app, "/nowhere").await;
+
+    let ok = r#"{method="GET",route="/task/:id",status="200"}"#;
+    assert_eq!(sample(&app, "http_requests_total", ok).await, Some(1.0));
+    let not_found = r#"{method="GET",route="/task/:id",status="404"}"#;
+    assert_eq!(
+        sample(&app, "http_requests_total", not_found).await,
+        Some(1.0)
+    );
+    let created = r#"{method="POST",route="/task",status="201"}"#;
+    assert_eq!(
+        sample(&app, "http_requests_total", created).await,
+        Some(1.0)
+    );
+    let unmatched = r#"{method="GET",route="unmatched",status="404"}"#;
+    assert_eq!(
+        sample(&app, "http_requests_total", unmatched).await,
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&app, "http_request_duration_seconds_count", ok).await,
+        Some(1.0)
+    );
+    let buckets = samples(&app, "http_request_duration_seconds_bucket").await;
+    assert!(buckets
+        .iter()
+        .any(|(labels, _)| labels.contains(r#"route="/task/:id",status="200",le="#)));
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_counted() {
+    let config = Config {
+        rate_limit: Some("1/60".parse::<Limit>().unwrap()),
+        rate_limit_routes: vec!["/auth/register=0".parse().unwrap()],
+        ..Config::default()
+    };
+    let app = app_with(config, Arc::new(MemoryStore::default())).await;
+    get(&app, "/tasks").await;
+    get(&app, "/tasks").await;
+
+    let limited = r#"{method="GET",route="/tasks",status="429"}"#;
+    assert_eq!(
+        sample(&app, "http_requests_total", limited).await,
+        Some(1.0)
+    );
+    // Scrapes themselves are not limited.
+    assert_eq!(get(&app, "/metrics").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn queries_are_timed_by_handler() {
+    let app = sqlite_app(true).await;
+    create_task(&app, "Buy milk").await;
+    post(&app, "/tags", json!({"name": "home"})).await;
+
+    let create = r#"{handler="POST /task",operation="create"}"#;
+    assert_eq!(
+        sample(&app, "db_query_duration_seconds_count", create).await,
+        Some(1.0)
+    );
+    let tag = r#"{handler="POST /tags",operation="create_tag"}"#;
+    assert_eq!(
+        sample(&app, "db_query_duration_seconds_count", tag).await,
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn pool_connections_are_reported() {
+    let app = sqlite_app(true).await;
+
+    let response = get(&app.anonymous(), "/metrics").await;
+
+    assert_eq!(
+        response.headers[header::CONTENT_TYPE],
+        "text/plain; version=0.0.4"
+    );
+    // The pool has a single connection, idle or not.
+    let idle = sample(&app, "db_pool_connections", r#"{state="idle"}"#).await;
+    let in_use = sample(&app, "db_pool_connections", r#"{state="in_use"}"#).await;
+    assert_eq!(idle.unwrap() + in_use.unwrap(), 1.0);
+    assert_eq!(
+        sample(&app, "db_pool_connections", r#"{state="waiting"}"#).await,
+        Some(0.0)
+    );
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, Method, StatusCode};
+use rest_api_axum::config::Config;
+use rest_api_axum::extract::MAX_BODY_LEN;
+use rest_api_axum::repository::memory::MemoryStore;
+use serde_json::{json, Value};
+
+use common::{app, app_with, get, request, send, sqlite_app, Response, TestApp};
+
+/// Creates a task with the `Idempotency-Key` header set to `key`.
+async fn create(app: &TestApp, key: &str, body: Value) -> Response {
+    let mut request = request(Method::POST, "/task", Some(body));
+    request
+        .headers_mut()
+        .insert("idempotency-key", key.parse().unwrap());
+    send(app, request).await
+}
+
+async fn count(app: &TestApp) -> u64 {
+    get(app, "/tasks").await.body["total"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn retries_replay_the_first_response() {
+    for app in [app().await, sqlite_app(true).await] {
+        let first = create(&app, "abc", json!({"task": "Buy milk"})).await;
+        assert_eq!(first.status, StatusCode::CREATED);
+        assert!(first.headers.get("idempotent-replayed").is_none());
+
+        let retry = create(&app, "abc", json!({"task": "Buy milk"})).await;
+
+        assert_eq!(retry.status, StatusCode::CREATED);
+        assert_eq!(retry.headers["idempotent-replayed"], "true");
+        assert_eq!(
+            retry.headers[header::CONTENT_TYPE],
+            first.headers[header::CONTENT_TYPE]
+        );
+        assert_eq!(retry.body, first.body);
+        assert_eq!(count(&app).await, 1);
+    }
+}
+
+#[tokio::test]
+async fn reusing_a_key_for_another_request_is_rejected() {
+    for app in [app().await, sqlite_app(true).await] {
+        create(&app, "abc", json!({"task": "Buy milk"})).await;
+
+        let response = create(&app, "abc", json!({"task": "Buy bread"})).await;
+
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.body["type"], "/problems/unprocessable-entity");
+        assert_eq!(count(&app).await, 1);
+    }
+}
+
+#[tokio::test]
+async fn keys_belong_to_their_user() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    create(&alice, "abc", json!({"task": "Buy milk"})).await;
+
+    let response = create(&bob, "abc", json!({"task": "Buy bread"})).await;
+
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert!(response.headers.get("idempotent-replayed").is_none());
+    assert_eq!(count(&bob).await, 1);
+}
+
+#[tokio::test]
+async fn requests_without_a_key_are_not_deduplicated() {
+    let app = app().await;
+    for _ in 0..2 {
+        let response = send(
+            &app,
+            request(Method::POST, "/task", Some(json!({"task": "Buy milk"}))),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::CREATED);
+    }
+
+    assert_eq!(count(&app).await, 2);
+}
+
+#[tokio::test]
+async fn client_errors_are_replayed() {
+    let app = app().await;
+    let first = create(&app, "abc", json!({"task": ""})).await;
+    assert_eq!(first.status, StatusCode::BAD_REQUEST);
+
+    let retry = create(&app, "abc", json!({"task": ""})).await;
+
+    assert_eq!(retry.status, StatusCode::BAD_REQUEST);
+    assert_eq!(retry.headers["idempotent-replayed"], "true");
+    assert_eq!(retry.body, first.body);
+}
+
+#[tokio::test]
+async fn invalid_keys_are_rejected() {
+    let app = app().await;
+    let long = "k".repeat(256);
+    for key in ["", long.as_str()] {
+        let response = create(&app, key, json!({"task": "Buy milk"})).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(count(&app).await, 0);
+}
+
+#[tokio::test]
+async fn bodies_over_the_limit_are_rejected() {
+    let app = app().await;
+    let task = "a".repeat(MAX_BODY_LEN);
+
+    let response = create(&app, "abc", json!({ "task": task })).await;
+
+    assert_eq!(response.status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        response.headers[header::CONTENT_TYPE],
+        "application/problem+json"
+    );
+    assert_eq!(response.body["type"], "/problems/payload-too-large");
+    // The key was not claimed.
+    let response = create(&app, "abc", json!({"task": "Buy milk"})).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(count(&app).await, 1);
+}
+
+#[tokio::test]
+async fn expired_keys_can_be_reused() {
+    let config = Config {
+        idempotency_ttl: Some(Duration::from_millis(100)),
+        ..Config::default()
+    };
+    let app = app_with(config, Arc::new(MemoryStore::default())).await;
+    create(&app, "abc", json!({"task": "Buy milk"})).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let response = create(&app, "abc", json!({"task": "Buy bread"})).await;
+
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.body["task"], "Buy bread");
+    assert_eq!(count(&app).await, 2);
+}
+
+#[tokio::test]
+async fn keys_are_ignored_when_disabled() {
+    let config = Config {
+        idempotency_ttl: None,
+        ..Config::default()
+    };
+    let app = app_with(config, Arc::new(MemoryStore::default())).await;
+    create(&app, "abc", json!({"task": "Buy milk"})).await;
+
+    let response = create(&app, "abc", json!({"task": "Buy milk"})).await;
+
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert!(response.headers.get("idempotent-replayed").is_none());
+    assert_eq!(count(&app).await, 2);
+}
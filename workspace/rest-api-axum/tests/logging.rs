@@ -0,0 +1,128 @@
+mod common;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use rest_api_axum::logging::{self, LogFormat};
+use serde_json::{json, Value};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::EnvFilter;
+
+use common::{app, request, send, sqlite_app};
+
+/// Log lines written to memory, for a test to read back.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<Value> {
+        let buffer = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buffer)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn request_ids_are_generated_and_echoed() {
+    let app = app().await;
+
+    let response = send(&app, request(Method::GET, "/tasks", None)).await;
+
+    let id = response.headers["x-request-id"].to_str().unwrap();
+    assert_eq!(id.len(), 36, "{id}");
+    let other = send(&app, request(Method::GET, "/tasks", None)).await;
+    assert_ne!(other.headers["x-request-id"], id);
+}
+
+#[tokio::test]
+async fn request_ids_sent_by_clients_are_kept() {
+    let app = app().await;
+
+    for (uri, status) in [
+        ("/tasks", StatusCode::OK),
+        ("/task/42", StatusCode::NOT_FOUND),
+        ("/nowhere", StatusCode::NOT_FOUND),
+    ] {
+        let request = Request::get(uri)
+            .header("x-request-id", "client-chosen")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = send(&app, request).await;
+
+        assert_eq!(response.status, status, "{uri}");
+        assert_eq!(response.headers["x-request-id"], "client-chosen", "{uri}");
+    }
+    let response = send(
+        &app.anonymous(),
+        Request::get("/tasks")
+            .header("x-request-id", "anonymous")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers["x-request-id"], "anonymous");
+}
+
+#[tokio::test]
+async fn json_logs_tell_the_request_and_query_of_each_line() {
+    let app = sqlite_app(true).await;
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(EnvFilter::new("rest_api_axum=trace,tower_http=debug"))
+        .with(logging::layer(LogFormat::Json, move || writer.clone()));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let mut request = request(Method::POST, "/task", Some(json!({"task": "Buy milk"})));
+    request
+        .headers_mut()
+        .insert("x-request-id", "req-123".parse().unwrap());
+    let response = send(&app, request).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+
+    let lines = capture.lines();
+    let request_span = json!({
+        "name": "request",
+        "method": "POST",
+        "uri": "/task",
+        "route": "/task",
+        "request_id": "req-123",
+    });
+    // `TraceLayer` logs the response within the span of the request.
+    assert!(lines.iter().any(|line| {
+        line["span"] == request_span && line["fields"]["message"] == "finished processing request"
+    }));
+    // And the queries of the handler within a span of their own.
+    let query = lines
+        .iter()
+        .find(|line| line["fields"]["message"] == "finished query")
+        .expect("no query was logged");
+    assert_eq!(query["spans"][0], request_span);
+    assert_eq!(query["span"]["name"], "query");
+    assert_eq!(query["span"]["handler"], "POST /task");
+    assert_eq!(query["span"]["operation"], "create");
+}
+
+#[test]
+fn log_formats_are_parsed() {
+    assert_eq!("json".parse(), Ok(LogFormat::Json));
+    assert_eq!("Text".parse(), Ok(LogFormat::Text));
+    assert!("xml".parse::<LogFormat>().is_err());
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::{app, get, open, request};
+
+/// Copy of the spec checked in for the clients generated from it.
+const SPEC_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+/// Fails whenever a handler or model changes the spec, until `openapi.json`
+/// is refreshed by running the tests with `UPDATE_OPENAPI=1`.
+#[tokio::test]
+async fn checked_in_spec_is_up_to_date() {
+    let app = app().await;
+
+    let response = get(&app, "/openapi.json").await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        let pretty = serde_json::to_string_pretty(&response.body).unwrap();
+        std::fs::write(SPEC_FILE, pretty + "\n").unwrap();
+    }
+    let checked_in: Value =
+        serde_json::from_str(&std::fs::read_to_string(SPEC_FILE).unwrap()).unwrap();
+    assert!(
+        response.body == checked_in,
+        "openapi.json is out of date, run the tests with UPDATE_OPENAPI=1 to refresh it"
+    );
+}
+
+/// Every documented operation reaches a handler, instead of the router's own
+/// 404 (without a body) or 405.
+#[tokio::test]
+async fn every_documented_operation_is_routed() {
+    let app = app().await;
+    let spec = get(&app, "/openapi.json").await.body;
+
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(!paths.is_empty());
+    for (path, operations) in paths {
+        let uri = path.replace("{id}", "1");
+        for method in operations.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let body = [Method::POST, Method::PUT, Method::PATCH]
+                .contains(&method)
+                .then(|| json!({}));
+
+            // Some bodies are endless streams, so only read those of 404s.
+            let response = open(&app, request(method.clone(), &uri, body)).await;
+            let status = response.status();
+
+            assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{method} {path}");
+            if status == StatusCode::NOT_FOUND {
+                let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+                assert!(!body.is_empty(), "{method} {path} is not routed");
+            }
+        }
+    }
+}
+
+/// Every `$ref` points at a schema of `components`.
+#[tokio::test]
+async fn every_schema_reference_resolves() {
+    fn check(value: &Value, schemas: &serde_json::Map<String, Value>) {
+        match value {
+            Value::Object(object) => {
+                if let Some(Value::String(reference)) = object.get("$ref") {
+                    let name = reference.trim_start_matches("#/components/schemas/");
+                    assert!(schemas.contains_key(name), "dangling {reference}");
+                }
+                object.values().for_each(|value| check(value, schemas));
+            }
+            Value::Array(values) => values.iter().for_each(|value| check(value, schemas)),
+            _ => {}
+        }
+    }
+
+    let app = app().await;
+    let spec = get(&app, "/openapi.json").await.body;
+
+    check(&spec, spec["components"]["schemas"].as_object().unwrap());
+}
+
+#[tokio::test]
+async fn docs_page_is_served() {
+    let app = app().await.anonymous();
+
+    let response = get(&app, "/docs/").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.as_str().unwrap().contains("swagger-ui"));
+}
@@ -0,0 +1,459 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::trash;
+use serde_json::{json, Value};
+
+use common::{
+    app, app_on, create_task, delete, get, post, put, request, send, sqlite_app, sqlite_pool,
+    TestApp,
+};
+
+/// The ids of the tasks listed at `uri`.
+async fn ids(app: &TestApp, uri: &str) -> Vec<i64> {
+    let response = get(app, uri).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["id"].as_i64().unwrap())
+        .collect()
+}
+
+/// The names in a list of projects or tags.
+fn names(body: &Value) -> Vec<&str> {
+    body.as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["name"].as_str().unwrap())
+        .collect()
+}
+
+async fn create(app: &TestApp, uri: &str, body: Value) -> i64 {
+    let response = post(app, uri, body).await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+    response.body["id"].as_i64().unwrap()
+}
+
+async fn assert_projects_are_managed(app: TestApp) {
+    let home = create(&app, "/projects", json!({"name": "Home"})).await;
+    create(&app, "/projects", json!({"name": "Errands"})).await;
+
+    assert_eq!(
+        names(&get(&app, "/projects").await.body),
+        ["Errands", "Home"]
+    );
+    let response = get(&app, &format!("/projects/{home}")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["name"], "Home");
+    assert!(response.body.get("owner_id").is_none());
+
+    let response = put(&app, &format!("/projects/{home}"), json!({"name": "House"})).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["name"], "House");
+
+    let response = post(&app, "/projects", json!({"name": "Errands"})).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    let response = put(
+        &app,
+        &format!("/projects/{home}"),
+        json!({"name": "Errands"}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+
+    let response = delete(&app, &format!("/projects/{home}")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(names(&get(&app, "/projects").await.body), ["Errands"]);
+    for response in [
+        get(&app, &format!("/projects/{home}")).await,
+        put(&app, &format!("/projects/{home}"), json!({"name": "Home"})).await,
+        delete(&app, &format!("/projects/{home}")).await,
+    ] {
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn projects_are_managed() {
+    assert_projects_are_managed(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_projects_are_managed() {
+    assert_projects_are_managed(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn invalid_names_are_rejected() {
+    let app = app().await;
+
+    for (uri, name) in [
+        ("/projects", "".to_owned()),
+        ("/projects", "x".repeat(256)),
+        ("/tags", " ".to_owned()),
+        ("/tags", "x".repeat(65)),
+    ] {
+        let response = post(&app, uri, json!({ "name": name })).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(response.body["errors"][0]["field"], "name");
+    }
+}
+
+async fn assert_tasks_are_grouped_in_projects(app: TestApp) {
+    let home = create(&app, "/projects", json!({"name": "Home"})).await;
+    let loose = create_task(&app, "Loose").await;
+    let dishes = create(&app, "/task", json!({"task": "Dishes", "project_id": home})).await;
+    let laundry = create(
+        &app,
+        &format!("/projects/{home}/tasks"),
+        json!({"task": "Laundry", "project_id": 0}),
+    )
+    .await;
+
+    assert_eq!(
+        ids(&app, &format!("/projects/{home}/tasks")).await,
+        [dishes, laundry]
+    );
+    assert_eq!(
+        ids(&app, &format!("/tasks?project={home}")).await,
+        [dishes, laundry]
+    );
+
+    let response = put(&app, &format!("/task/{dishes}"), json!({"task": "Dishes"})).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body["project_id"].is_null());
+    let body = json!({"task": "Loose", "project_id": home});
+    assert_eq!(
+        put(&app, &format!("/task/{loose}"), body).await.body["project_id"],
+        home
+    );
+    assert_eq!(
+        ids(&app, &format!("/projects/{home}/tasks")).await,
+        [loose, laundry]
+    );
+
+    let missing = home + 100;
+    assert_eq!(
+        get(&app, &format!("/projects/{missing}/tasks"))
+            .await
+            .status,
+        StatusCode::NOT_FOUND
+    );
+    let response = post(
+        &app,
+        &format!("/projects/{missing}/tasks"),
+        json!({"task": "X"}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tasks_are_grouped_in_projects() {
+    assert_tasks_are_grouped_in_projects(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_tasks_are_grouped_in_projects() {
+    assert_tasks_are_grouped_in_projects(sqlite_app(true).await).await;
+}
+
+async fn assert_deleting_a_project_purges_its_tasks(app: TestApp) {
+    let home = create(&app, "/projects", json!({"name": "Home"})).await;
+    let kept = create_task(&app, "Kept").await;
+    let dishes = create(&app, "/task", json!({"task": "Dishes", "project_id": home})).await;
+    let subtask = create(
+        &app,
+        &format!("/task/{dishes}/subtasks"),
+        json!({"task": "Dry"}),
+    )
+    .await;
+    let trashed = create(&app, "/task", json!({"task": "Old", "project_id": home})).await;
+    delete(&app, &format!("/task/{trashed}")).await;
+
+    delete(&app, &format!("/projects/{home}")).await;
+
+    assert_eq!(ids(&app, "/tasks").await, [kept]);
+    assert!(ids(&app, "/tasks/trash").await.is_empty());
+    for id in [dishes, subtask, trashed] {
+        let history = get(&app, &format!("/task/{id}/history")).await;
+        assert_eq!(history.body["items"][0]["action"], "purge", "{id}");
+    }
+}
+
+#[tokio::test]
+async fn deleting_a_project_purges_its_tasks() {
+    assert_deleting_a_project_purges_its_tasks(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_deleting_a_project_purges_its_tasks() {
+    assert_deleting_a_project_purges_its_tasks(sqlite_app(true).await).await;
+}
+
+async fn assert_tasks_are_tagged(app: TestApp) {
+    let urgent = create(&app, "/tags", json!({"name": "urgent"})).await;
+    let later = create(&app, "/tags", json!({"name": "later"})).await;
+    let milk = create_task(&app, "Buy milk").await;
+    let bread = create_task(&app, "Buy bread").await;
+
+    let response = put(&app, &format!("/task/{milk}/tags/{urgent}"), json!(null)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(names(&response.body), ["urgent"]);
+    // Tagging twice changes nothing.
+    put(&app, &format!("/task/{milk}/tags/{urgent}"), json!(null)).await;
+    put(&app, &format!("/task/{milk}/tags/{later}"), json!(null)).await;
+    put(&app, &format!("/task/{bread}/tags/{urgent}"), json!(null)).await;
+
+    let tags = get(&app, &format!("/task/{milk}/tags")).await;
+    assert_eq!(names(&tags.body), ["later", "urgent"]);
+    assert_eq!(ids(&app, "/tasks?tag=urgent").await, [milk, bread]);
+    assert_eq!(ids(&app, "/tasks?tag=later").await, [milk]);
+    assert!(ids(&app, "/tasks?tag=none").await.is_empty());
+    assert_eq!(get(&app, &format!("/task/{milk}")).await.body["version"], 1);
+
+    let response = delete(&app, &format!("/task/{milk}/tags/{later}")).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(names(&response.body), ["urgent"]);
+    let response = delete(&app, &format!("/task/{milk}/tags/{later}")).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = put(&app, &format!("/tags/{urgent}"), json!({"name": "now"})).await;
+    assert_eq!(response.body["name"], "now");
+    assert_eq!(ids(&app, "/tasks?tag=now").await, [milk, bread]);
+
+    assert_eq!(
+        delete(&app, &format!("/tags/{urgent}")).await.status,
+        StatusCode::OK
+    );
+    assert_eq!(names(&get(&app, "/tags").await.body), ["later"]);
+    assert!(names(&get(&app, &format!("/task/{milk}/tags")).await.body).is_empty());
+    assert_eq!(ids(&app, "/tasks").await, [milk, bread]);
+}
+
+#[tokio::test]
+async fn tasks_are_tagged() {
+    assert_tasks_are_tagged(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_tasks_are_tagged() {
+    assert_tasks_are_tagged(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn tagging_needs_the_task_and_the_tag() {
+    let app = app().await;
+    let tag = create(&app, "/tags", json!({"name": "urgent"})).await;
+    let task = create_task(&app, "Buy milk").await;
+
+    for (uri, detail) in [
+        (format!("/task/{task}/tags/{}", tag + 1), "tag"),
+        (format!("/task/{}/tags/{tag}", task + 1), "task"),
+    ] {
+        let response = put(&app, &uri, json!(null)).await;
+
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{uri}");
+        assert!(
+            response.body["detail"].as_str().unwrap().contains(detail),
+            "{}",
+            response.body
+        );
+    }
+    assert_eq!(
+        get(&app, &format!("/task/{}/tags", task + 1)).await.status,
+        StatusCode::NOT_FOUND
+    );
+    let response = post(&app, "/tags", json!({"name": "urgent"})).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+}
+
+async fn assert_subtasks_follow_their_parent(app: TestApp) {
+    let home = create(&app, "/projects", json!({"name": "Home"})).await;
+    let parent = create(&app, "/task", json!({"task": "Clean", "project_id": home})).await;
+    let child = create(
+        &app,
+        &format!("/task/{parent}/subtasks"),
+        json!({"task": "Floor"}),
+    )
+    .await;
+    let grandchild = create(
+        &app,
+        &format!("/task/{child}/subtasks"),
+        json!({"task": "Mop", "parent_id": 0}),
+    )
+    .await;
+    let sibling = create(
+        &app,
+        "/task",
+        json!({"task": "Windows", "parent_id": parent}),
+    )
+    .await;
+
+    let response = get(&app, &format!("/task/{child}")).await;
+    assert_eq!(response.body["parent_id"], parent);
+    assert_eq!(response.body["project_id"], home);
+    assert_eq!(
+        ids(&app, &format!("/task/{parent}/subtasks")).await,
+        [child, sibling]
+    );
+    assert_eq!(
+        ids(&app, &format!("/tasks?parent={child}")).await,
+        [grandchild]
+    );
+
+    // A subtask trashed earlier stays in the trash when its parent comes back.
+    delete(&app, &format!("/task/{sibling}")).await;
+    delete(&app, &format!("/task/{parent}")).await;
+    assert!(ids(&app, "/tasks").await.is_empty());
+    assert_eq!(
+        ids(&app, "/tasks/trash").await,
+        [parent, child, grandchild, sibling]
+    );
+
+    let uri = format!("/task/{child}/restore");
+    let response = send(&app, request(Method::POST, &uri, None)).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+
+    let uri = format!("/task/{parent}/restore");
+    let response = send(&app, request(Method::POST, &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(ids(&app, "/tasks").await, [parent, child, grandchild]);
+    assert_eq!(ids(&app, "/tasks/trash").await, [sibling]);
+
+    delete(&app, &format!("/task/{child}?purge=true")).await;
+    assert_eq!(ids(&app, "/tasks").await, [parent]);
+    let history = get(&app, &format!("/task/{grandchild}/history")).await;
+    assert_eq!(history.body["items"][0]["action"], "purge");
+
+    let missing = grandchild + 100;
+    assert_eq!(
+        get(&app, &format!("/task/{missing}/subtasks")).await.status,
+        StatusCode::NOT_FOUND
+    );
+    let response = post(
+        &app,
+        &format!("/task/{missing}/subtasks"),
+        json!({"task": "X"}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn subtasks_follow_their_parent() {
+    assert_subtasks_follow_their_parent(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_subtasks_follow_their_parent() {
+    assert_subtasks_follow_their_parent(sqlite_app(true).await).await;
+}
+
+async fn assert_references_must_exist(app: TestApp) {
+    let trashed = create_task(&app, "Trashed").await;
+    delete(&app, &format!("/task/{trashed}")).await;
+    let task = create_task(&app, "Task").await;
+
+    for (body, field) in [
+        (json!({"task": "X", "project_id": 42}), "project_id"),
+        (json!({"task": "X", "parent_id": 42}), "parent_id"),
+        (json!({"task": "X", "parent_id": trashed}), "parent_id"),
+    ] {
+        let response = post(&app, "/task", body).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+        assert_eq!(response.body["errors"][0]["field"], field);
+    }
+    let response = put(
+        &app,
+        &format!("/task/{task}"),
+        json!({"task": "X", "project_id": 42}),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["errors"][0]["field"], "project_id");
+    assert_eq!(ids(&app, "/tasks").await, [task]);
+}
+
+#[tokio::test]
+async fn references_must_exist() {
+    assert_references_must_exist(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_references_must_exist() {
+    assert_references_must_exist(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn projects_and_tags_only_belong_to_their_owner() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let project = create(&bob, "/projects", json!({"name": "Bob's"})).await;
+    let tag = create(&bob, "/tags", json!({"name": "bob"})).await;
+    let task = create_task(&bob, "Bob's task").await;
+    put(&bob, &format!("/task/{task}/tags/{tag}"), json!(null)).await;
+
+    assert!(names(&get(&alice, "/projects").await.body).is_empty());
+    assert!(names(&get(&alice, "/tags").await.body).is_empty());
+    assert_eq!(
+        get(&alice, &format!("/projects/{project}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        delete(&alice, &format!("/tags/{tag}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert!(ids(&alice, "/tasks?tag=bob").await.is_empty());
+    let body = json!({"task": "Mine", "project_id": project});
+    assert_eq!(
+        post(&alice, "/task", body).await.status,
+        StatusCode::BAD_REQUEST
+    );
+    let body = json!({"task": "Mine", "parent_id": task});
+    assert_eq!(
+        post(&alice, "/task", body).await.status,
+        StatusCode::BAD_REQUEST
+    );
+
+    // The same names are free for everybody else.
+    create(&alice, "/projects", json!({"name": "Bob's"})).await;
+    create(&alice, "/tags", json!({"name": "bob"})).await;
+    assert_eq!(ids(&bob, "/tasks?tag=bob").await, [task]);
+}
+
+#[tokio::test]
+async fn sqlite_purging_the_trash_records_every_subtask() {
+    let store = SqliteStore::new(sqlite_pool(true).await);
+    let store: Arc<dyn Store> = Arc::new(store);
+    let app = app_on(store.clone()).await;
+    let parent = create_task(&app, "Clean").await;
+    let child = create(
+        &app,
+        &format!("/task/{parent}/subtasks"),
+        json!({"task": "Floor"}),
+    )
+    .await;
+    delete(&app, &format!("/task/{parent}")).await;
+    // SQLite compares times to the millisecond.
+    tokio::time::sleep(Duration::from_millis(5)).await;
+
+    assert_eq!(
+        trash::purge_expired(&*store, Duration::ZERO).await.unwrap(),
+        2
+    );
+
+    for id in [parent, child] {
+        let history = get(&app, &format!("/task/{id}/history")).await;
+        assert_eq!(history.body["items"][0]["action"], "purge", "{id}");
+    }
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ConnectInfo;
+use axum::http::{header, Method, StatusCode};
+use rest_api_axum::config::Config;
+use rest_api_axum::models::rate_limit::{Limit, Rule};
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::state::AppState;
+use serde_json::json;
+
+use common::{app_with, get, post, request, send, sqlite_pool, TestApp};
+
+/// `limit` by default and `routes`, leaving out the requests of [`app`].
+fn config(limit: &str, routes: &[&str]) -> Config {
+    let exempt = ["/auth/register=0", "/auth/login=0"];
+    Config {
+        rate_limit: Some(limit.parse().unwrap()),
+        rate_limit_routes: routes
+            .iter()
+            .chain(&exempt)
+            .map(|rule| rule.parse().unwrap())
+            .collect(),
+        ..Config::default()
+    }
+}
+
+async fn app(config: Config) -> TestApp {
+    app_with(config, Arc::new(MemoryStore::default())).await
+}
+
+/// The status of `GET /tasks`, sent from `ip` without credentials if any.
+async fn list_from(app: &TestApp, ip: Option<[u8; 4]>) -> StatusCode {
+    let mut request = request(Method::GET, "/tasks", None);
+    if let Some(ip) = ip {
+        let addr = SocketAddr::from((ip, 40000));
+        request.extensions_mut().insert(ConnectInfo(addr));
+    }
+    let app = match ip {
+        Some(_) => app.anonymous(),
+        None => app.clone(),
+    };
+    send(&app, request).await.status
+}
+
+#[tokio::test]
+async fn requests_over_the_limit_are_turned_down() {
+    let app = app(config("3/60", &[])).await;
+
+    for remaining in ["2", "1", "0"] {
+        let response = get(&app, "/tasks").await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers["ratelimit-limit"], "3");
+        assert_eq!(response.headers["ratelimit-remaining"], remaining);
+        assert_eq!(response.headers["ratelimit-policy"], "3;w=60");
+    }
+
+    let response = get(&app, "/tasks").await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.body["type"], "/problems/too-many-requests");
+    assert_eq!(response.headers[header::RETRY_AFTER], "20");
+    assert_eq!(response.headers["ratelimit-remaining"], "0");
+    assert_eq!(response.headers["ratelimit-reset"], "60");
+}
+
+#[tokio::test]
+async fn tokens_come_back_over_time() {
+    let app = app(config("1/1", &[])).await;
+    assert_eq!(get(&app, "/tasks").await.status, StatusCode::OK);
+    let response = get(&app, "/tasks").await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers[header::RETRY_AFTER], "1");
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(get(&app, "/tasks").await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn routes_have_limits_of_their_own() {
+    let app = app(config(
+        "100/60",
+        &["POST /task=2/60", "/task/:id=1/60", "GET /tasks=0"],
+    ))
+    .await;
+
+    for _ in 0..2 {
+        let response = post(&app, "/task", json!({"task": "Buy milk"})).await;
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(response.headers["ratelimit-limit"], "2");
+    }
+    let response = post(&app, "/task", json!({"task": "Buy milk"})).await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other routes and methods are not affected.
+    assert_eq!(get(&app, "/task/1").await.status, StatusCode::OK);
+    assert_eq!(
+        get(&app, "/task/2").await.status,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    let response = get(&app, "/tasks/trash").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.headers["ratelimit-limit"], "100");
+    let response = get(&app, "/tasks").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("ratelimit-limit"));
+}
+
+#[tokio::test]
+async fn clients_are_limited_separately() {
+    let alice = app(config("100/60", &["GET /tasks=1/60"])).await;
+    let bob = alice.register("bob@example.com").await;
+
+    assert_eq!(list_from(&alice, None).await, StatusCode::OK);
+    assert_eq!(list_from(&alice, None).await, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(list_from(&bob, None).await, StatusCode::OK);
+
+    // Anonymous clients are told apart by their address.
+    let first = list_from(&alice, Some([10, 0, 0, 1])).await;
+    assert_eq!(first, StatusCode::UNAUTHORIZED);
+    let again = list_from(&alice, Some([10, 0, 0, 1])).await;
+    assert_eq!(again, StatusCode::TOO_MANY_REQUESTS);
+    let other = list_from(&alice, Some([10, 0, 0, 2])).await;
+    assert_eq!(other, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn limits_can_be_disabled() {
+    let config = Config {
+        rate_limit: None,
+        rate_limit_routes: Vec::new(),
+        ..Config::default()
+    };
+    let app = app(config).await;
+
+    let response = get(&app, "/tasks").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(!response.headers.contains_key("ratelimit-limit"));
+}
+
+#[tokio::test]
+async fn sqlite_instances_share_their_limits() {
+    let store: Arc<dyn Store> = Arc::new(SqliteStore::new(sqlite_pool(true).await));
+    let config = Config {
+        rate_limit_shared: true,
+        ..config("2/60", &[])
+    };
+    let first = app_with(config.clone(), store.clone()).await;
+    let second = TestApp {
+        router: rest_api_axum::app(AppState::new(config, store)),
+        token: first.token.clone(),
+    };
+
+    assert_eq!(get(&first, "/tasks").await.status, StatusCode::OK);
+    assert_eq!(get(&second, "/tasks").await.status, StatusCode::OK);
+    assert_eq!(
+        get(&first, "/tasks").await.status,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[test]
+fn rules_are_parsed() {
+    let rule: Rule = " POST  /task/:id = 10/60 ".parse().unwrap();
+    assert_eq!(rule.method, Some(Method::POST));
+    assert_eq!(rule.path, "/task/:id");
+    assert_eq!(
+        rule.limit,
+        Some(Limit {
+            requests: 10,
+            period: Duration::from_secs(60)
+        })
+    );
+    let rule: Rule = "/tasks=0".parse().unwrap();
+    assert_eq!((rule.method, rule.limit), (None, None));
+
+    for invalid in [
+        "/tasks",
+        "tasks=1/60",
+        "/tasks=1",
+        "/tasks=0/60",
+        "/tasks=1/0",
+    ] {
+        assert!(invalid.parse::<Rule>().is_err(), "{invalid}");
+    }
+}
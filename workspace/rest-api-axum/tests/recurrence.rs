@@ -0,0 +1,405 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use chrono::{DateTime, Utc};
+use rest_api_axum::models::recurrence::Recurrence;
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::scheduler;
+use rest_api_axum::shutdown::Workers;
+use serde_json::{json, Value};
+
+use common::{app, app_on, delete, get, post, put, send, sqlite_pool, TestApp};
+
+fn time(s: &str) -> DateTime<Utc> {
+    s.parse().unwrap()
+}
+
+/// The active tasks of `app`, by id.
+async fn tasks(app: &TestApp) -> Vec<Value> {
+    get(app, "/tasks").await.body["items"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+/// Marks the task `id` as done, returning it.
+async fn complete(app: &TestApp, id: &Value) -> Value {
+    let request = Request::patch(format!("/task/{id}"))
+        .header(header::CONTENT_TYPE, "application/merge-patch+json")
+        .body(Body::from(json!({"status": "done"}).to_string()))
+        .unwrap();
+    let response = send(app, request).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body
+}
+
+#[test]
+fn rules_are_written_back_in_canonical_form() {
+    for (rule, canonical) in [
+        ("FREQ=DAILY", "FREQ=DAILY"),
+        (
+            "freq=weekly;interval=1;byday=mo,th,mo",
+            "FREQ=WEEKLY;BYDAY=MO,TH",
+        ),
+        (
+            "RRULE:FREQ=MONTHLY;BYDAY=-1FR,+1MO;INTERVAL=3",
+            "FREQ=MONTHLY;INTERVAL=3;BYDAY=-1FR,1MO",
+        ),
+    ] {
+        let recurrence: Recurrence = rule.parse().unwrap();
+
+        assert_eq!(recurrence.to_string(), canonical, "{rule}");
+    }
+}
+
+#[test]
+fn unsupported_rules_are_rejected() {
+    for rule in [
+        "",
+        "FREQ=YEARLY",
+        "INTERVAL=2",
+        "FREQ=DAILY;FREQ=WEEKLY",
+        "FREQ=DAILY;INTERVAL=0",
+        "FREQ=DAILY;COUNT=3",
+        "FREQ=WEEKLY;BYDAY=XX",
+        // Ranks only make sense within a month.
+        "FREQ=WEEKLY;BYDAY=2TU",
+        "FREQ=MONTHLY;BYDAY=6MO",
+        "FREQ=MONTHLY;BYDAY=-128MO",
+        "FREQ=WEEKLY;BYDAY=éMO",
+    ] {
+        assert!(rule.parse::<Recurrence>().is_err(), "{rule}");
+    }
+}
+
+#[test]
+fn next_occurrences_follow_the_rule() {
+    // A Sunday.
+    let start = "2030-01-06T09:00:00Z";
+    for (rule, after, next) in [
+        ("FREQ=DAILY", start, "2030-01-07T09:00:00Z"),
+        (
+            "FREQ=DAILY;INTERVAL=3",
+            "2030-01-10T12:00:00Z",
+            "2030-01-12T09:00:00Z",
+        ),
+        (
+            "FREQ=DAILY;BYDAY=MO,FR",
+            "2030-01-07T10:00:00Z",
+            "2030-01-11T09:00:00Z",
+        ),
+        ("FREQ=WEEKLY", start, "2030-01-13T09:00:00Z"),
+        ("FREQ=WEEKLY;BYDAY=MO,TH", start, "2030-01-07T09:00:00Z"),
+        (
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=SU,MO",
+            start,
+            "2030-01-14T09:00:00Z",
+        ),
+        (
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU",
+            "2030-01-15T09:00:00Z",
+            "2030-01-29T09:00:00Z",
+        ),
+        (
+            "FREQ=MONTHLY",
+            "2030-03-01T00:00:00Z",
+            "2030-03-06T09:00:00Z",
+        ),
+        ("FREQ=MONTHLY;BYDAY=1MO", start, "2030-01-07T09:00:00Z"),
+        (
+            "FREQ=MONTHLY;BYDAY=1MO",
+            "2030-01-07T09:00:00Z",
+            "2030-02-04T09:00:00Z",
+        ),
+        ("FREQ=MONTHLY;BYDAY=-1FR", start, "2030-01-25T09:00:00Z"),
+        (
+            "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR",
+            "2030-01-26T00:00:00Z",
+            "2030-03-29T09:00:00Z",
+        ),
+    ] {
+        let recurrence: Recurrence = rule.parse().unwrap();
+
+        let found = recurrence.next_after(time(start), time(after));
+
+        assert_eq!(found, Some(time(next)), "{rule} after {after}");
+    }
+}
+
+#[test]
+fn months_without_the_day_are_skipped() {
+    let recurrence: Recurrence = "FREQ=MONTHLY".parse().unwrap();
+    let start = time("2030-01-31T08:00:00Z");
+
+    assert_eq!(
+        recurrence.next_after(start, start),
+        Some(time("2030-03-31T08:00:00Z"))
+    );
+}
+
+async fn assert_done_tasks_recur_once(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    let project = post(&app, "/projects", json!({"name": "Work"})).await;
+    let tag = post(&app, "/tags", json!({"name": "report"})).await;
+    let task = post(
+        &app,
+        "/task",
+        json!({
+            "task": "Weekly report",
+            "priority": 2,
+            "due_at": "2030-01-06T09:00:00Z",
+            "project_id": project.body["id"],
+            "recurrence": "FREQ=WEEKLY;BYDAY=MO,TH",
+        }),
+    )
+    .await;
+    assert_eq!(task.status, StatusCode::CREATED, "{}", task.body);
+    assert_eq!(task.body["recurrence"], "FREQ=WEEKLY;BYDAY=MO,TH");
+    let id = &task.body["id"];
+    put(
+        &app,
+        &format!("/task/{id}/tags/{}", tag.body["id"]),
+        json!({}),
+    )
+    .await;
+
+    // Not done yet.
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        0
+    );
+    complete(&app, id).await;
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        1
+    );
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        0
+    );
+
+    let listed = tasks(&app).await;
+    assert_eq!(listed.len(), 2);
+    let next = &listed[1];
+    assert_eq!(next["task"], "Weekly report");
+    assert_eq!(next["status"], "todo");
+    assert_eq!(next["priority"], 2);
+    assert_eq!(next["due_at"], "2030-01-07T09:00:00Z");
+    assert_eq!(next["project_id"], project.body["id"]);
+    assert_eq!(next["recurrence"], "FREQ=WEEKLY;BYDAY=MO,TH");
+    let next_id = &next["id"];
+    let tags = get(&app, &format!("/task/{next_id}/tags")).await;
+    assert_eq!(tags.body[0]["name"], "report");
+    let history = get(&app, &format!("/task/{next_id}/history")).await;
+    assert_eq!(history.body["items"][0]["action"], "create");
+    assert!(history.body["items"][0]["actor_id"].is_null());
+
+    // Done again, the task does not recur twice.
+    put(
+        &app,
+        &format!("/task/{id}"),
+        json!({"task": "Weekly report", "recurrence": "FREQ=WEEKLY;BYDAY=MO,TH"}),
+    )
+    .await;
+    complete(&app, id).await;
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        0
+    );
+
+    // Its occurrence does, on the next day of the rule.
+    complete(&app, next_id).await;
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        1
+    );
+    assert_eq!(tasks(&app).await[2]["due_at"], "2030-01-10T09:00:00Z");
+}
+
+#[tokio::test]
+async fn done_tasks_recur_once() {
+    assert_done_tasks_recur_once(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_done_tasks_recur_once() {
+    assert_done_tasks_recur_once(Arc::new(SqliteStore::new(sqlite_pool(true).await))).await;
+}
+
+#[tokio::test]
+async fn tasks_without_a_due_date_recur_from_their_completion() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let task = post(
+        &app,
+        "/task",
+        json!({"task": "Water plants", "recurrence": "FREQ=DAILY;INTERVAL=2"}),
+    )
+    .await;
+
+    let done = complete(&app, &task.body["id"]).await;
+    scheduler::create_next_occurrences(&*store).await.unwrap();
+
+    let completed_at = time(done["updated_at"].as_str().unwrap());
+    let next = &tasks(&app).await[1];
+    let due_at = time(next["due_at"].as_str().unwrap());
+    assert_eq!(due_at, completed_at + chrono::Duration::days(2));
+}
+
+#[tokio::test]
+async fn tasks_in_the_trash_or_not_recurring_do_not_recur() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let plain = post(&app, "/task", json!({"task": "Once", "status": "done"})).await;
+    let trashed = post(
+        &app,
+        "/task",
+        json!({"task": "Daily", "status": "done", "recurrence": "FREQ=DAILY"}),
+    )
+    .await;
+    delete(&app, &format!("/task/{}", trashed.body["id"])).await;
+
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        0
+    );
+
+    assert!(plain.body["recurrence"].is_null());
+    // Back from the trash, it recurs after all.
+    post(
+        &app,
+        &format!("/task/{}/restore", trashed.body["id"]),
+        json!({}),
+    )
+    .await;
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        1
+    );
+}
+
+async fn assert_tasks_past_a_batch_of_ended_ones_recur(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    // Their next occurrence would be past the last date there is.
+    let ended = json!({
+        "op": "create",
+        "task": {
+            "task": "Someday",
+            "status": "done",
+            "due_at": "+262100-01-01T09:00:00Z",
+            "recurrence": "FREQ=MONTHLY;INTERVAL=1000",
+        },
+    });
+    let response = post(&app, "/tasks/bulk", json!({"operations": vec![ended; 100]})).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    post(
+        &app,
+        "/task",
+        json!({"task": "Daily", "status": "done", "recurrence": "FREQ=DAILY"}),
+    )
+    .await;
+
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn tasks_past_a_batch_of_ended_ones_recur() {
+    assert_tasks_past_a_batch_of_ended_ones_recur(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_tasks_past_a_batch_of_ended_ones_recur() {
+    assert_tasks_past_a_batch_of_ended_ones_recur(Arc::new(SqliteStore::new(
+        sqlite_pool(true).await,
+    )))
+    .await;
+}
+
+#[tokio::test]
+async fn recurrences_can_be_changed_and_removed() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let task = post(
+        &app,
+        "/task",
+        json!({"task": "Call mom", "recurrence": "FREQ=WEEKLY"}),
+    )
+    .await;
+    let uri = format!("/task/{}", task.body["id"]);
+
+    let patch = |body: Value| {
+        Request::patch(&uri)
+            .header(header::CONTENT_TYPE, "application/merge-patch+json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let changed = send(&app, patch(json!({"recurrence": "freq=monthly"}))).await;
+    assert_eq!(changed.body["recurrence"], "FREQ=MONTHLY");
+    let kept = send(&app, patch(json!({"priority": 1}))).await;
+    assert_eq!(kept.body["recurrence"], "FREQ=MONTHLY");
+    let removed = send(&app, patch(json!({"recurrence": null, "status": "done"}))).await;
+    assert!(removed.body["recurrence"].is_null());
+
+    assert_eq!(
+        scheduler::create_next_occurrences(&*store).await.unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn invalid_rules_are_rejected() {
+    let app = app().await;
+
+    let response = post(
+        &app,
+        "/task",
+        json!({"task": "Taxes", "recurrence": "FREQ=YEARLY"}),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(tasks(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn the_scheduler_wakes_up_when_tasks_are_done() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let task = post(
+        &app,
+        "/task",
+        json!({"task": "Stand-up", "recurrence": "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR"}),
+    )
+    .await;
+    // Far longer than the test may take.
+    let mut workers = Workers::new();
+    workers.push(scheduler::start(
+        store,
+        Duration::from_secs(3600),
+        workers.stop_signal(),
+    ));
+
+    complete(&app, &task.body["id"]).await;
+
+    let recurred = async {
+        while tasks(&app).await.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), recurred)
+        .await
+        .expect("the task did not recur");
+    // Stops right away, rather than after the interval.
+    tokio::time::timeout(Duration::from_secs(5), workers.stop())
+        .await
+        .expect("the scheduler did not stop");
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use common::{app, create_task, get, sqlite_app, TestApp};
+
+/// The ids and snippets of the results of `GET /tasks/search`.
+async fn search(app: &TestApp, query: &str) -> Vec<(i64, String)> {
+    let response = get(app, &format!("/tasks/search?{query}")).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| {
+            assert!(hit["rank"].is_null());
+            let snippet = hit["snippet"].as_str().unwrap().to_owned();
+            (hit["task"]["id"].as_i64().unwrap(), snippet)
+        })
+        .collect()
+}
+
+async fn assert_search_matches_every_word(app: TestApp) {
+    let milk = create_task(&app, "Buy milk").await;
+    let bread = create_task(&app, "Buy bread and MILK").await;
+    create_task(&app, "Call mom").await;
+    let percent = create_task(&app, "Reach 100% coverage").await;
+    create_task(&app, "Reach 1000 stars").await;
+
+    assert_eq!(
+        search(&app, "q=milk%20buy").await,
+        [
+            (
+                bread,
+                "<mark>Buy</mark> bread and <mark>MILK</mark>".to_owned()
+            ),
+            (milk, "<mark>Buy</mark> <mark>milk</mark>".to_owned()),
+        ]
+    );
+    assert_eq!(search(&app, "q=milk&limit=1").await.len(), 1);
+    // Wildcards are plain characters.
+    let hits = search(&app, "q=100%25").await;
+    assert_eq!(
+        hits,
+        [(percent, "Reach <mark>100%</mark> coverage".to_owned())]
+    );
+    assert!(search(&app, "q=tea").await.is_empty());
+}
+
+#[tokio::test]
+async fn search_matches_every_word() {
+    assert_search_matches_every_word(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_search_matches_every_word() {
+    assert_search_matches_every_word(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn snippets_are_escaped() {
+    let app = app().await;
+    create_task(&app, "<b>milk</b> & co").await;
+
+    let hits = search(&app, "q=MILK").await;
+
+    assert_eq!(hits[0].1, "&lt;b&gt;<mark>milk</mark>&lt;/b&gt; &amp; co");
+}
+
+#[tokio::test]
+async fn search_only_finds_the_callers_tasks() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    create_task(&bob, "Buy milk").await;
+
+    assert!(search(&alice, "q=milk").await.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_search_is_rejected() {
+    let app = app().await;
+
+    for (query, field) in [("", "q"), ("q=%20", "q"), ("q=milk&limit=0", "limit")] {
+        let response = get(&app, &format!("/tasks/search?{query}")).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(response.body["errors"][0]["field"], field, "{query}");
+    }
+    let response = get(&app, &format!("/tasks/search?q={}", "a".repeat(256))).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.body["errors"],
+        Value::from(vec![
+            json!({"field": "q", "message": "must be at most 255 characters"})
+        ])
+    );
+}
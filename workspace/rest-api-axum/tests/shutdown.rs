@@ -0,0 +1,166 @@
+mod common;
+
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::Router;
+use hyper::{Body, Client};
+use rest_api_axum::config::Config;
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::shutdown;
+use rest_api_axum::state::AppState;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use common::{app_on, get, sqlite_pool, TestApp};
+
+/// Serves `router` on a free port until the returned sender fires.
+fn start(
+    router: Router,
+    timeout: Option<Duration>,
+) -> (
+    SocketAddr,
+    oneshot::Sender<()>,
+    JoinHandle<anyhow::Result<()>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let signal = async {
+        let _ = stopped.await;
+    };
+    let server = tokio::spawn(shutdown::serve(listener, router, signal, timeout));
+    (addr, stop, server)
+}
+
+/// Delays every request by half a second, as if it were slow to handle.
+async fn slowly<B>(request: Request<B>, next: Next<B>) -> Response {
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    next.run(request).await
+}
+
+/// Delays every request by a minute, far past any timeout of the tests.
+async fn stuck<B>(request: Request<B>, next: Next<B>) -> Response {
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    next.run(request).await
+}
+
+/// Sends `request` to the server at `addr` with the access `token`.
+async fn send_to(
+    addr: SocketAddr,
+    token: &str,
+    request: Request<Body>,
+) -> hyper::Result<hyper::Response<Body>> {
+    let (mut parts, body) = request.into_parts();
+    parts.uri = format!("http://{addr}{}", parts.uri).parse().unwrap();
+    parts.headers.insert(
+        header::AUTHORIZATION,
+        format!("Bearer {token}").parse().unwrap(),
+    );
+    Client::new()
+        .request(Request::from_parts(parts, body))
+        .await
+}
+
+fn create(task: &str) -> Request<Body> {
+    Request::post("/task")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json!({ "task": task }).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn slow_request_completes_across_shutdown() {
+    let pool = sqlite_pool(true).await;
+    let app = app_on(Arc::new(SqliteStore::new(pool.clone()))).await;
+    let router = app.router.clone().layer(middleware::from_fn(slowly));
+    let (addr, stop, server) = start(router, Some(Duration::from_secs(10)));
+
+    let token = app.token.clone().unwrap();
+    let request = tokio::spawn(async move { send_to(addr, &token, create("Buy milk")).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    stop.send(()).unwrap();
+
+    let response = request.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let task: Value = serde_json::from_slice(&bytes).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("the server did not stop")
+        .unwrap()
+        .unwrap();
+    // No longer accepting connections.
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+
+    // The transaction was committed, and the pool closes cleanly after it.
+    let found = get(&app, &format!("/task/{}", task["id"])).await;
+    assert_eq!(found.status, StatusCode::OK);
+    assert_eq!(found.body["task"], "Buy milk");
+    tokio::time::timeout(Duration::from_secs(5), pool.close())
+        .await
+        .expect("the pool did not close");
+    assert!(pool.is_closed());
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_on_requests_past_the_timeout() {
+    let app = app_on(Arc::new(MemoryStore::default())).await;
+    let router = app.router.clone().layer(middleware::from_fn(stuck));
+    let (addr, stop, server) = start(router, Some(Duration::from_millis(200)));
+
+    let token = app.token.clone().unwrap();
+    tokio::spawn(async move { send_to(addr, &token, create("Never done")).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    stop.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("the server did not give up")
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn closing_the_hub_ends_event_feeds() {
+    let state = AppState::new(Config::default(), Arc::new(MemoryStore::default()));
+    let events = state.events.clone();
+    let app = TestApp {
+        router: rest_api_axum::app(state),
+        token: None,
+    }
+    .register("user@example.com")
+    .await;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let signal = async move {
+        let _ = stopped.await;
+        events.close();
+    };
+    // Without a timeout, an open feed would keep the server up forever.
+    let server = tokio::spawn(shutdown::serve(listener, app.router.clone(), signal, None));
+
+    let feed = Request::get("/tasks/events").body(Body::empty()).unwrap();
+    let response = send_to(addr, app.token.as_ref().unwrap(), feed)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    stop.send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), hyper::body::to_bytes(response))
+        .await
+        .expect("the feed did not end")
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("the server did not stop")
+        .unwrap()
+        .unwrap();
+}
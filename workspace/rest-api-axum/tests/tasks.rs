@@ -0,0 +1,271 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use serde_json::json;
+
+use common::{app, create_task, delete, get, post, put, send, sqlite_app};
+
+#[tokio::test]
+async fn hello() {
+    let app = app().await;
+
+    let response = get(&app, "/hello").await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, "Hello, World!");
+}
+
+#[tokio::test]
+async fn create_and_get_task() {
+    let app = app().await;
+
+    let created = post(
+        &app,
+        "/task",
+        json!({"task": "write report", "priority": 2, "due_at": "2026-11-01T10:00:00Z"}),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED);
+    assert_eq!(created.body["status"], "todo");
+    assert_eq!(created.body["priority"], 2);
+
+    let id = created.body["id"].as_i64().unwrap();
+    let fetched = get(&app, &format!("/task/{id}")).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.body, created.body);
+    assert_eq!(fetched.headers[header::ETAG], "\"1\"");
+}
+
+#[tokio::test]
+async fn create_task_rejects_invalid_fields() {
+    let app = app().await;
+
+    let response = post(&app, "/task", json!({"task": " ", "priority": 9})).await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.headers[header::CONTENT_TYPE],
+        "application/problem+json"
+    );
+    assert_eq!(response.body["type"], "/problems/validation-error");
+    let fields: Vec<_> = response.body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["task", "priority"]);
+}
+
+#[tokio::test]
+async fn malformed_bodies_are_problems() {
+    let app = app().await;
+    let malformed = |content_type: &str, body: &'static str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/task")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap()
+    };
+
+    for (request, status, kind) in [
+        (
+            malformed("application/json", r#"{"task": "#),
+            StatusCode::BAD_REQUEST,
+            "bad-request",
+        ),
+        (
+            malformed("application/json", r#"{"task": 1}"#),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable-entity",
+        ),
+        (
+            malformed("text/plain", r#"{"task": "Buy milk"}"#),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "unsupported-media-type",
+        ),
+    ] {
+        let response = send(&app, request).await;
+
+        assert_eq!(response.status, status, "{}", response.body);
+        assert_eq!(
+            response.headers[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        assert_eq!(response.body["type"], format!("/problems/{kind}"));
+    }
+}
+
+#[tokio::test]
+async fn malformed_paths_and_queries_are_problems() {
+    let app = app().await;
+
+    for uri in ["/task/abc", "/tasks?limit=x"] {
+        let response = get(&app, uri).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{uri}");
+        assert_eq!(
+            response.headers[header::CONTENT_TYPE],
+            "application/problem+json",
+            "{uri}"
+        );
+        assert_eq!(response.body["type"], "/problems/bad-request", "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn list_tasks_paginates_and_filters() {
+    let app = app().await;
+    for task in ["alpha", "beta", "gamma", "alphabet"] {
+        create_task(&app, task).await;
+    }
+
+    let first = get(&app, "/tasks?limit=3").await;
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(first.body["total"], 4);
+    assert_eq!(first.body["items"].as_array().unwrap().len(), 3);
+    assert_eq!(first.body["next_cursor"], 3);
+
+    let second = get(&app, "/tasks?limit=3&cursor=3").await;
+    assert_eq!(second.body["items"][0]["task"], "alphabet");
+    assert_eq!(second.body["next_cursor"], json!(null));
+
+    let filtered = get(&app, "/tasks?q=ALPHA&sort=-id").await;
+    assert_eq!(filtered.body["total"], 2);
+    assert_eq!(filtered.body["items"][0]["task"], "alphabet");
+    assert_eq!(filtered.body["items"][1]["task"], "alpha");
+}
+
+#[tokio::test]
+async fn list_tasks_rejects_invalid_pagination() {
+    let app = app().await;
+
+    let response = get(&app, "/tasks?limit=0&offset=1&cursor=1").await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["errors"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn get_missing_task() {
+    let app = app().await;
+
+    let response = get(&app, "/task/42").await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.body["resource"], "task");
+    assert_eq!(response.body["id"], "42");
+}
+
+#[tokio::test]
+async fn update_task() {
+    let app = app().await;
+    let id = create_task(&app, "draft").await;
+
+    let response = put(
+        &app,
+        &format!("/task/{id}"),
+        json!({"task": "final", "status": "done"}),
+    )
+    .await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["task"], "final");
+    assert_eq!(response.body["status"], "done");
+    assert_eq!(response.body["version"], 2);
+    assert_eq!(response.headers[header::ETAG], "\"2\"");
+}
+
+#[tokio::test]
+async fn update_missing_task() {
+    let app = app().await;
+
+    let response = put(&app, "/task/42", json!({"task": "final"})).await;
+
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn update_task_with_stale_etag() {
+    let app = app().await;
+    let id = create_task(&app, "draft").await;
+    put(&app, &format!("/task/{id}"), json!({"task": "second"})).await;
+
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/task/{id}"))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::IF_MATCH, "\"1\"")
+        .body(Body::from(json!({"task": "third"}).to_string()))
+        .unwrap();
+    let response = send(&app, request).await;
+
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(
+        get(&app, &format!("/task/{id}")).await.body["task"],
+        "second"
+    );
+}
+
+#[tokio::test]
+async fn patch_task() {
+    let app = app().await;
+    let id = create_task(&app, "draft").await;
+
+    let request = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("/task/{id}"))
+        .header(header::CONTENT_TYPE, "application/merge-patch+json")
+        .header(header::IF_MATCH, "\"1\"")
+        .body(Body::from(json!({"priority": 3}).to_string()))
+        .unwrap();
+    let response = send(&app, request).await;
+
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["task"], "draft");
+    assert_eq!(response.body["priority"], 3);
+}
+
+#[tokio::test]
+async fn delete_task() {
+    let app = app().await;
+    let id = create_task(&app, "draft").await;
+
+    let response = delete(&app, &format!("/task/{id}")).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = delete(&app, &format!("/task/{id}")).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        get(&app, &format!("/task/{id}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn sqlite_backend_round_trip() {
+    let app = sqlite_app(true).await;
+    let id = create_task(&app, "draft").await;
+
+    let response = put(&app, &format!("/task/{id}"), json!({"task": "final"})).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = get(&app, "/tasks").await;
+    assert_eq!(response.body["total"], 1);
+    assert_eq!(response.body["items"][0]["task"], "final");
+}
+
+#[tokio::test]
+async fn database_errors_are_internal_server_errors() {
+    // Without migrations there is no `task` table to query.
+    let app = sqlite_app(false).await;
+
+    let response = get(&app, "/tasks").await;
+
+    assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.body["type"], "/problems/internal-server-error");
+    // The underlying error is logged, not leaked to the client.
+    assert_eq!(response.body["detail"], "an unexpected error occurred");
+}
@@ -0,0 +1,111 @@
+// Only built with `--features otel`.
+#![cfg(feature = "otel")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::{Method, StatusCode};
+use futures_util::future::BoxFuture;
+use opentelemetry::trace::{SpanId, TraceId, TracerProvider as _};
+use opentelemetry::Value;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::trace::TracerProvider;
+use serde_json::json;
+use tracing_subscriber::layer::SubscriberExt;
+
+use common::{app, request, send, TestApp};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_ID: &str = "00f067aa0ba902b7";
+
+/// Keeps the exported spans in memory.
+#[derive(Clone, Debug, Default)]
+struct Exported(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for Exported {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.0.lock().unwrap().extend(batch);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Creates a task, with the `traceparent` header if any, and returns the
+/// spans exported meanwhile.
+async fn create_traced(app: &TestApp, traceparent: Option<&str>) -> Vec<SpanData> {
+    let exported = Exported::default();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(exported.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let mut request = request(Method::POST, "/task", Some(json!({"task": "Buy milk"})));
+    if let Some(traceparent) = traceparent {
+        request
+            .headers_mut()
+            .insert("traceparent", traceparent.parse().unwrap());
+    }
+    let response = send(app, request).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+
+    for result in provider.force_flush() {
+        result.unwrap();
+    }
+    let spans = exported.0.lock().unwrap().clone();
+    spans
+}
+
+fn span<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+    spans
+        .iter()
+        .find(|span| span.name == name)
+        .unwrap_or_else(|| panic!("no {name} span"))
+}
+
+fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+    span.attributes
+        .iter()
+        .find(|attribute| attribute.key.as_str() == key)
+        .map(|attribute| attribute.value.clone())
+}
+
+#[tokio::test]
+async fn requests_continue_the_trace_of_the_client() {
+    let app = app().await;
+
+    let spans = create_traced(&app, Some(&format!("00-{TRACE_ID}-{PARENT_ID}-01"))).await;
+
+    let request = span(&spans, "request");
+    let trace_id = TraceId::from_hex(TRACE_ID).unwrap();
+    assert_eq!(request.span_context.trace_id(), trace_id);
+    assert_eq!(request.parent_span_id, SpanId::from_hex(PARENT_ID).unwrap());
+    assert_eq!(attribute(request, "route"), Some("/task".into()));
+
+    let request_id = request.span_context.span_id();
+    let acquire = span(&spans, "acquire_connection");
+    assert_eq!(acquire.span_context.trace_id(), trace_id);
+    assert_eq!(acquire.parent_span_id, request_id);
+    let query = span(&spans, "query");
+    assert_eq!(query.span_context.trace_id(), trace_id);
+    assert_eq!(query.parent_span_id, request_id);
+    assert_eq!(attribute(query, "operation"), Some("create".into()));
+    assert_eq!(attribute(query, "handler"), Some("POST /task".into()));
+}
+
+#[tokio::test]
+async fn requests_without_a_parent_start_a_trace() {
+    let app = app().await;
+
+    for traceparent in [None, Some("not a traceparent")] {
+        let spans = create_traced(&app, traceparent).await;
+
+        let request = span(&spans, "request");
+        assert_ne!(
+            request.span_context.trace_id(),
+            TraceId::from_hex(TRACE_ID).unwrap()
+        );
+        assert_eq!(request.parent_span_id, SpanId::INVALID);
+    }
+}
@@ -0,0 +1,297 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use serde_json::{json, Value};
+
+use common::{app, create_task, delete, get, open, post, send, sqlite_app, Response, TestApp};
+
+/// Exports the tasks of `app` in `format`, returning the headers and body.
+async fn export(app: &TestApp, format: &str) -> (HeaderMap, String) {
+    let response = open(
+        app,
+        Request::get(format!("/tasks/export?format={format}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    (headers, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn import(app: &TestApp, content_type: &str, body: &str) -> Response {
+    let request = Request::post("/tasks/import")
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.to_owned()))
+        .unwrap();
+    send(app, request).await
+}
+
+/// The task texts of `app`, by id.
+async fn texts(app: &TestApp) -> Vec<String> {
+    let page = get(app, "/tasks?limit=500").await;
+    page.body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["task"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+/// The lines of the rows rejected by an import.
+fn rejected_lines(response: &Response) -> Vec<u64> {
+    response.body["rejected"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row["line"].as_u64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn tasks_are_exported_in_every_format() {
+    let app = app().await;
+    let first = create_task(&app, "Buy milk, \"whole\"").await;
+    let trashed = create_task(&app, "Trashed").await;
+    delete(&app, &format!("/task/{trashed}")).await;
+    let other = app.register("other@example.com").await;
+    create_task(&other, "Not mine").await;
+    let second = create_task(&app, "Call\nmom").await;
+
+    let (headers, body) = export(&app, "json").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+    assert_eq!(
+        headers[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"tasks.json\""
+    );
+    let tasks: Vec<Value> = serde_json::from_str(&body).unwrap();
+    let ids: Vec<i64> = tasks.iter().map(|t| t["id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, [first, second]);
+    assert_eq!(tasks[0], get(&app, &format!("/task/{first}")).await.body);
+
+    let (headers, body) = export(&app, "ndjson").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "application/x-ndjson");
+    let lines: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines, tasks);
+
+    let (headers, body) = export(&app, "csv").await;
+    assert_eq!(headers[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+    let created_at = tasks[0]["created_at"].as_str().unwrap();
+    let updated_at = tasks[0]["updated_at"].as_str().unwrap();
+    assert!(body.starts_with(&format!(
+        "id,task,status,priority,due_at,created_at,updated_at,project_id,parent_id,recurrence\r\n\
+         {first},\"Buy milk, \"\"whole\"\"\",todo,0,,{created_at},{updated_at},,,\r\n\
+         {second},\"Call\nmom\",todo,0,,"
+    )));
+}
+
+#[tokio::test]
+async fn empty_exports_are_still_well_formed() {
+    let app = app().await;
+
+    assert_eq!(export(&app, "json").await.1, "[]");
+    assert_eq!(export(&app, "ndjson").await.1, "");
+    assert_eq!(export(&app, "csv").await.1.lines().count(), 1);
+}
+
+#[tokio::test]
+async fn exports_do_not_hold_a_connection_while_the_client_reads() {
+    // A pool of a single connection.
+    let app = sqlite_app(true).await;
+    create_task(&app, "Exported").await;
+
+    let download = open(
+        &app,
+        Request::get("/tasks/export?format=ndjson")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    let response = tokio::time::timeout(Duration::from_secs(5), get(&app, "/tasks"))
+        .await
+        .expect("the export kept the connection");
+    assert_eq!(response.status, StatusCode::OK);
+
+    let bytes = hyper::body::to_bytes(download.into_body()).await.unwrap();
+    assert!(String::from_utf8(bytes.to_vec())
+        .unwrap()
+        .contains("Exported"));
+}
+
+#[tokio::test]
+async fn exports_go_through_every_batch() {
+    let app = app().await;
+    let upload: String = (0..1203)
+        .map(|i| format!("{}\n", json!({ "task": format!("Task {i}") })))
+        .collect();
+    let response = import(&app, "application/x-ndjson", &upload).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["imported"], 1203);
+
+    let (_, body) = export(&app, "json").await;
+
+    let tasks: Vec<Value> = serde_json::from_str(&body).unwrap();
+    assert_eq!(tasks.len(), 1203);
+    assert!(tasks
+        .windows(2)
+        .all(|pair| pair[0]["id"].as_i64() < pair[1]["id"].as_i64()));
+    assert_eq!(tasks[1202]["task"], "Task 1202");
+}
+
+#[tokio::test]
+async fn unknown_export_formats_are_rejected() {
+    let app = app().await;
+
+    let response = get(&app, "/tasks/export?format=xml").await;
+
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn exports_can_be_imported_back() {
+    for app in [app().await, sqlite_app(true).await] {
+        let project = post(&app, "/projects", json!({"name": "Home"})).await;
+        post(
+            &app,
+            "/task",
+            json!({
+                "task": "Buy milk, \"whole\"",
+                "status": "in_progress",
+                "priority": 2,
+                "due_at": "2030-01-01T09:30:00Z",
+                "project_id": project.body["id"],
+                "recurrence": "FREQ=WEEKLY;BYDAY=MO,TH",
+            }),
+        )
+        .await;
+        create_task(&app, "Call\nmom").await;
+
+        for format in ["json", "ndjson", "csv"] {
+            let (headers, body) = export(&app, format).await;
+            let content_type = headers[header::CONTENT_TYPE].to_str().unwrap();
+            let before = texts(&app).await.len();
+
+            let response = import(&app, content_type, &body).await;
+
+            assert_eq!(response.status, StatusCode::OK, "{format}");
+            assert_eq!(response.body["imported"], before, "{format}");
+            assert_eq!(response.body["rejected"], json!([]), "{format}");
+        }
+
+        let (_, body) = export(&app, "json").await;
+        let tasks: Vec<Value> = serde_json::from_str(&body).unwrap();
+        assert_eq!(tasks.len(), 16);
+        let copy = &tasks[14];
+        assert_eq!(copy["task"], "Buy milk, \"whole\"");
+        assert_eq!(copy["status"], "in_progress");
+        assert_eq!(copy["priority"], 2);
+        assert_eq!(copy["due_at"], "2030-01-01T09:30:00Z");
+        assert_eq!(copy["project_id"], project.body["id"]);
+        assert_eq!(copy["recurrence"], "FREQ=WEEKLY;BYDAY=MO,TH");
+        assert_eq!(tasks[15]["task"], "Call\nmom");
+    }
+}
+
+#[tokio::test]
+async fn rejected_rows_are_reported_by_line() {
+    let app = app().await;
+    let csv = "task,priority,project_id\r\n\
+               Buy milk,,\r\n\
+               ,1,\r\n\
+               \"Call\n\
+               mom\",9,\r\n\
+               Water plants,1,4242\r\n\
+               too,many,fields,here\r\n\
+               Walk the dog,3,\r\n";
+
+    let response = import(&app, "text/csv", csv).await;
+
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    assert_eq!(response.body["imported"], 2);
+    assert_eq!(rejected_lines(&response), [3, 4, 6, 7]);
+    let rejected = &response.body["rejected"];
+    assert_eq!(rejected[0]["error"]["type"], "/problems/validation-error");
+    assert_eq!(rejected[1]["error"]["errors"][0]["field"], "priority");
+    // Rows are also checked against the database, like in `POST /task`.
+    assert_eq!(rejected[2]["error"]["errors"][0]["field"], "project_id");
+    assert_eq!(rejected[3]["error"]["status"], 400);
+    assert_eq!(texts(&app).await, ["Buy milk", "Walk the dog"]);
+}
+
+#[tokio::test]
+async fn rejected_lines_are_counted_in_json_uploads() {
+    let app = app().await;
+    let ndjson =
+        "{\"task\": \"Buy milk\"}\n\nnot json\n{\"task\": \"\"}\n{\"task\": \"Call mom\"}\n";
+
+    let response = import(&app, "application/x-ndjson", ndjson).await;
+
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    assert_eq!(rejected_lines(&response), [3, 4]);
+
+    let array = "[\n  {\"task\": \"Water plants\"},\n  {\"task\": \"x\", \"priority\": -1},\n  {\n    \"status\": \"done\"\n  }\n]";
+
+    let response = import(&app, "application/json; charset=utf-8", array).await;
+
+    assert_eq!(response.status, StatusCode::MULTI_STATUS);
+    assert_eq!(response.body["imported"], 1);
+    assert_eq!(rejected_lines(&response), [3, 4]);
+    assert_eq!(texts(&app).await.len(), 3);
+}
+
+#[tokio::test]
+async fn malformed_uploads_are_rejected_as_a_whole() {
+    let app = app().await;
+
+    for (content_type, body) in [
+        ("application/json", "{\"task\": \"Buy milk\"}"),
+        ("application/json", "[{\"task\": \"Buy milk\"}"),
+        (
+            "application/json",
+            "[{\"task\": \"Buy milk\"} {\"task\": \"Call mom\"}]",
+        ),
+        ("text/csv", "priority\r\n1\r\n"),
+        ("text/csv", "task\r\n\"Buy milk\r\n"),
+    ] {
+        let response = import(&app, content_type, body).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{body}");
+    }
+    assert!(texts(&app).await.is_empty());
+}
+
+#[tokio::test]
+async fn imports_need_a_known_format() {
+    let app = app().await;
+
+    let response = import(&app, "application/xml", "<task/>").await;
+
+    assert_eq!(response.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(
+        response.body["detail"],
+        "expected text/csv, application/x-ndjson or application/json"
+    );
+}
+
+#[tokio::test]
+async fn imports_and_exports_need_credentials() {
+    let app = app().await.anonymous();
+
+    assert_eq!(
+        get(&app, "/tasks/export").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        import(&app, "text/csv", "task\r\nBuy milk\r\n")
+            .await
+            .status,
+        StatusCode::UNAUTHORIZED
+    );
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::{header, Method, StatusCode};
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::trash;
+use serde_json::json;
+
+use common::{
+    app, app_on, create_task, delete, get, post, put, request, send, sqlite_app, sqlite_pool,
+    TestApp,
+};
+
+/// The ids of the tasks listed at `uri`.
+async fn ids(app: &TestApp, uri: &str) -> Vec<i64> {
+    let response = get(app, uri).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["id"].as_i64().unwrap())
+        .collect()
+}
+
+async fn restore(app: &TestApp, id: i64) -> common::Response {
+    let uri = format!("/task/{id}/restore");
+    send(app, request(Method::POST, &uri, None)).await
+}
+
+async fn assert_delete_moves_to_the_trash(app: TestApp) {
+    let kept = create_task(&app, "Keep me").await;
+    let id = create_task(&app, "Delete me").await;
+
+    assert_eq!(
+        delete(&app, &format!("/task/{id}")).await.status,
+        StatusCode::OK
+    );
+
+    assert_eq!(
+        get(&app, &format!("/task/{id}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(ids(&app, "/tasks").await, [kept]);
+    let trash = get(&app, "/tasks/trash").await;
+    assert_eq!(trash.body["total"], 1);
+    assert_eq!(trash.body["items"][0]["id"], id);
+    assert!(trash.body["items"][0]["deleted_at"].is_string());
+
+    let response = restore(&app, id).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body["deleted_at"].is_null());
+    assert_eq!(response.body["version"], 3);
+    assert_eq!(response.headers[header::ETAG], "\"3\"");
+    assert_eq!(ids(&app, "/tasks").await, [kept, id]);
+    assert!(ids(&app, "/tasks/trash").await.is_empty());
+    assert_eq!(restore(&app, id).await.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn delete_moves_to_the_trash() {
+    assert_delete_moves_to_the_trash(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_delete_moves_to_the_trash() {
+    assert_delete_moves_to_the_trash(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn trashed_tasks_cannot_be_changed() {
+    let app = app().await;
+    let id = create_task(&app, "Delete me").await;
+    delete(&app, &format!("/task/{id}")).await;
+
+    let response = put(&app, &format!("/task/{id}"), json!({"task": "Changed"})).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        delete(&app, &format!("/task/{id}")).await.status,
+        StatusCode::NOT_FOUND
+    );
+    assert!(get(&app, "/tasks/search?q=delete").await.body["results"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn purge_deletes_for_good() {
+    let app = app().await;
+    let live = create_task(&app, "Purge me").await;
+    let trashed = create_task(&app, "Trash me first").await;
+    delete(&app, &format!("/task/{trashed}")).await;
+
+    for id in [live, trashed] {
+        let uri = format!("/task/{id}?purge=true");
+        assert_eq!(delete(&app, &uri).await.status, StatusCode::OK);
+        assert_eq!(delete(&app, &uri).await.status, StatusCode::NOT_FOUND);
+        assert_eq!(restore(&app, id).await.status, StatusCode::NOT_FOUND);
+    }
+    assert!(ids(&app, "/tasks").await.is_empty());
+    assert!(ids(&app, "/tasks/trash").await.is_empty());
+}
+
+async fn assert_sorted_pages_survive_the_trash_not_purges(app: TestApp) {
+    for task in ["d", "c", "b", "a"] {
+        create_task(&app, task).await;
+    }
+    let first = get(&app, "/tasks?sort=task&limit=2").await;
+    let cursor = first.body["next_cursor"].as_i64().unwrap();
+    let uri = format!("/tasks?sort=task&limit=2&cursor={cursor}");
+
+    delete(&app, &format!("/task/{cursor}")).await;
+    assert_eq!(ids(&app, &uri).await, [2, 1]);
+
+    delete(&app, &format!("/task/{cursor}?purge=true")).await;
+    let response = get(&app, &uri).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["errors"][0]["field"], "cursor");
+}
+
+#[tokio::test]
+async fn sorted_pages_survive_the_trash_not_purges() {
+    assert_sorted_pages_survive_the_trash_not_purges(app().await).await;
+}
+
+#[tokio::test]
+async fn sqlite_sorted_pages_survive_the_trash_not_purges() {
+    assert_sorted_pages_survive_the_trash_not_purges(sqlite_app(true).await).await;
+}
+
+#[tokio::test]
+async fn trash_only_has_the_callers_tasks() {
+    let alice = app().await;
+    let bob = alice.register("bob@example.com").await;
+    let id = create_task(&bob, "Not for alice").await;
+    delete(&bob, &format!("/task/{id}")).await;
+
+    assert!(ids(&alice, "/tasks/trash").await.is_empty());
+    assert_eq!(restore(&alice, id).await.status, StatusCode::NOT_FOUND);
+    let response = delete(&alice, &format!("/task/{id}?purge=true")).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+async fn assert_expired_trash_is_purged(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    let kept = create_task(&app, "Keep me").await;
+    let id = create_task(&app, "Delete me").await;
+    delete(&app, &format!("/task/{id}")).await;
+
+    let hour = Duration::from_secs(60 * 60);
+    assert_eq!(trash::purge_expired(&*store, hour).await.unwrap(), 0);
+    assert_eq!(ids(&app, "/tasks/trash").await, [id]);
+
+    assert_eq!(
+        trash::purge_expired(&*store, Duration::ZERO).await.unwrap(),
+        1
+    );
+    assert!(ids(&app, "/tasks/trash").await.is_empty());
+    assert_eq!(ids(&app, "/tasks").await, [kept]);
+}
+
+#[tokio::test]
+async fn expired_trash_is_purged() {
+    assert_expired_trash_is_purged(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_expired_trash_is_purged() {
+    let store = SqliteStore::new(sqlite_pool(true).await);
+    assert_expired_trash_is_purged(Arc::new(store)).await;
+}
+
+#[tokio::test]
+async fn bulk_deletes_move_to_the_trash() {
+    let app = app().await;
+    let id = create_task(&app, "Delete me").await;
+
+    let body = json!({"operations": [{"op": "delete", "id": id}]});
+    let response = post(&app, "/tasks/bulk", body).await;
+
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert_eq!(ids(&app, "/tasks/trash").await, [id]);
+}
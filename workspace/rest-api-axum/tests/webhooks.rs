@@ -0,0 +1,540 @@
+mod common;
+
+use std::collections::VecDeque;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post as route_post;
+use axum::Router;
+use rest_api_axum::config::Config;
+use rest_api_axum::repository::memory::MemoryStore;
+use rest_api_axum::repository::sqlite::SqliteStore;
+use rest_api_axum::repository::Store;
+use rest_api_axum::shutdown::Workers;
+use rest_api_axum::webhooks::{self, Settings, Worker};
+use serde_json::{json, Value};
+
+use common::{app_with, create_task, delete, get, post, put, sqlite_pool, TestApp};
+
+/// A local HTTP server standing for a webhook, recording what it is sent.
+#[derive(Clone, Default)]
+struct Receiver {
+    received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+    /// The statuses of the next responses, 200 once there are none left.
+    statuses: Arc<Mutex<VecDeque<StatusCode>>>,
+}
+
+impl Receiver {
+    /// Starts receiving, returning the URL to register.
+    fn start(&self) -> String {
+        async fn receive(
+            State(receiver): State<Receiver>,
+            headers: HeaderMap,
+            body: Bytes,
+        ) -> StatusCode {
+            receiver.received.lock().unwrap().push((headers, body));
+            let next = receiver.statuses.lock().unwrap().pop_front();
+            next.unwrap_or(StatusCode::OK)
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/hook", route_post(receive))
+            .with_state(self.clone());
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+        format!("http://{addr}/hook")
+    }
+
+    fn respond_with(&self, statuses: &[StatusCode]) {
+        self.statuses.lock().unwrap().extend(statuses);
+    }
+
+    /// The headers and JSON bodies received so far.
+    fn received(&self) -> Vec<(HeaderMap, Value)> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(headers, body)| (headers.clone(), serde_json::from_slice(body).unwrap()))
+            .collect()
+    }
+}
+
+/// The hosts of the local receivers, which are refused otherwise.
+fn allowed_hosts() -> Vec<String> {
+    vec!["127.0.0.1".to_owned(), "localhost".to_owned()]
+}
+
+fn settings() -> Settings {
+    Settings {
+        interval: Duration::from_secs(3600),
+        timeout: Duration::from_secs(5),
+        max_attempts: 3,
+        backoff: Duration::from_millis(100),
+        retention: None,
+        allowed_hosts: allowed_hosts(),
+    }
+}
+
+async fn app() -> TestApp {
+    app_on(Arc::new(MemoryStore::default())).await
+}
+
+async fn app_on(store: Arc<dyn Store>) -> TestApp {
+    let config = Config {
+        webhook_allowed_hosts: allowed_hosts(),
+        ..Config::default()
+    };
+    app_with(config, store).await
+}
+
+async fn deliveries(app: &TestApp, webhook: &Value) -> Vec<Value> {
+    let response = get(app, &format!("/webhooks/{}/deliveries", webhook["id"])).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    response.body["items"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn webhooks_can_be_created_listed_and_deleted() {
+    let app = app().await;
+
+    let created = post(
+        &app,
+        "/webhooks",
+        json!({"url": "https://localhost/hooks", "events": ["updated", "created", "updated"]}),
+    )
+    .await;
+    assert_eq!(created.status, StatusCode::CREATED, "{}", created.body);
+    assert_eq!(created.body["url"], "https://localhost/hooks");
+    assert_eq!(created.body["events"], json!(["updated", "created"]));
+    assert_eq!(created.body["secret"].as_str().unwrap().len(), 64);
+    let every = post(&app, "/webhooks", json!({"url": "http://localhost:8080/"})).await;
+    assert_eq!(
+        every.body["events"],
+        json!(["created", "updated", "deleted"])
+    );
+
+    let listed = get(&app, "/webhooks").await;
+    assert_eq!(listed.body.as_array().unwrap().len(), 2);
+    assert_eq!(listed.body[0]["id"], created.body["id"]);
+    // The secret is only ever returned on creation.
+    assert!(listed.body[0].get("secret").is_none());
+
+    let other = app.register("other@example.com").await;
+    let uri = format!("/webhooks/{}", created.body["id"]);
+    assert_eq!(delete(&other, &uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(get(&other, "/webhooks").await.body, json!([]));
+    assert_eq!(delete(&app, &uri).await.status, StatusCode::OK);
+    assert_eq!(delete(&app, &uri).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        get(&app, "/webhooks").await.body.as_array().unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn invalid_webhooks_are_rejected() {
+    let app = app().await;
+
+    for (body, field) in [
+        (json!({"url": "example.com/hooks"}), "url"),
+        (json!({"url": "ftp://example.com/hooks"}), "url"),
+        (
+            json!({"url": format!("https://example.com/{}", "a".repeat(2048))}),
+            "url",
+        ),
+        (
+            json!({"url": "https://example.com", "events": []}),
+            "events",
+        ),
+    ] {
+        let response = post(&app, "/webhooks", body.clone()).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{body}");
+        assert_eq!(response.body["errors"][0]["field"], field, "{body}");
+    }
+    assert_eq!(get(&app, "/webhooks").await.body, json!([]));
+}
+
+#[tokio::test]
+async fn webhooks_on_the_local_network_are_refused() {
+    let app = common::app().await;
+
+    for url in [
+        "http://localhost:8080/hooks",
+        "http://127.0.0.1/",
+        "http://0.0.0.0/",
+        "http://10.0.0.1/",
+        "http://192.168.1.1:8080/",
+        "http://169.254.169.254/latest/meta-data/",
+        "http://[::1]/",
+        "http://[::ffff:10.0.0.1]/",
+        "http://[fe80::1]/",
+    ] {
+        let response = post(&app, "/webhooks", json!({"url": url})).await;
+
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{url}");
+        assert_eq!(response.body["errors"][0]["field"], "url", "{url}");
+    }
+    assert_eq!(get(&app, "/webhooks").await.body, json!([]));
+}
+
+#[tokio::test]
+async fn deliveries_to_the_local_network_are_refused() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let receiver = Receiver::default();
+    let url = receiver.start();
+    let by_name = url.replace("127.0.0.1", "localhost");
+    let webhooks = [
+        post(&app, "/webhooks", json!({"url": url})).await.body,
+        post(&app, "/webhooks", json!({"url": by_name})).await.body,
+    ];
+    create_task(&app, "Internal").await;
+    // Since allowed when the webhooks were created, but no longer.
+    let settings = Settings {
+        allowed_hosts: Vec::new(),
+        ..settings()
+    };
+
+    Worker::new(store, settings).deliver_due().await.unwrap();
+
+    assert!(receiver.received().is_empty());
+    for webhook in &webhooks {
+        let delivery = &deliveries(&app, webhook).await[0];
+        assert_eq!(delivery["status"], "pending");
+        let error = delivery["last_error"].as_str().unwrap();
+        assert!(error.contains("not a public address"), "{error}");
+    }
+}
+
+async fn assert_events_are_delivered_signed(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    let receiver = Receiver::default();
+    let webhook = post(
+        &app,
+        "/webhooks",
+        json!({"url": receiver.start(), "events": ["created", "deleted"]}),
+    )
+    .await
+    .body;
+    let secret = webhook["secret"].as_str().unwrap();
+    let other = app.register("other@example.com").await;
+    create_task(&other, "Not theirs").await;
+
+    let id = create_task(&app, "Write report").await;
+    put(
+        &app,
+        &format!("/task/{id}"),
+        json!({"task": "Write the report"}),
+    )
+    .await;
+    delete(&app, &format!("/task/{id}")).await;
+    let worker = Worker::new(store, settings());
+    assert_eq!(worker.deliver_due().await.unwrap(), 2);
+    assert_eq!(worker.deliver_due().await.unwrap(), 0);
+
+    let received = receiver.received();
+    assert_eq!(received.len(), 2);
+    let (headers, event) = &received[0];
+    assert_eq!(event["kind"], "created");
+    assert_eq!(event["task_id"], id);
+    assert_eq!(event["task"]["task"], "Write report");
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers[webhooks::X_WEBHOOK_EVENT], "created");
+    let timestamp: i64 = headers[webhooks::X_WEBHOOK_TIMESTAMP]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let body = receiver.received.lock().unwrap()[0].1.clone();
+    assert_eq!(
+        headers[webhooks::X_WEBHOOK_SIGNATURE],
+        webhooks::sign(secret, timestamp, &body).as_str()
+    );
+    assert_eq!(received[1].1["kind"], "deleted");
+    assert!(received[1].1["task"].is_null());
+
+    let deliveries = deliveries(&app, &webhook).await;
+    assert_eq!(deliveries.len(), 2);
+    // Newest first.
+    assert_eq!(
+        received[1].0[webhooks::X_WEBHOOK_DELIVERY],
+        deliveries[0]["id"].to_string().as_str()
+    );
+    assert_eq!(deliveries[1]["status"], "delivered");
+    assert_eq!(deliveries[1]["attempts"], 1);
+    assert_eq!(deliveries[1]["response_status"], 200);
+    assert_eq!(deliveries[1]["event_id"], event["id"]);
+    assert!(deliveries[1]["delivered_at"].is_string());
+}
+
+#[tokio::test]
+async fn events_are_delivered_signed() {
+    assert_events_are_delivered_signed(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_events_are_delivered_signed() {
+    assert_events_are_delivered_signed(Arc::new(SqliteStore::new(sqlite_pool(true).await))).await;
+}
+
+async fn assert_failed_deliveries_are_retried_until_dead(store: Arc<dyn Store>) {
+    let app = app_on(store.clone()).await;
+    let receiver = Receiver::default();
+    receiver.respond_with(&[StatusCode::INTERNAL_SERVER_ERROR; 3]);
+    let webhook = post(&app, "/webhooks", json!({"url": receiver.start()}))
+        .await
+        .body;
+    create_task(&app, "Flaky").await;
+    let worker = Worker::new(store, settings());
+
+    assert_eq!(worker.deliver_due().await.unwrap(), 1);
+    let delivery = &deliveries(&app, &webhook).await[0];
+    assert_eq!(delivery["status"], "pending");
+    assert_eq!(delivery["attempts"], 1);
+    assert_eq!(delivery["response_status"], 500);
+    assert_eq!(
+        delivery["last_error"],
+        "responded with 500 Internal Server Error"
+    );
+    // Not due before the backoff.
+    assert_eq!(worker.deliver_due().await.unwrap(), 0);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(worker.deliver_due().await.unwrap(), 1);
+    // Twice as long after the second attempt.
+    assert_eq!(worker.deliver_due().await.unwrap(), 0);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(worker.deliver_due().await.unwrap(), 1);
+
+    let dead = get(
+        &app,
+        &format!("/webhooks/{}/deliveries?status=dead", webhook["id"]),
+    )
+    .await
+    .body["items"]
+        .clone();
+    assert_eq!(dead[0]["attempts"], 3);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(worker.deliver_due().await.unwrap(), 0);
+    assert_eq!(receiver.received().len(), 3);
+
+    let redelivered = post(
+        &app,
+        &format!(
+            "/webhooks/{}/deliveries/{}/redeliver",
+            webhook["id"], dead[0]["id"]
+        ),
+        json!({}),
+    )
+    .await;
+    assert_eq!(redelivered.status, StatusCode::ACCEPTED);
+    assert_eq!(redelivered.body["status"], "pending");
+    assert_eq!(redelivered.body["attempts"], 0);
+    assert_eq!(worker.deliver_due().await.unwrap(), 1);
+    let delivery = &deliveries(&app, &webhook).await[0];
+    assert_eq!(delivery["status"], "delivered");
+    assert_eq!(receiver.received().len(), 4);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_until_dead() {
+    assert_failed_deliveries_are_retried_until_dead(Arc::new(MemoryStore::default())).await;
+}
+
+#[tokio::test]
+async fn sqlite_failed_deliveries_are_retried_until_dead() {
+    assert_failed_deliveries_are_retried_until_dead(Arc::new(SqliteStore::new(
+        sqlite_pool(true).await,
+    )))
+    .await;
+}
+
+#[tokio::test]
+async fn unreachable_webhooks_are_retried() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    // Nothing listens on a port just let go of.
+    let addr: SocketAddr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let webhook = post(&app, "/webhooks", json!({"url": format!("http://{addr}/")}))
+        .await
+        .body;
+    create_task(&app, "Lost").await;
+
+    Worker::new(store, settings()).deliver_due().await.unwrap();
+
+    let delivery = &deliveries(&app, &webhook).await[0];
+    assert_eq!(delivery["status"], "pending");
+    assert!(delivery["response_status"].is_null());
+    assert!(delivery["last_error"].is_string());
+}
+
+#[tokio::test]
+async fn only_deliveries_of_the_caller_can_be_seen_and_redelivered() {
+    let app = app().await;
+    let webhook = post(&app, "/webhooks", json!({"url": "http://localhost:1/"}))
+        .await
+        .body;
+    create_task(&app, "Mine").await;
+    let delivery = &deliveries(&app, &webhook).await[0];
+    let other = app.register("other@example.com").await;
+    let uri = format!("/webhooks/{}/deliveries", webhook["id"]);
+
+    assert_eq!(get(&other, &uri).await.status, StatusCode::NOT_FOUND);
+    let redeliver = format!("{uri}/{}/redeliver", delivery["id"]);
+    let response = post(&other, &redeliver, json!({})).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let response = post(&app, "/webhooks/1/deliveries/999/redeliver", json!({})).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let response = get(&app, &format!("{uri}?limit=0")).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn deliveries_are_paged() {
+    let app = app().await;
+    let webhook = post(&app, "/webhooks", json!({"url": "http://localhost:1/"}))
+        .await
+        .body;
+    for task in ["one", "two", "three"] {
+        create_task(&app, task).await;
+    }
+    let uri = format!("/webhooks/{}/deliveries?limit=2", webhook["id"]);
+
+    let first = get(&app, &uri).await.body;
+    assert_eq!(first["items"].as_array().unwrap().len(), 2);
+    let cursor = &first["next_cursor"];
+    let second = get(&app, &format!("{uri}&cursor={cursor}")).await.body;
+    assert_eq!(second["items"].as_array().unwrap().len(), 1);
+    assert!(second["next_cursor"].is_null());
+    assert_eq!(second["items"][0]["task_id"], 1);
+}
+
+#[tokio::test]
+async fn sqlite_changes_rolled_back_are_not_delivered() {
+    let store = Arc::new(SqliteStore::new(sqlite_pool(true).await));
+    let app = app_on(store.clone()).await;
+    let webhook = post(&app, "/webhooks", json!({"url": "http://localhost:1/"}))
+        .await
+        .body;
+
+    let response = post(
+        &app,
+        "/tasks/bulk",
+        json!({"mode": "atomic", "operations": [
+            {"op": "create", "task": {"task": "new"}},
+            {"op": "delete", "id": 999},
+        ]}),
+    )
+    .await;
+
+    assert_eq!(response.body["committed"], false);
+    assert!(deliveries(&app, &webhook).await.is_empty());
+}
+
+#[tokio::test]
+async fn concurrent_workers_send_each_delivery_once() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let receiver = Receiver::default();
+    post(&app, "/webhooks", json!({"url": receiver.start()})).await;
+    for i in 0..50 {
+        create_task(&app, &format!("Task {i}")).await;
+    }
+
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let worker = Worker::new(store.clone(), settings());
+            tokio::spawn(async move { worker.deliver_due().await.unwrap() })
+        })
+        .collect();
+    let mut sent = 0;
+    for worker in workers {
+        sent += worker.await.unwrap();
+    }
+
+    assert_eq!(sent, 50);
+    assert_eq!(receiver.received().len(), 50);
+}
+
+#[tokio::test]
+async fn the_worker_wakes_up_when_tasks_change() {
+    let store = Arc::new(MemoryStore::default());
+    let app = app_on(store.clone()).await;
+    let receiver = Receiver::default();
+    post(&app, "/webhooks", json!({"url": receiver.start()})).await;
+    // Looks for retries far less often than the test may take.
+    let mut workers = Workers::new();
+    workers.push(Worker::new(store, settings()).start(workers.stop_signal()));
+
+    create_task(&app, "Ping").await;
+
+    let received = async {
+        while receiver.received().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), received)
+        .await
+        .expect("nothing was delivered");
+    tokio::time::timeout(Duration::from_secs(5), workers.stop())
+        .await
+        .expect("the worker did not stop");
+}
+
+#[test]
+fn only_public_addresses_are_public() {
+    for ip in [
+        "93.184.216.34",
+        "2606:2800:220:1::1",
+        "::ffff:93.184.216.34",
+    ] {
+        assert!(webhooks::is_public(ip.parse().unwrap()), "{ip}");
+    }
+    for ip in [
+        "0.0.0.0",
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.0.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!webhooks::is_public(ip.parse().unwrap()), "{ip}");
+    }
+}
+
+#[test]
+fn retries_back_off_exponentially() {
+    let settings = Settings {
+        backoff: Duration::from_secs(30),
+        ..settings()
+    };
+
+    let delays: Vec<_> = [1, 2, 3, 4, 20]
+        .map(|attempts| settings.delay(attempts))
+        .into();
+
+    assert_eq!(
+        delays,
+        [30, 60, 120, 240, 24 * 60 * 60].map(Duration::from_secs)
+    );
+}
@@ -24,7 +24,7 @@ use std::iter::FromIterator;
 
 const BLOCK_SIZE: f64 = 20.0;
 
-const BG_COLOR: [f32; 4] = [0.1568627451, 0.1725490196, 0.1725490196, 1.0];
+const BG_COLOR: [f32; 4] = [0.156_862_75, 0.172_549_02, 0.172_549_02, 1.0];
 const SNAKE_COLOR: [f32; 4] = [0.644, 0.776, 0.516, 1.0];
 const FOOD_COLOR: [f32; 4] = [0.928, 0.408, 0.452, 1.0];
 const TEXT_COLOR: [f32; 4] = [0.996, 0.996, 1.0, 1.0];
@@ -178,7 +178,7 @@ impl Game {
             // restart game
             self.running = true;
             self.snake = Snake {
-                body: LinkedList::from_iter((vec![(0, 0), (0, 1)]).into_iter()),
+                body: LinkedList::from_iter(vec![(0, 0), (0, 1)]),
                 dir: Direction::Right,
             };
             return;
@@ -186,11 +186,11 @@ impl Game {
 
         let last_direction = self.snake.dir.clone();
 
-        self.snake.dir = match btn {
-            &Button::Keyboard(Key::Up) if last_direction != Direction::Down => Direction::Up,
-            &Button::Keyboard(Key::Down) if last_direction != Direction::Up => Direction::Down,
-            &Button::Keyboard(Key::Left) if last_direction != Direction::Right => Direction::Left,
-            &Button::Keyboard(Key::Right) if last_direction != Direction::Left => Direction::Right,
+        self.snake.dir = match *btn {
+            Button::Keyboard(Key::Up) if last_direction != Direction::Down => Direction::Up,
+            Button::Keyboard(Key::Down) if last_direction != Direction::Up => Direction::Down,
+            Button::Keyboard(Key::Left) if last_direction != Direction::Right => Direction::Left,
+            Button::Keyboard(Key::Right) if last_direction != Direction::Left => Direction::Right,
             _ => last_direction,
         }
     }
@@ -255,7 +255,7 @@ impl Snake {
     }
 
     fn update(&mut self, food_at: (i32, i32)) -> Result<bool, SnakeUpdateError> {
-        let mut new_head = (*self.body.front().expect("Snake has no body")).clone();
+        let mut new_head = *self.body.front().expect("Snake has no body");
 
         match self.dir {
             Direction::Left => new_head.0 -= 1,
@@ -310,7 +310,7 @@ fn main() {
         gl: GlGraphics::new(opengl),
         food: Food { at: (5, 5) },
         snake: Snake {
-            body: LinkedList::from_iter((vec![(0, 0), (0, 1)]).into_iter()),
+            body: LinkedList::from_iter(vec![(0, 0), (0, 1)]),
             dir: Direction::Right,
         },
     };
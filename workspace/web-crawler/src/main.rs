@@ -14,6 +14,7 @@ use scraper::{Html, Selector};
 use std::{thread, time};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 enum DownloadError {
     GetPageRequestFailure,
     GetPageBodyFailure,